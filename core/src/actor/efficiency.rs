@@ -0,0 +1,291 @@
+use super::*;
+use crate::hand::*;
+
+pub struct EfficiencyBotBuilder;
+
+impl ActorBuilder for EfficiencyBotBuilder {
+    fn get_default_config(&self) -> Config {
+        Config {
+            name: "EfficiencyBot".to_string(),
+            args: vec![
+                Arg::bool("riichi", true),
+                Arg::bool("yakuhai_pon", true),
+                Arg::bool("fold", false),
+                Arg::int("push_point", 2000),
+            ],
+        }
+    }
+
+    fn create(&self, config: Config) -> Box<dyn Actor> {
+        Box::new(EfficiencyBot::from_config(config))
+    }
+}
+
+// 牌効率Bot
+// 向聴数が最小かつ有効牌の残り枚数が最大となる牌を打牌する
+// args:
+//   riichi: 聴牌した場合にリーチを宣言
+//   yakuhai_pon: 役牌をポン (ベタオリ中は行わない)
+//   fold: 他家のリーチに対してベタオリ
+//   push_point: ベタオリせずに押す場合の聴牌時の和了点の下限
+#[derive(Clone)]
+pub struct EfficiencyBot {
+    config: Config,
+    seat: Seat,
+    riichi: bool,
+    yakuhai_pon: bool,
+    fold: bool,
+    push_point: Point,
+}
+
+impl EfficiencyBot {
+    pub fn from_config(config: Config) -> Self {
+        let args = &config.args;
+        Self {
+            riichi: args[0].value.as_bool(),
+            yakuhai_pon: args[1].value.as_bool(),
+            fold: args[2].value.as_bool(),
+            push_point: args[3].value.as_int(),
+            config: config,
+            seat: NO_SEAT,
+        }
+    }
+
+    fn select_action_turn(&self, stage: &Stage, acts: &[Action]) -> Action {
+        for act in acts {
+            match act.0 {
+                ActionType::Tsumo | ActionType::Kita => return act.clone(),
+                _ => {}
+            }
+        }
+
+        // 鳴き後の喰い替えで捨てられない牌
+        let prohibited = match acts.iter().find(|a| a.0 == ActionType::Discard) {
+            Some(Action(_, v)) => v.clone(),
+            None => return Action::nop(), // リーチ中
+        };
+
+        let pl = &stage.players[self.seat];
+        let unseen = calc_unseen_tile_table(stage, self.seat);
+        // 打牌候補 (向聴数, 有効牌の枚数, 牌の価値, 打牌, 有効牌)
+        let mut cands = vec![];
+        for (t, shanten, tiles) in calc_discards_with_effective_tiles(&pl.hand) {
+            if prohibited.contains(&t) {
+                continue;
+            }
+            let n = count_effective_tiles(&tiles, &unseen);
+            cands.push((shanten, n, tile_value(stage, self.seat, t), t, tiles));
+        }
+        cands.sort_by_key(|c| (c.0, std::cmp::Reverse(c.1), c.2));
+
+        let (shanten, _, _, mut t, ref tiles) = cands[0];
+        if self.fold && self.is_threatened(stage) {
+            // 聴牌かつ和了時の点数が十分な場合以外はベタオリ
+            if shanten != 0 || self.calc_win_point(stage, t, tiles) < self.push_point {
+                // 放銃率が最小の牌を打牌 (同じ場合は牌効率を優先)
+                let dt = estimate_danger_all(stage, self.seat);
+                let mut min = f32::MAX;
+                for c in &cands {
+                    let d = dt[c.3 .0][c.3 .1];
+                    if d < min {
+                        min = d;
+                        t = c.3;
+                    }
+                }
+                return Action::discard(self.select_red5(stage, t));
+            }
+        }
+
+        let t = self.select_red5(stage, t);
+        if self.riichi && shanten == 0 && acts.contains(&Action::riichi(t)) {
+            return Action::riichi(t);
+        }
+        Action::discard(t)
+    }
+
+    // 赤5と通常の5がある場合は通常の5を打牌
+    fn select_red5(&self, stage: &Stage, t: Tile) -> Tile {
+        if t.1 == 5 && stage.players[self.seat].count_tile(t) == 0 {
+            Tile(t.0, 0)
+        } else {
+            t
+        }
+    }
+
+    // 他家のリーチ
+    fn is_threatened(&self, stage: &Stage) -> bool {
+        (0..stage.get_seat_count()).any(|s| s != self.seat && stage.players[s].is_riichi)
+    }
+
+    // 牌tを打牌して聴牌した場合に和了牌(wins)で和了した場合の最大の点数 (リーチ, ロン和了を想定)
+    fn calc_win_point(&self, stage: &Stage, t: Tile, wins: &[Tile]) -> Point {
+        let pl = &stage.players[self.seat];
+        let mut hand = pl.hand;
+        hand[t.0][t.1] -= 1;
+        if t.1 == 5 && hand[t.0][5] < hand[t.0][0] {
+            hand[t.0][0] -= 1;
+        }
+
+        let yf = YakuFlags {
+            riichi: pl.is_menzen && self.riichi,
+            ..Default::default()
+        };
+        let mut point = 0;
+        for &w in wins {
+            hand[w.0][w.1] += 1;
+            if let Some(ctx) = evaluate_hand(
+                &hand,
+                &pl.melds,
+                pl.kitas.len(),
+                &stage.doras,
+                &vec![],
+                w,
+                false,
+                stage.is_dealer(self.seat),
+                stage.get_prevalent_wind(),
+                stage.get_seat_wind(self.seat),
+                &yf,
+                &stage.rule,
+            ) {
+                point = std::cmp::max(point, ctx.points.0);
+            }
+            hand[w.0][w.1] -= 1;
+        }
+        point
+    }
+
+    fn select_action_call(&self, stage: &Stage, acts: &[Action]) -> Action {
+        if acts.contains(&Action::ron()) {
+            return Action::ron();
+        }
+
+        if self.yakuhai_pon && !(self.fold && self.is_threatened(stage)) {
+            let t = stage.last_tile.unwrap().2;
+            if is_yakuhai(stage, self.seat, t) {
+                if let Some(act) = acts.iter().find(|a| a.0 == ActionType::Pon) {
+                    return act.clone();
+                }
+            }
+        }
+
+        Action::nop()
+    }
+}
+
+impl Actor for EfficiencyBot {
+    fn init(&mut self, seat: Seat) {
+        self.seat = seat;
+    }
+
+    fn select_action(&mut self, stage: &Stage, acts: &Vec<Action>) -> Action {
+        if stage.turn == self.seat {
+            self.select_action_turn(stage, acts)
+        } else {
+            self.select_action_call(stage, acts)
+        }
+    }
+
+    fn get_config(&self) -> &Config {
+        &self.config
+    }
+}
+
+impl Listener for EfficiencyBot {}
+
+// 役牌 (三元牌, 場風, 自風)
+fn is_yakuhai(stage: &Stage, seat: Seat, t: Tile) -> bool {
+    t.is_doragon()
+        || t == Tile(TZ, stage.get_prevalent_wind())
+        || t == Tile(TZ, stage.get_seat_wind(seat))
+}
+
+// 同じ評価の打牌候補が複数ある場合の優先度 (小さい方から打牌)
+// 客風牌 < 役牌 < 老頭牌 < 中張牌
+fn tile_value(stage: &Stage, seat: Seat, t: Tile) -> usize {
+    if t.is_hornor() {
+        if is_yakuhai(stage, seat, t) {
+            1
+        } else {
+            0
+        }
+    } else if t.is_terminal() {
+        2
+    } else {
+        3
+    }
+}
+
+#[test]
+fn test_efficiency_bot() {
+    use crate::actor::create_actor;
+    use crate::controller::{calc_possible_turn_actions, create_test_controller};
+
+    // 座席0: 123m 34m 456p 789s 11s + ツモ牌t
+    let create_stage = |t: &str| {
+        let hand = format!("m12334p456s11789{}", t);
+        let ctrl = create_test_controller(EventNew::from_hands(1, [&hand, "", "", ""]));
+        ctrl.get_stage().clone()
+    };
+    let select = |exp: &str, stg: &Stage| {
+        let mut bot = create_actor(exp);
+        bot.init(0);
+        bot.select_action(stg, &calc_possible_turn_actions(stg, &None))
+    };
+
+    // 孤立牌(9p)を打牌して聴牌 (2m5m待ち)
+    let stg = create_stage("p9");
+    assert_eq!(select("EfficiencyBot", &stg), Action::riichi(Tile(TP, 9)));
+    assert_eq!(
+        select("EfficiencyBot(false)", &stg),
+        Action::discard(Tile(TP, 9))
+    );
+
+    // 和了牌をツモした場合はツモ和了
+    let stg = create_stage("m5");
+    assert_eq!(select("EfficiencyBot", &stg), Action::tsumo());
+}
+
+#[test]
+fn test_efficiency_bot_fold() {
+    use crate::actor::create_actor;
+    use crate::controller::{calc_possible_turn_actions, create_test_controller};
+
+    // 座席0: 123m 34m 456p 789s 11s + W (Wを打牌して聴牌)
+    // 座席1: 5pでツモ切りリーチ
+    let hands = ["m12334p456s11789z4", "", "", ""];
+    let mut ctrl = create_test_controller(EventNew::from_hands(1, hands));
+    for e in [
+        Event::discard(0, Tile(TZ, WN), true, false),
+        Event::deal(1, Z8),
+        Event::discard(1, Tile(TP, 5), true, true),
+        Event::deal(2, Z8),
+        Event::discard(2, Tile(TZ, DR), true, false),
+        Event::deal(3, Z8),
+        Event::discard(3, Tile(TZ, DW), true, false),
+        Event::deal(0, Tile(TZ, WW)),
+    ] {
+        ctrl.handle_event(&e);
+    }
+    let stg = ctrl.get_stage();
+    let select = |exp: &str| {
+        let mut bot = create_actor(exp);
+        bot.init(0);
+        bot.select_action(stg, &calc_possible_turn_actions(stg, &None))
+    };
+
+    // ベタオリなし: 牌効率に従ってWを打牌
+    assert_eq!(
+        select("EfficiencyBot(false,true,false)"),
+        Action::discard(Tile(TZ, WW))
+    );
+    // 聴牌時の和了点がpush_point以上: 押してWを打牌
+    assert_eq!(
+        select("EfficiencyBot(false,true,true,1000)"),
+        Action::discard(Tile(TZ, WW))
+    );
+    // 聴牌時の和了点がpush_point未満: 現物の5pを打牌してベタオリ
+    assert_eq!(
+        select("EfficiencyBot(false,true,true,100000)"),
+        Action::discard(Tile(TP, 5))
+    );
+}
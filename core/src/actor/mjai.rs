@@ -179,35 +179,11 @@ impl MjaiEndpoint {
     fn notify_meld(&mut self, stg: &Stage, event: &EventMeld) {
         self.confirm_riichi_accepted(stg);
 
-        self.add_record(match event.meld_type {
-            MeldType::Chi => {
-                let lt = stg.last_tile.unwrap();
-                MjaiEvent::chi(event.seat, &event.consumed, lt.2, lt.0)
-            }
-            MeldType::Pon => {
-                let lt = stg.last_tile.unwrap();
-                MjaiEvent::pon(event.seat, &event.consumed, lt.2, lt.0)
-            }
-            MeldType::Minkan => {
-                let lt = stg.last_tile.unwrap();
-                MjaiEvent::daiminkan(event.seat, &event.consumed, lt.2, lt.0)
-            }
-            MeldType::Ankan => MjaiEvent::ankan(event.seat, &event.consumed),
-            MeldType::Kakan => {
-                let c = event.consumed[0];
-                let t = c.to_normal();
-                let t0 = if t.is_suit() && t.1 == 5 && c.1 != 0 {
-                    Tile(t.0, 0)
-                } else {
-                    t
-                };
-                MjaiEvent::kakan(event.seat, &event.consumed, &vec![t, t, t0])
-            }
-        });
+        self.add_record(MjaiEvent::meld(stg, event));
     }
 
-    fn notify_kita(&mut self, _stg: &Stage, _event: &EventKita) {
-        panic!();
+    fn notify_kita(&mut self, _stg: &Stage, event: &EventKita) {
+        self.add_record(MjaiEvent::nukidora(event.seat));
     }
 
     fn notify_dora(&mut self, _stg: &Stage, event: &EventDora) {
@@ -216,10 +192,11 @@ impl MjaiEndpoint {
 
     fn notify_win(&mut self, stg: &Stage, event: &EventWin) {
         for (seat, deltas, ctx) in &event.contexts {
+            let (target, tile) = get_hora_target(stg, *seat);
             self.add_record(MjaiEvent::hora(
                 *seat,
-                stg.turn,
-                stg.last_tile.unwrap().2,
+                target,
+                tile,
                 &event.ura_doras,
                 ctx,
                 deltas,
@@ -232,8 +209,9 @@ impl MjaiEndpoint {
     fn notify_draw(&mut self, stg: &Stage, event: &EventDraw) {
         self.add_record(MjaiEvent::ryukyoku(
             event.type_,
-            &[false; SEAT],
-            &[0; SEAT],
+            &get_draw_hands(stg, event),
+            &event.tenpais,
+            &event.points,
             &stg.get_scores(),
         ));
         self.add_record(MjaiEvent::end_kyoku());
@@ -290,13 +268,25 @@ impl Actor for MjaiEndpoint {
         if d.is_riichi {
             d.is_riichi = false;
             if let MjaiAction::Dahai { pai, .. } = mjai_act {
-                return Action::riichi(tile_from_mjai(&pai));
+                return match tile_from_mjai(&pai) {
+                    Ok(t) => Action::riichi(t),
+                    Err(e) => {
+                        error!("{}", e);
+                        Action::nop()
+                    }
+                };
             } else {
                 panic!();
             }
         }
 
-        let act = mjai_act.to_action(self.seat == stage.turn);
+        let act = match mjai_act.to_action(self.seat == stage.turn) {
+            Ok(act) => act,
+            Err(e) => {
+                error!("{}", e);
+                return Action::nop();
+            }
+        };
         // actがacts内に存在する有効な操作であるかをチェック
         match act.0 {
             ActionType::Discard => {
@@ -1,8 +1,10 @@
+mod efficiency;
 mod manual;
 mod mjai;
 mod nop;
 mod null;
 mod random;
+mod subprocess;
 mod tiitoitsu;
 
 use crate::controller::{Actor, Config, Listener};
@@ -17,6 +19,17 @@ trait ActorBuilder {
 }
 
 pub fn create_actor(exp: &str) -> Box<dyn Actor> {
+    match try_create_actor(exp) {
+        Ok(a) => a,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(0);
+        }
+    }
+}
+
+// Actorの生成 (ライブラリ用) 不正な式の場合はプロセスを終了せずにエラーを返却
+pub fn try_create_actor(exp: &str) -> Result<Box<dyn Actor>, String> {
     let builders: Vec<Box<dyn ActorBuilder>> = vec![
         Box::new(null::NullBuilder {}),
         Box::new(nop::NopBuilder {}),
@@ -24,6 +37,8 @@ pub fn create_actor(exp: &str) -> Box<dyn Actor> {
         Box::new(manual::ManualBuilder {}),
         Box::new(mjai::MjaiEndpointBuilder {}),
         Box::new(tiitoitsu::TiitoitsuBotBuilder {}),
+        Box::new(efficiency::EfficiencyBotBuilder {}),
+        Box::new(subprocess::SubprocessBuilder {}),
     ];
 
     let name: &str;
@@ -34,8 +49,7 @@ pub fn create_actor(exp: &str) -> Box<dyn Actor> {
         let l = paren_left.unwrap();
         let r = paren_right.unwrap();
         if r < l {
-            error!("invalid paren: {}", exp);
-            std::process::exit(0);
+            return Err(format!("invalid paren: {}", exp));
         }
 
         args = exp[l + 1..r].split(',').collect();
@@ -49,33 +63,26 @@ pub fn create_actor(exp: &str) -> Box<dyn Actor> {
         let mut conf = b.get_default_config();
         if name == conf.name {
             if conf.args.len() < args.len() {
-                error!(
+                return Err(format!(
                     "expected {} arguments for {}. but {} arguments are provided.",
                     conf.args.len(),
                     name,
                     args.len(),
-                );
-                std::process::exit(0);
+                ));
             }
 
             for (i, &a) in args.iter().enumerate() {
                 if a != "" {
-                    conf.args[i].value = match parse_as(&conf.args[i].value, a) {
-                        Ok(v) => v,
-                        Err(e) => {
-                            error!("{}: {}", e, a);
-                            std::process::exit(0);
-                        }
-                    };
+                    conf.args[i].value =
+                        parse_as(&conf.args[i].value, a).map_err(|e| format!("{}: {}", e, a))?;
                 }
             }
 
-            return b.create(conf);
+            return Ok(b.create(conf));
         }
     }
 
-    error!("unknown actor name: {}", name);
-    std::process::exit(0);
+    Err(format!("unknown actor name: {}", name))
 }
 
 fn parse_as(target: &Variant, value: &str) -> Result<Variant, String> {
@@ -86,3 +93,72 @@ fn parse_as(target: &Variant, value: &str) -> Result<Variant, String> {
         Variant::String(_) => Variant::String(value.parse::<String>().map_err(|e| e.to_string())?),
     })
 }
+
+// Actorが選択した操作が可能な操作(acts)に含まれるかの判定
+// 打牌はactsに含まれる打牌の選択肢(喰い替えで捨てられない牌)以外の手牌を有効とする
+pub fn is_valid_action(stg: &Stage, seat: Seat, acts: &[Action], act: &Action) -> bool {
+    if act.0 != ActionType::Discard {
+        return acts.contains(act);
+    }
+
+    // 打牌: 手牌に存在し, 喰い替えにならない牌
+    let forbidden = match acts.iter().find(|a| a.0 == ActionType::Discard) {
+        Some(a) => &a.1,
+        None => return false,
+    };
+    if stg.turn != seat || act.1.len() != 1 {
+        return false;
+    }
+    let t = act.1[0];
+    let h = &stg.players[seat].hand;
+    h[t.0][t.1] > 0 && !forbidden.contains(&t) && !forbidden.contains(&t.to_normal())
+}
+
+// Actorから有効な操作が得られなかった場合の代替の操作
+// 打牌可能な牌がない場合(不正な卓情報)はエラーを返却して呼び出し元で扱いを決める
+pub fn fallback_action(stg: &Stage, seat: Seat, acts: &[Action]) -> Result<Action, String> {
+    let pl = &stg.players[seat];
+    if stg.turn != seat || pl.drawn.is_some() {
+        return Ok(Action::nop()); // ツモ切り, 鳴きのスキップ
+    }
+
+    // 鳴き後の打牌
+    let forbidden = match acts.iter().find(|a| a.0 == ActionType::Discard) {
+        Some(a) => a.1.clone(),
+        None => vec![],
+    };
+    for t in tiles_from_tile_table(&pl.hand).into_iter().rev() {
+        if !forbidden.contains(&t) && !forbidden.contains(&t.to_normal()) {
+            return Ok(Action::discard(t));
+        }
+    }
+    Err(format!("no discardable tile for seat {}", seat))
+}
+
+#[test]
+fn test_fallback_action() {
+    let mut stg = Stage::default();
+    let s = 0;
+    stg.turn = s;
+    let pl = &mut stg.players[s];
+    pl.hand[TM][1] = 1;
+    pl.hand[TP][2] = 1;
+
+    // ツモ牌がある場合はツモ切り, 手番でない場合は鳴きのスキップ
+    pl.drawn = Some(Tile(TP, 2));
+    let acts = vec![Action(ActionType::Discard, vec![])];
+    assert_eq!(fallback_action(&stg, s, &acts), Ok(Action::nop()));
+    assert_eq!(fallback_action(&stg, 1, &acts), Ok(Action::nop()));
+
+    // 鳴き後は喰い替えにならない牌を打牌
+    stg.players[s].drawn = None;
+    let acts = vec![Action(ActionType::Discard, vec![Tile(TP, 2)])];
+    assert_eq!(
+        fallback_action(&stg, s, &acts),
+        Ok(Action::discard(Tile(TM, 1)))
+    );
+
+    // 打牌可能な牌がない場合はエラー
+    let acts = vec![Action(ActionType::Discard, vec![Tile(TM, 1), Tile(TP, 2)])];
+    assert!(fallback_action(&stg, s, &acts).is_err());
+}
@@ -34,6 +34,10 @@ impl Actor for Nop {
     fn get_config(&self) -> &Config {
         &self.config
     }
+
+    fn uses_stage(&self) -> bool {
+        false
+    }
 }
 
 impl Listener for Nop {}
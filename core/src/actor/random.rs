@@ -39,6 +39,11 @@ impl Actor for RandomDiscard {
         self.seat = seat;
     }
 
+    fn reset(&mut self) {
+        // 試合の実行順に依存しないように乱数を初期化
+        self.rng = rand::SeedableRng::seed_from_u64(0);
+    }
+
     fn select_action(&mut self, stage: &Stage, _acts: &Vec<Action>) -> Action {
         if stage.turn != self.seat {
             return Action::nop();
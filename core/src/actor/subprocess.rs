@@ -0,0 +1,321 @@
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use serde_json::json;
+
+use super::*;
+use crate::util::common::flush;
+
+use crate::{error, info};
+
+const MAX_TIMEOUT_COUNT: i32 = 5; // 連続でタイムアウトした場合はプロセスを再起動
+
+pub struct SubprocessBuilder;
+
+impl ActorBuilder for SubprocessBuilder {
+    fn get_default_config(&self) -> Config {
+        Config {
+            name: "Subprocess".to_string(),
+            args: vec![
+                Arg::string("cmd", ""),
+                Arg::int("timeout", 10),
+                Arg::bool("debug", false),
+            ],
+        }
+    }
+
+    fn create(&self, config: Config) -> Box<dyn Actor> {
+        Box::new(Subprocess::from_config(config))
+    }
+}
+
+// 外部プロセスのBotを子プロセスとして起動して標準入出力(JSON Lines)で通信するActor
+// 送信 (stdin):
+//   {"type":"Init","seat":0}                           プロセス起動時
+//   {"type":"New",...}, {"type":"Discard",...}, ...    Event (Eventのjsonそのまま)
+//   {"type":"SelectAction","seat":0,"possible_actions":[["Nop",[]],["Discard",["m1"]],...]}
+// 受信 (stdout):
+//   SelectActionに対する応答としてAction 1行 例) ["Discard",["m1"]], ["Riichi",["z1"]]
+// args:
+//   cmd: 起動するコマンド (空白区切りで引数を指定)
+//   timeout: select_actionの最大待機時間(秒)
+//   debug: 送受信したメッセージを表示
+// プロセスの起動に失敗した場合, 異常終了した場合, またはタイムアウトが連続した場合は
+// 局の終了まで代替の操作(ツモ切り,鳴きのスキップ)を行い, 次の局の開始時にプロセスを再起動する
+// 複数の試合で再利用される場合は同じプロセスを使い続ける (試合の開始はInitで通知)
+pub struct Subprocess {
+    config: Config,
+    seat: Seat,
+    cmd: String,
+    timeout: i32,
+    debug: bool,
+    proc: Option<Process>,
+    timeout_count: i32,
+}
+
+impl Subprocess {
+    pub fn from_config(config: Config) -> Self {
+        let args = &config.args;
+        let cmd = args[0].value.as_string();
+        let timeout = args[1].value.as_int();
+        let debug = args[2].value.as_bool();
+        Self {
+            config: config,
+            seat: NO_SEAT,
+            cmd: cmd,
+            timeout: timeout,
+            debug: debug,
+            proc: None,
+            timeout_count: 0,
+        }
+    }
+
+    fn spawn(&mut self) -> Result<(), String> {
+        self.kill();
+        let proc = Process::spawn(&self.cmd)?;
+        info!(
+            "subprocess started: {} (pid: {})",
+            self.cmd,
+            proc.child.id()
+        );
+        self.proc = Some(proc);
+        self.timeout_count = 0;
+        self.send(&json!({"type": "Init", "seat": self.seat}).to_string());
+        Ok(())
+    }
+
+    fn kill(&mut self) {
+        if let Some(mut p) = self.proc.take() {
+            p.kill();
+        }
+    }
+
+    fn send(&mut self, msg: &str) {
+        if let Some(p) = &mut self.proc {
+            if self.debug {
+                println!("-> {}", msg);
+                flush();
+            }
+            if let Err(e) = writeln!(p.stdin, "{}", msg) {
+                error!("subprocess write error: {}", e);
+                self.kill();
+            }
+        }
+    }
+
+    fn recv(&mut self) -> Result<String, String> {
+        let p = match &mut self.proc {
+            Some(p) => p,
+            None => return Err("subprocess is not running".to_string()),
+        };
+
+        let timeout = Duration::from_secs(self.timeout as u64);
+        match p.rx.recv_timeout(timeout) {
+            Ok(line) => {
+                if self.debug {
+                    println!("<- {}", line);
+                    flush();
+                }
+                self.timeout_count = 0;
+                Ok(line)
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                self.timeout_count += 1;
+                if self.timeout_count == MAX_TIMEOUT_COUNT {
+                    error!("timeout_count exceeded");
+                    self.kill();
+                }
+                Err("select_action timeout".to_string())
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                self.kill();
+                Err("subprocess exited".to_string())
+            }
+        }
+    }
+
+    fn query_action(&mut self, stg: &Stage, acts: &Vec<Action>) -> Result<Action, String> {
+        if let Some(p) = &self.proc {
+            // タイムアウトした以前の問い合わせに対する応答を破棄
+            while p.rx.try_recv().is_ok() {}
+        }
+
+        let msg = json!({
+            "type": "SelectAction",
+            "seat": self.seat,
+            "possible_actions": acts,
+        });
+        self.send(&msg.to_string());
+
+        let line = self.recv()?;
+        let act: Action = serde_json::from_str(&line).map_err(|e| format!("{}: {}", e, line))?;
+        if !is_valid_action(stg, self.seat, acts, &act) {
+            return Err(format!(
+                "selected_action={:?} is not contained in possible_actions={:?}",
+                act, acts
+            ));
+        }
+        Ok(act)
+    }
+}
+
+impl Clone for Subprocess {
+    // プロセスは共有せず, 複製先のinitで新たに起動する
+    fn clone(&self) -> Self {
+        Self::from_config(self.config.clone())
+    }
+}
+
+impl Drop for Subprocess {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+impl Actor for Subprocess {
+    fn init(&mut self, seat: Seat) {
+        self.seat = seat;
+        if self.proc.is_some() {
+            // 再利用時は起動済みのプロセスを使用
+            self.send(&json!({"type": "Init", "seat": self.seat}).to_string());
+            return;
+        }
+        // 起動に失敗した場合は異常終了時と同様に次の局の開始時に再起動を試みる
+        if let Err(e) = self.spawn() {
+            error!("{}: {}", e, self.cmd);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.timeout_count = 0;
+    }
+
+    fn select_action(&mut self, stg: &Stage, acts: &Vec<Action>) -> Action {
+        match self.query_action(stg, acts) {
+            Ok(act) => act,
+            Err(e) => {
+                error!("{}", e);
+                fallback_action(stg, self.seat, acts).unwrap_or_else(|e| {
+                    error!("{}", e);
+                    Action::nop()
+                })
+            }
+        }
+    }
+
+    fn get_config(&self) -> &Config {
+        &self.config
+    }
+}
+
+impl Listener for Subprocess {
+    fn notify_event(&mut self, _stg: &Stage, event: &Event) {
+        if let Event::New(_) = event {
+            if self.proc.is_none() && self.seat != NO_SEAT {
+                if let Err(e) = self.spawn() {
+                    error!("{}: {}", e, self.cmd);
+                }
+            }
+        }
+
+        match serde_json::to_string(event) {
+            Ok(msg) => self.send(&msg),
+            Err(e) => error!("{}", e),
+        }
+    }
+}
+
+// [Process]
+struct Process {
+    child: Child,
+    stdin: ChildStdin,
+    rx: mpsc::Receiver<String>, // stdoutから読み込んだ行
+}
+
+impl Process {
+    fn spawn(cmd: &str) -> Result<Self, String> {
+        let mut it = cmd.split_whitespace();
+        let prog = it.next().ok_or("command is empty")?;
+        let mut child = Command::new(prog)
+            .args(it)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| e.to_string())?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        // 読み込みスレッド プロセス終了時(EOF)にチャンネルが切断される
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(l) if l.trim().is_empty() => {}
+                    Ok(l) => {
+                        if tx.send(l).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+        });
+
+        Ok(Self { child, stdin, rx })
+    }
+
+    fn kill(&mut self) {
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+#[test]
+fn test_subprocess() {
+    use crate::controller::*;
+    use crate::convert::mjai::MjaiDeserializer;
+
+    // 常に1mを打牌するBot
+    let script = std::env::temp_dir().join("mahjong_test_subprocess.sh");
+    std::fs::write(
+        &script,
+        r#"while read line; do
+  case "$line" in
+    *SelectAction*) echo '["Discard",["m1"]]' ;;
+  esac
+done
+"#,
+    )
+    .unwrap();
+
+    let log = r#"{"type":"start_game","id":0,"names":["a","b","c","d"]}
+{"type":"start_kyoku","bakaze":"E","dora_marker":"1s","kyoku":1,"honba":0,"kyotaku":0,"oya":0,"tehais":[["1m","2m","3m","4p","5p","6p","7s","8s","9s","E","E","S","N"],["1m","2m","3m","4p","5p","6p","7s","8s","9s","S","S","W","N"],["1p","2p","3p","4s","5s","6s","7m","8m","9m","W","W","P","F"],["1s","2s","3s","4m","5m","6m","7p","8p","9p","P","F","C","C"]],"scores":[25000,25000,25000,25000]}
+{"type":"tsumo","actor":0,"pai":"W"}"#;
+
+    let bot = create_actor(&format!("Subprocess(sh {},2)", script.display()));
+    let crashed = create_actor("Subprocess(sh -c exit,2)");
+    let missing = create_actor("Subprocess(mahjong-test-no-such-command,2)");
+    let nop = create_actor("Nop");
+    let actors = [bot, crashed, missing, nop];
+    let mut ctrl = StageController::new(actors, vec![]);
+    let mut de = MjaiDeserializer::new();
+    for line in log.lines() {
+        for e in de.push_record(serde_json::from_str(line).unwrap()).unwrap() {
+            ctrl.handle_event(&e);
+        }
+    }
+
+    let acts = calc_possible_turn_actions(ctrl.get_stage(), &None);
+    assert_eq!(ctrl.select_action(0, &acts), Action::discard(Tile(TM, 1)));
+    // 異常終了したプロセスはツモ切り
+    assert_eq!(ctrl.select_action(1, &acts), Action::nop());
+    // 起動に失敗したプロセスも終了せずに代替の操作
+    assert_eq!(ctrl.select_action(2, &acts), Action::nop());
+
+    std::fs::remove_file(&script).ok();
+}
@@ -164,6 +164,7 @@ impl Calculator {
         if let Some(ctx) = evaluate_hand(
             &self.hand,
             &self.melds,
+            0,
             &self.doras,
             &self.ura_doras,
             self.win_tile,
@@ -172,6 +173,7 @@ impl Calculator {
             self.prevalent_wind,
             self.seat_wind,
             &self.yaku_flags,
+            &Rule::default(),
         ) {
             if self.detail {
                 println!("{:?}", ctx);
@@ -324,99 +326,6 @@ impl Calculator {
     }
 }
 
-fn tiles_from_string(exp: &str) -> Result<Vec<Tile>, String> {
-    let mut tiles = vec![];
-    let undef: usize = 255;
-    let mut ti = undef;
-    for c in exp.chars() {
-        match c {
-            'm' => ti = 0,
-            'p' => ti = 1,
-            's' => ti = 2,
-            'z' => ti = 3,
-            '0'..='9' => {
-                if ti == undef {
-                    return Err(format!("tile number befor tile type"));
-                }
-                let ni = c.to_digit(10).unwrap() as usize;
-                tiles.push(Tile(ti, ni));
-            }
-            _ => {
-                return Err(format!("invalid char: '{}'", c));
-            }
-        }
-    }
-    Ok(tiles)
-}
-
-fn meld_from_string(exp: &str, seat: Seat) -> Result<Meld, String> {
-    let undef: usize = 255;
-    let mut ti = undef;
-    let mut nis = vec![];
-    let mut from = 0;
-    let mut tiles = vec![];
-    let mut froms = vec![];
-    for c in exp.chars() {
-        match c {
-            'm' => ti = 0,
-            'p' => ti = 1,
-            's' => ti = 2,
-            'z' => ti = 3,
-            '+' => {
-                if froms.is_empty() {
-                    return Err("invalid '+' suffix".into());
-                }
-                let last = froms.len() - 1;
-                froms[last] = from % SEAT;
-            }
-            '0'..='9' => {
-                if ti == undef {
-                    return Err("tile number befor tile type".into());
-                }
-
-                from += 1;
-                let ni = c.to_digit(10).unwrap() as usize;
-                nis.push(if ni == 0 { 5 } else { ni });
-                tiles.push(Tile(ti, ni));
-                froms.push(seat);
-            }
-            _ => {
-                return Err(format!("invalid char: '{}'", c));
-            }
-        }
-    }
-
-    nis.sort();
-    let mut diffs = vec![];
-    let mut ni0 = nis[0];
-    for ni in &nis[1..] {
-        diffs.push(ni - ni0);
-        ni0 = *ni;
-    }
-
-    let meld_type = if diffs.len() == 2 && vec_count(&diffs, &1) == 2 {
-        MeldType::Chi
-    } else if diffs.len() == 2 && vec_count(&diffs, &0) == 2 {
-        MeldType::Pon
-    } else if diffs.len() == 3 && vec_count(&diffs, &0) == 3 {
-        if vec_count(&froms, &seat) == 4 {
-            MeldType::Ankan
-        } else {
-            MeldType::Minkan
-        }
-    } else {
-        return Err(format!("invalid meld: '{}'", exp));
-    };
-
-    Ok(Meld {
-        step: 0,
-        seat: seat,
-        type_: meld_type,
-        tiles: tiles,
-        froms: froms,
-    })
-}
-
 fn wind_from_char(c: char) -> Result<Index, String> {
     Ok(match c {
         'E' => 1,
@@ -1,4 +1,5 @@
 use rand::prelude::*;
+use serde_json::json;
 
 use crate::actor::create_actor;
 use crate::controller::*;
@@ -8,7 +9,7 @@ use crate::model::*;
 use crate::util::common::*;
 use crate::util::server::Server;
 
-use crate::{error, warn};
+use crate::{error, info, warn};
 
 use ActionType::*;
 
@@ -19,9 +20,14 @@ pub struct EngineApp {
     mode: usize,
     n_game: u32,
     n_thread: u32,
+    n_dup: usize, // 0: 無効, それ以外: 同一の牌山で座席を入れ替えて実行する試合数 (デュプリケート)
     write: bool,
+    write_mjai: bool,
+    stats_path: String, // マルチプル実行の成績(json)の出力先
     gui_port: u32,
     debug: bool,
+    omniscient: bool, // Actorに他家の手牌などすべての情報を公開 (解析用)
+    rule: Rule,
     names: [String; SEAT], // actor names
 }
 
@@ -32,9 +38,14 @@ impl EngineApp {
             mode: 1,
             n_game: 0,
             n_thread: 16,
+            n_dup: 0,
             write: false,
+            write_mjai: false,
+            stats_path: "".to_string(),
             gui_port: super::GUI_PORT,
             debug: false,
+            omniscient: false,
+            rule: Rule::default(),
             names: [
                 "".to_string(),
                 "".to_string(),
@@ -43,16 +54,22 @@ impl EngineApp {
             ],
         };
 
+        let mut rule_path = "".to_string();
         let mut it = args.iter();
         while let Some(s) = it.next() {
             match s.as_str() {
                 "-s" => app.seed = next_value(&mut it, "-s"),
                 "-m" => app.mode = next_value(&mut it, "-m"),
+                "-r" => rule_path = next_value(&mut it, "-r"),
                 "-g" => app.n_game = next_value(&mut it, "-g"),
                 "-t" => app.n_thread = next_value(&mut it, "-t"),
+                "-dup" => app.n_dup = next_value(&mut it, "-dup"),
                 "-w" => app.write = true,
+                "-wm" => app.write_mjai = true,
+                "-stats" => app.stats_path = next_value(&mut it, "-stats"),
                 "-gui-port" => app.gui_port = next_value(&mut it, "-gui-port"),
                 "-d" => app.debug = true,
+                "-omniscient" => app.omniscient = true,
                 "-0" => app.names[0] = next_value(&mut it, "-0"),
                 "-1" => app.names[1] = next_value(&mut it, "-1"),
                 "-2" => app.names[2] = next_value(&mut it, "-2"),
@@ -64,6 +81,23 @@ impl EngineApp {
             }
         }
 
+        if app.mode > 10 && app.names[3] == "" {
+            app.names[3] = "Null".to_string(); // 三麻は座席3を使用しない
+        }
+
+        if app.n_dup != 0 {
+            // 座席のローテーション(4, 三麻:3)またはすべての順列(24, 三麻:6)
+            let n = if app.mode > 10 { 3 } else { SEAT };
+            if app.n_dup != n && app.n_dup != (1..=n).product::<usize>() {
+                error!("invalid duplicate count: {}", app.n_dup);
+                std::process::exit(0);
+            }
+        }
+
+        if rule_path != "" {
+            app.rule = Rule::from_file(&rule_path).unwrap_or_else(error_exit);
+        }
+
         if app.seed == 0 {
             app.seed = unixtime_now();
             warn!(
@@ -89,15 +123,17 @@ impl EngineApp {
         }
 
         let start = std::time::Instant::now();
+        let mut n_game = 0;
         if self.n_game == 0 {
             self.run_single_game(actors);
         } else {
-            self.run_multiple_game(actors);
+            n_game = self.run_multiple_game(actors);
+        }
+        let elapsed = start.elapsed().as_nanos() as f32 / 1000000000.0;
+        println!("total elapsed time: {:8.3}sec", elapsed);
+        if n_game != 0 {
+            println!("throughput: {:8.1}games/sec", n_game as f32 / elapsed);
         }
-        println!(
-            "total elapsed time: {:8.3}sec",
-            start.elapsed().as_nanos() as f32 / 1000000000.0
-        );
     }
 
     fn run_single_game(&mut self, actors: [Box<dyn Actor>; 4]) {
@@ -112,92 +148,318 @@ impl EngineApp {
         if self.write {
             listeners.push(Box::new(EventWriter::new()));
         }
+        if self.write_mjai {
+            let names = [0, 1, 2, 3].map(|s| actors[s].get_config().name.clone());
+            listeners.push(Box::new(MjaiEventWriter::new(names)));
+        }
         // let log = crate::convert::tenhou::TenhouLog::new();
         // listeners.push(Box::new(crate::listener::TenhouEventWriter::new(log)));
         if self.debug {
             listeners.push(Box::new(Prompt::new()));
         }
 
-        let mut game = MahjongEngine::new(self.seed, self.mode, 25000, actors, listeners);
+        let mut game = MahjongEngine::new(self.seed, self.mode, &self.rule, actors, listeners);
+        if self.omniscient {
+            game.set_omniscient();
+        }
         game.run();
     }
 
-    fn run_multiple_game(&mut self, actors: [Box<dyn Actor>; 4]) {
-        use std::sync::mpsc;
-        use std::{thread, time};
-
+    // 実行した試合数を返却
+    fn run_multiple_game(&mut self, actors: [Box<dyn Actor>; 4]) -> u32 {
         let mode = self.mode;
+        let n_seat = if mode > 10 { 3 } else { SEAT }; // 三麻の場合は座席3を使用しない
         let mut n_game = 0;
-        let mut n_thread = 0;
         let mut n_game_end = 0;
         let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(self.seed);
-        let (tx, rx) = mpsc::channel();
-        let mut total_score_delta = [0; SEAT];
-        let mut total_rank_sum = [0; SEAT];
-        loop {
-            if n_game < self.n_game && n_thread < self.n_thread {
-                n_game += 1;
-                n_thread += 1;
+        let names: Vec<String> = actors[..n_seat]
+            .iter()
+            .map(|a| format!("{:?}", a))
+            .collect();
+        let mut stats = vec![ActorStats::new(); n_seat];
+
+        // デュプリケート: 各シード値(牌山)でn_dup回,座席を入れ替えて実行
+        let dup_tables = if self.n_dup == 0 {
+            vec![]
+        } else if self.n_dup == n_seat {
+            (0..n_seat)
+                .map(|r| {
+                    let mut t = [0, 1, 2, 3];
+                    for s in 0..n_seat {
+                        t[s] = (s + r) % n_seat;
+                    }
+                    t
+                })
+                .collect()
+        } else {
+            seat_permutations(n_seat)
+        };
+        let n_game_total = if dup_tables.is_empty() {
+            self.n_game
+        } else {
+            self.n_game * dup_tables.len() as u32
+        };
+        let mut dup_seed = 0;
+
+        let next_task = || {
+            if n_game == n_game_total {
+                return None;
+            }
 
+            let (seed, shuffle_table) = if dup_tables.is_empty() {
                 let seed = rng.next_u64();
                 let mut shuffle_table = [0, 1, 2, 3];
-                shuffle_table.shuffle(&mut rng);
-                let null = create_actor("Null");
-                let mut shuffled_actors: [Box<dyn Actor>; SEAT] = [
-                    null.clone_box(),
-                    null.clone_box(),
-                    null.clone_box(),
-                    null.clone_box(),
-                ];
-                for s in 0..SEAT {
-                    shuffled_actors[s] = actors[shuffle_table[s]].clone_box();
+                shuffle_table[..n_seat].shuffle(&mut rng);
+                (seed, shuffle_table)
+            } else {
+                let i = n_game as usize % dup_tables.len();
+                if i == 0 {
+                    dup_seed = rng.next_u64();
                 }
+                (dup_seed, dup_tables[i])
+            };
+            n_game += 1;
+
+            Some(GameTask {
+                id: n_game as usize - 1,
+                seed: seed,
+                table: shuffle_table,
+            })
+        };
 
-                let tx2 = tx.clone();
-                thread::spawn(move || {
-                    let start = time::Instant::now();
-                    let mut game = MahjongEngine::new(seed, mode, 25000, shuffled_actors, vec![]);
-                    game.run();
-                    tx2.send((shuffle_table, game, start.elapsed())).unwrap();
-                });
+        let on_result = |res: GameResult| {
+            let ms = res.elapsed.as_nanos() / 1000000;
+            print!("{:5},{:4}ms,{:20}", n_game_end, ms, res.seed);
+            for s in 0..n_seat {
+                let i = res.table[s];
+                if dup_tables.is_empty() {
+                    stats[i].add(&res.stats[s], self.rule.initial_score);
+                } else {
+                    let group = res.id / dup_tables.len();
+                    stats[i].add_dup(group, &res.stats[s], self.rule.initial_score);
+                }
+                print!(", ac{}:{:5}({})", i, res.scores[s], res.ranks[s] + 1);
             }
+            println!();
+            n_game_end += 1;
+        };
+
+        run_games(
+            mode,
+            &self.rule,
+            self.n_thread,
+            self.omniscient,
+            &actors,
+            next_task,
+            on_result,
+        );
 
+        print_stats_table(&names, &stats, n_seat);
+        if self.stats_path != "" {
+            let v = json!({
+                "seed": self.seed,
+                "mode": mode,
+                "n_game": n_game,
+                "n_dup": self.n_dup,
+                "actors": (0..n_seat)
+                    .map(|i| stats[i].to_json(&names[i], n_seat))
+                    .collect::<Vec<_>>(),
+            });
+            write_to_file(&self.stats_path, &serde_json::to_string_pretty(&v).unwrap());
+            info!("stats written to {}", self.stats_path);
+        }
+
+        n_game
+    }
+}
+
+// [GameRunner]
+// 並列実行する試合
+pub(super) struct GameTask {
+    pub id: usize,
+    pub seed: u64,
+    pub table: [usize; SEAT], // 各座席のActorのindex (三麻の場合,座席3は使用しない)
+}
+
+// 試合の結果
+pub(super) struct GameResult {
+    pub id: usize,
+    pub seed: u64,
+    pub table: [usize; SEAT],
+    pub elapsed: std::time::Duration,
+    pub scores: [Score; SEAT],
+    pub ranks: [usize; SEAT], // 0~3
+    pub final_points: [Point; SEAT],
+    pub stats: GameStats,
+}
+
+// next_taskがNoneを返すまでn_threadのワーカースレッドで試合を並列に実行
+// 各ワーカーはactorsの複製を保持して試合ごとに再利用する (再利用時にActor::resetを呼び出し)
+// on_resultは試合が終了した順に呼び出される
+pub(super) fn run_games(
+    mode: usize,
+    rule: &Rule,
+    n_thread: u32,
+    omniscient: bool,
+    actors: &[Box<dyn Actor>],
+    mut next_task: impl FnMut() -> Option<GameTask>,
+    mut on_result: impl FnMut(GameResult),
+) {
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::thread;
+
+    let (task_tx, task_rx) = mpsc::channel::<GameTask>();
+    let (res_tx, res_rx) = mpsc::channel();
+    let task_rx = Arc::new(Mutex::new(task_rx));
+
+    let mut workers = vec![];
+    for _ in 0..n_thread.max(1) {
+        let pool: Vec<Box<dyn Actor>> = actors.iter().map(|a| a.clone_box()).collect();
+        let task_rx = task_rx.clone();
+        let res_tx = res_tx.clone();
+        let rule = rule.clone();
+        workers.push(thread::spawn(move || {
+            let mut worker = GameWorker::new(mode, rule, omniscient, pool);
             loop {
-                if let Ok((shuffle, game, elapsed)) = rx.try_recv() {
-                    let ms = elapsed.as_nanos() / 1000000;
-                    print!("{:5},{:4}ms,{:20}", n_game_end, ms, game.seed);
-                    for s in 0..SEAT {
-                        let pl = &game.get_stage().players[s];
-                        let (score, rank) = (pl.score, pl.rank + 1);
-                        let i = shuffle[s];
-                        total_score_delta[i] += score - game.initial_score;
-                        total_rank_sum[i] += rank;
-                        print!(", ac{}:{:5}({})", i, score, rank);
+                let task = task_rx.lock().unwrap().recv();
+                match task {
+                    Ok(task) => {
+                        if res_tx.send(worker.run(task)).is_err() {
+                            break;
+                        }
                     }
-                    println!();
-
-                    n_thread -= 1;
-                    n_game_end += 1;
+                    Err(_) => break, // タスクの送信側が破棄された
                 }
-                if n_thread < self.n_thread {
-                    break;
+            }
+        }));
+    }
+    drop(res_tx);
+
+    // 未処理のタスクがワーカー数の2倍を超えないように逐次追加
+    let max_queued = n_thread.max(1) as usize * 2;
+    let mut n_queued = 0;
+    let mut is_task_end = false;
+    loop {
+        while !is_task_end && n_queued < max_queued {
+            match next_task() {
+                Some(task) => {
+                    task_tx.send(task).unwrap();
+                    n_queued += 1;
                 }
-                sleep_ms(10);
+                None => is_task_end = true,
             }
+        }
 
-            if n_thread == 0 && n_game == self.n_game {
-                for i in 0..SEAT {
-                    println!(
-                        "ac{} avg_rank: {:.2}, avg_score_delta: {:6}",
-                        i,
-                        total_rank_sum[i] as f32 / n_game as f32,
-                        total_score_delta[i] / n_game as i32,
-                    );
-                }
+        if n_queued == 0 {
+            break;
+        }
+
+        match res_rx.recv() {
+            Ok(res) => {
+                n_queued -= 1;
+                on_result(res);
+            }
+            Err(_) => {
+                error!("all workers stopped");
                 break;
             }
         }
     }
+
+    drop(task_tx);
+    for w in workers {
+        w.join().ok();
+    }
+}
+
+struct GameWorker {
+    mode: usize,
+    rule: Rule,
+    omniscient: bool,
+    pool: Vec<Box<dyn Actor>>, // 再利用するActor (GameTask.tableのindexに対応)
+    null: Box<dyn Actor>,
+}
+
+impl GameWorker {
+    fn new(mode: usize, rule: Rule, omniscient: bool, pool: Vec<Box<dyn Actor>>) -> Self {
+        Self {
+            mode: mode,
+            rule: rule,
+            omniscient: omniscient,
+            pool: pool,
+            null: create_actor("Null"),
+        }
+    }
+
+    fn run(&mut self, task: GameTask) -> GameResult {
+        use std::sync::{Arc, Mutex};
+
+        let start = std::time::Instant::now();
+        let n_seat = if self.mode > 10 { 3 } else { SEAT };
+
+        // poolからActorを取り出して卓に割り当て (同じActorが複数の座席に指定された場合は複製)
+        let mut actors = [0, 1, 2, 3].map(|_| self.null.clone_box());
+        let mut borrowed = [false; SEAT];
+        for s in 0..n_seat {
+            let i = task.table[s];
+            if task.table[..s].contains(&i) {
+                actors[s] = self.pool[i].clone_box();
+            } else {
+                std::mem::swap(&mut actors[s], &mut self.pool[i]);
+                borrowed[s] = true;
+            }
+            actors[s].reset();
+        }
+
+        let game_stats = Arc::new(Mutex::new(GameStats::default()));
+        let listeners: Vec<Box<dyn Listener>> =
+            vec![Box::new(StatsCollector::new(game_stats.clone()))];
+        let mut game = MahjongEngine::new(task.seed, self.mode, &self.rule, actors, listeners);
+        if self.omniscient {
+            game.set_omniscient();
+        }
+        game.run();
+
+        // Actorをpoolに戻す
+        for s in 0..n_seat {
+            if borrowed[s] {
+                game.ctrl.swap_actor(s, &mut self.pool[task.table[s]]);
+            }
+        }
+
+        let stats = game_stats.lock().unwrap().clone();
+        let stg = game.get_stage();
+        GameResult {
+            id: task.id,
+            seed: game.seed,
+            table: task.table,
+            elapsed: start.elapsed(),
+            scores: [0, 1, 2, 3].map(|s| stg.players[s].score),
+            ranks: [0, 1, 2, 3].map(|s| stg.players[s].rank),
+            final_points: [0, 1, 2, 3].map(|s| stats[s].final_point),
+            stats: stats,
+        }
+    }
+}
+
+// 座席0..nのすべての順列 (n以降の座席は固定)
+fn seat_permutations(n: usize) -> Vec<[usize; SEAT]> {
+    let mut res = vec![];
+    let mut t = [0, 1, 2, 3];
+    permutate(&mut t, 0, n, &mut res);
+    res
+}
+
+fn permutate(t: &mut [usize; SEAT], k: usize, n: usize, res: &mut Vec<[usize; SEAT]>) {
+    if k == n {
+        res.push(*t);
+        return;
+    }
+    for i in k..n {
+        t.swap(k, i);
+        permutate(t, k + 1, n, res);
+        t.swap(k, i);
+    }
 }
 
 // [Engine]
@@ -217,11 +479,13 @@ struct NextKyokuInfo {
     scores: [Score; SEAT],
 }
 
+// 1試合分のゲーム進行 (牌山の生成, Eventの生成, 局の精算)
+// 操作はActorが選択し, Eventの通知はStageControllerを介してActorとListenerに行う
 #[derive(Debug)]
-struct MahjongEngine {
+pub struct MahjongEngine {
     seed: u64,               // 牌山生成用の乱数のシード値
-    mode: usize,             // 1: 東風戦, 2: 半荘戦, 4: 一荘戦
-    initial_score: Score,    // 初期得点
+    mode: usize,             // 1: 東風戦, 2: 半荘戦, 4: 一荘戦 (三麻の場合は+10)
+    rule: Rule,              // ルール設定
     rng: rand::rngs::StdRng, // 乱数 (牌山生成)
     // ゲーム制御
     ctrl: StageController,
@@ -242,10 +506,10 @@ struct MahjongEngine {
 }
 
 impl MahjongEngine {
-    fn new(
+    pub fn new(
         seed: u64,
         mode: usize,
-        initial_score: Score,
+        rule: &Rule,
         actors: [Box<dyn Actor>; SEAT],
         listeners: Vec<Box<dyn Listener>>,
     ) -> Self {
@@ -256,13 +520,13 @@ impl MahjongEngine {
             kyoku: 0,
             honba: 0,
             kyoutaku: 0,
-            scores: [initial_score; SEAT],
+            scores: [rule.initial_score; SEAT],
         };
 
         Self {
             seed: seed,
             mode: mode,
-            initial_score: initial_score,
+            rule: rule.clone(),
             rng: rng,
             ctrl: ctrl,
             melding: None,
@@ -282,16 +546,23 @@ impl MahjongEngine {
     }
 
     #[inline]
-    fn get_stage(&self) -> &Stage {
+    pub fn get_stage(&self) -> &Stage {
         self.ctrl.get_stage()
     }
 
+    // Actorにすべての情報が公開された卓情報を渡す (解析用)
+    pub fn set_omniscient(&mut self) {
+        for s in 0..SEAT {
+            self.ctrl.set_omniscient(s, true);
+        }
+    }
+
     #[inline]
     fn handle_event(&mut self, event: Event) {
         self.ctrl.handle_event(&event);
     }
 
-    fn run(&mut self) {
+    pub fn run(&mut self) {
         self.do_event_begin();
         while !self.is_end {
             self.do_event_new();
@@ -314,6 +585,15 @@ impl MahjongEngine {
         self.do_event_end();
     }
 
+    // 試合終了後にActorを取り出す (卓にはNullが残る)
+    pub fn take_actors(&mut self) -> [Box<dyn Actor>; SEAT] {
+        let mut actors = [0, 1, 2, 3].map(|_| create_actor("Null"));
+        for s in 0..SEAT {
+            self.ctrl.swap_actor(s, &mut actors[s]);
+        }
+        actors
+    }
+
     fn do_event_begin(&mut self) {
         self.handle_event(Event::Begin(EventBegin {}));
     }
@@ -336,19 +616,26 @@ impl MahjongEngine {
         self.ura_dora_wall = vec![];
         self.replacement_wall = vec![];
 
-        let is_3p = false;
+        let is_3p = self.mode > 10;
+        let n_seat = if is_3p { 3 } else { SEAT };
 
         // 山の初期化
-        self.wall = create_wall(self.rng.next_u64());
+        self.wall = create_wall(self.rng.next_u64(), is_3p, &self.rule);
 
         // 王牌
         self.dora_wall = self.draw_tiles(5); // 槓ドラ
         self.ura_dora_wall = self.draw_tiles(5); // 裏ドラ
-        self.replacement_wall = self.draw_tiles(if is_3p { 8 } else { 4 }); // 嶺上牌
+        self.replacement_wall = if is_3p {
+            // 三麻は北抜きがあるため嶺上牌は8枚
+            // 王牌は14枚で固定なので5枚目以降は牌山の末尾(海底側)から補充される
+            self.wall.iter().rev().take(8).cloned().collect()
+        } else {
+            self.draw_tiles(4)
+        }; // 嶺上牌
 
         // プレイヤーの手牌生成
         let mut ph = [vec![], vec![], vec![], vec![]];
-        for s in 0..SEAT {
+        for s in 0..n_seat {
             ph[s] = self.draw_tiles(13);
         }
         // 親の14枚目
@@ -371,6 +658,7 @@ impl MahjongEngine {
             rn.scores,
             ph,
             self.mode,
+            self.rule.clone(),
         );
         self.handle_event(event);
     }
@@ -461,7 +749,16 @@ impl MahjongEngine {
         }
 
         // dispatch action
-        if !rons.is_empty() {
+        if rons.len() == 3 && self.rule.sanchaho {
+            self.kyoku_result = Some(KyokuResult::Draw(DrawType::Sanchaho));
+            return;
+        } else if !rons.is_empty() {
+            if !self.rule.double_ron {
+                // 頭ハネ: 放銃者から一番近い和了プレイヤーのみ和了
+                let turn = self.get_stage().turn;
+                rons.sort_by_key(|&s| (s + SEAT - turn) % SEAT);
+                rons.truncate(1);
+            }
             self.kyoku_result = Some(KyokuResult::Ron(rons));
             return;
         } else if let Some((s, act)) = minkan {
@@ -507,14 +804,17 @@ impl MahjongEngine {
             }
         } else {
             if stg.left_tile_count > 0 {
-                let s = (turn + 1) % SEAT;
+                let s = (turn + 1) % stg.get_seat_count();
                 let t = self.draw_tile();
                 self.handle_event(Event::deal(s, t));
             } else {
                 self.kyoku_result = Some(KyokuResult::Draw(DrawType::Kouhaiheikyoku));
             }
         }
-        assert!(self.get_stage().left_tile_count + self.n_deal + self.n_kan == self.wall.len());
+        let stg = self.get_stage();
+        let n_dead = if stg.is_3p { 4 } else { 0 }; // 牌山の末尾に残る嶺上牌 (三麻)
+        let n_replacement = self.n_kan + self.n_kita;
+        assert!(stg.left_tile_count + self.n_deal + n_replacement + n_dead == self.wall.len());
     }
 
     fn do_event_win_draw(&mut self) {
@@ -524,19 +824,46 @@ impl MahjongEngine {
         let mut honba = stg.honba;
         let mut kyoutaku = stg.kyoutaku;
         let turn = stg.turn;
+        let n_seat = stg.get_seat_count();
         let mut need_dealer_change = false; // 親の交代
+        let mut can_yame = false; // 親の和了止め・聴牌止めの対象
         match self.kyoku_result.as_ref().unwrap() {
             KyokuResult::Tsumo => {
                 let mut d_scores = [0; SEAT]; // 得点変動
 
                 let ctx = evaluate_hand_tsumo(stg, &self.ura_dora_wall).unwrap();
-                let (_, mut non_dealer, mut dealer) = ctx.points;
+                let (total, mut non_dealer, mut dealer) = ctx.points;
+
+                // 責任払い(包): 責任者が該当役満分をロン和了と同額で支払い,残りを通常のツモ和了として分配
+                let pao = get_pao(&stg.players[turn], &ctx);
+                if let Some((ps, pt)) = pao {
+                    let t = ctx.yakuman_times as i32;
+                    let pao_point = total * pt as i32 / t;
+                    d_scores[ps] -= pao_point;
+                    d_scores[turn] += pao_point;
+                    non_dealer = non_dealer * (t - pt as i32) / t;
+                    dealer = dealer * (t - pt as i32) / t;
+                }
 
-                // 積み棒
-                non_dealer += honba as i32 * 100;
-                dealer += honba as i32 * 100;
+                // 三麻(北家折半): 不在の北家(子)の支払いを残りの2人で折半
+                // ツモ損の場合は北家の支払い分がそのまま無くなる
+                if stg.is_3p && !self.rule.tsumo_loss {
+                    let half = (non_dealer / 2 + 99) / 100 * 100;
+                    non_dealer += half;
+                    dealer += half;
+                }
+
+                // 積み棒 (責任払いの場合は責任者が全額支払い)
+                if let Some((ps, _)) = pao {
+                    let honba_point = honba as i32 * 100 * (n_seat as i32 - 1);
+                    d_scores[ps] -= honba_point;
+                    d_scores[turn] += honba_point;
+                } else {
+                    non_dealer += honba as i32 * 100;
+                    dealer += honba as i32 * 100;
+                }
 
-                for s in 0..SEAT {
+                for s in 0..n_seat {
                     if s != turn {
                         if !stg.is_dealer(s) {
                             // 子の支払い
@@ -559,6 +886,8 @@ impl MahjongEngine {
                 if !stg.is_dealer(turn) {
                     honba = 0;
                     need_dealer_change = true;
+                } else {
+                    can_yame = self.rule.agari_yame;
                 }
 
                 let contexts = vec![(turn, d_scores, ctx)];
@@ -585,6 +914,15 @@ impl MahjongEngine {
                     d_scores[turn] -= total; // 直撃を受けたプレイヤー
                     d_scores[s] += total; // 和了ったプレイヤー
 
+                    // 責任払い(包): 該当役満分の半額を責任者が負担
+                    if let Some((ps, pt)) = get_pao(&stg.players[s], &ctx) {
+                        if ps != turn {
+                            let half = total * pt as i32 / ctx.yakuman_times as i32 / 2;
+                            d_scores[turn] += half;
+                            d_scores[ps] -= half;
+                        }
+                    }
+
                     // 積み棒&供託(上家取り)
                     if s == s0 {
                         d_scores[turn] -= honba as i32 * 300;
@@ -603,6 +941,7 @@ impl MahjongEngine {
                 }
                 // 和了が子しかいない場合は親交代
                 need_dealer_change = seats.iter().all(|&s| !stg.is_dealer(s));
+                can_yame = !need_dealer_change && self.rule.agari_yame;
 
                 let ura_doras = self.ura_dora_wall[0..stg.doras.len()].to_vec();
                 self.handle_event(Event::win(ura_doras, contexts));
@@ -613,7 +952,7 @@ impl MahjongEngine {
                         // 聴牌集計
                         let mut tenpais = [false; SEAT];
                         let mut n_tenpai = 0;
-                        for s in 0..SEAT {
+                        for s in 0..n_seat {
                             tenpais[s] = !stg.players[s].win_tiles.is_empty();
                             if tenpais[s] {
                                 n_tenpai += 1;
@@ -621,21 +960,62 @@ impl MahjongEngine {
                         }
 
                         // 流局時の聴牌人数による得点変動
-                        let (pay, recv) = match n_tenpai {
-                            0 => (0, 0), // 全員ノーテン
-                            1 => (1000, 3000),
-                            2 => (1500, 1500),
-                            3 => (3000, 1000),
-                            4 => (0, 0), // 全員聴牌
-                            _ => panic!(),
+                        let (pay, recv) = if stg.is_3p {
+                            match n_tenpai {
+                                0 => (0, 0), // 全員ノーテン
+                                1 => (1000, 2000),
+                                2 => (2000, 1000),
+                                3 => (0, 0), // 全員聴牌
+                                _ => panic!(),
+                            }
+                        } else {
+                            match n_tenpai {
+                                0 => (0, 0), // 全員ノーテン
+                                1 => (1000, 3000),
+                                2 => (1500, 1500),
+                                3 => (3000, 1000),
+                                4 => (0, 0), // 全員聴牌
+                                _ => panic!(),
+                            }
                         };
 
                         // プレイヤーごとの得点変動
                         let mut d_scores = [0; SEAT];
-                        for s in 0..SEAT {
+                        for s in 0..n_seat {
                             d_scores[s] = if tenpais[s] { recv } else { -pay };
                         }
 
+                        // 流し満貫: 聴牌料の代わりに満貫のツモ和了と同じ点数を受け取る
+                        let mut type_ = DrawType::Kouhaiheikyoku;
+                        for s in 0..n_seat {
+                            if !is_nagashimangan(&stg.players[s]) {
+                                continue;
+                            }
+                            if type_ != DrawType::Nagashimangan {
+                                type_ = DrawType::Nagashimangan;
+                                d_scores = [0; SEAT];
+                            }
+
+                            let (_, mut non_dealer, mut dealer) =
+                                get_points(stg.is_dealer(s), 30, 5, 0, &self.rule);
+                            if stg.is_3p && !self.rule.tsumo_loss {
+                                let half = (non_dealer / 2 + 99) / 100 * 100;
+                                non_dealer += half;
+                                dealer += half;
+                            }
+                            for s2 in 0..n_seat {
+                                if s2 != s {
+                                    let p = if stg.is_dealer(s2) {
+                                        dealer
+                                    } else {
+                                        non_dealer
+                                    };
+                                    d_scores[s2] -= p;
+                                    d_scores[s] += p;
+                                }
+                            }
+                        }
+
                         let mut hands = [vec![], vec![], vec![], vec![]];
                         for s in 0..SEAT {
                             if tenpais[s] {
@@ -643,14 +1023,33 @@ impl MahjongEngine {
                             }
                         }
 
-                        let event = Event::draw(DrawType::Kouhaiheikyoku, hands, tenpais, d_scores);
+                        let event = Event::draw(type_, hands, tenpais, d_scores);
                         self.handle_event(event);
                         need_dealer_change = !tenpais[kyoku];
+                        can_yame = !need_dealer_change && self.rule.tenpai_yame;
                     }
                     _ => {
-                        let hands = [vec![], vec![], vec![], vec![]]; // TODO
-                        let event = Event::draw(*type_, hands, [false; SEAT], [0; SEAT]);
+                        // 途中流局: 九種九牌は宣言者, 四家立直は全員, 三家和は和了者の手牌を公開
+                        let mut hands = [vec![], vec![], vec![], vec![]];
+                        let mut tenpais = [false; SEAT];
+                        for s in 0..n_seat {
+                            let is_shown = match type_ {
+                                DrawType::Kyushukyuhai => s == turn,
+                                DrawType::Suuchariichi => true,
+                                DrawType::Sanchaho => s != turn,
+                                _ => false,
+                            };
+                            if is_shown {
+                                hands[s] = tiles_from_tile_table(&stg.players[s].hand);
+                                tenpais[s] = *type_ != DrawType::Kyushukyuhai;
+                            }
+                        }
+
+                        let event = Event::draw(*type_, hands, tenpais, [0; SEAT]);
                         self.handle_event(event);
+
+                        // 親は連荘, 供託(流局直前に成立したリーチを含む)は次局に持ち越し
+                        kyoutaku = self.get_stage().kyoutaku;
                     }
                 }
                 honba += 1;
@@ -660,7 +1059,7 @@ impl MahjongEngine {
         // 親交代
         if need_dealer_change {
             kyoku += 1;
-            if kyoku == SEAT {
+            if kyoku == n_seat {
                 kyoku = 0;
                 bakaze += 1;
             }
@@ -678,14 +1077,26 @@ impl MahjongEngine {
         };
 
         // 対戦終了判定
-        if bakaze == self.mode {
+        // オーラス(延長戦中は毎局)で親が交代する場合,または親の和了止め・聴牌止めの場合に終局
+        // 延長戦ありの場合は返し点に達したプレイヤーがいるまで1場まで延長
+        let n_bakaze = self.mode % 10; // 延長戦を除く場数
+        let is_last = stg.bakaze * n_seat + stg.kyoku + 1 >= n_bakaze * n_seat;
+        let is_reached = !self.rule.extension
+            || (0..n_seat).any(|s| stg.players[s].score >= self.rule.return_score);
+        let is_yame = can_yame && stg.players[stg.kyoku].rank == 0;
+        if is_last && is_reached && (need_dealer_change || is_yame) {
             self.is_end = true;
         }
+        if bakaze > n_bakaze || bakaze == SEAT {
+            self.is_end = true; // 延長戦の終了
+        }
 
         // 飛びによる対戦終了
-        for s in 0..SEAT {
-            if stg.players[s].score < 0 {
-                self.is_end = true;
+        if self.rule.tobi {
+            for s in 0..SEAT {
+                if stg.players[s].score < 0 {
+                    self.is_end = true;
+                }
             }
         }
 
@@ -693,7 +1104,23 @@ impl MahjongEngine {
     }
 
     fn do_event_end(&mut self) {
-        self.handle_event(Event::end());
+        let stg = self.get_stage();
+        let n_seat = stg.get_seat_count();
+
+        // 残った供託の精算
+        let mut points = [0; SEAT];
+        if self.rule.kyoutaku_top {
+            let top = (0..n_seat).find(|&s| stg.players[s].rank == 0).unwrap();
+            points[top] += self.kyoku_next.kyoutaku as Point * 1000;
+        }
+
+        let mut scores = stg.get_scores();
+        for s in 0..SEAT {
+            scores[s] += points[s];
+        }
+        let ranks = calc_ranks(&scores, n_seat);
+        let final_points = calc_final_points(&scores, &ranks, n_seat, &self.rule);
+        self.handle_event(Event::end(points, ranks, final_points));
     }
 
     fn draw_tile(&mut self) -> Tile {
@@ -722,14 +1149,14 @@ impl MahjongEngine {
 
     fn check_suufuurenda(&mut self) {
         let stg = self.get_stage();
-        if stg.left_tile_count != 66 {
+        if stg.is_3p || stg.left_tile_count != 66 {
             return;
         }
 
         let mut discards = vec![];
         for s in 0..SEAT {
             let pl = &stg.players[s];
-            if !pl.melds.is_empty() {
+            if !pl.melds.is_empty() || pl.discards.len() != 1 {
                 return;
             }
             discards.push(pl.discards[0].tile);
@@ -794,15 +1221,50 @@ impl MahjongEngine {
 }
 
 // [Utility]
-pub fn create_wall(seed: u64) -> Vec<Tile> {
+// 終局時の得点と順位から最終ポイント(返し点との差 + ウマ + オカ)を計算
+pub fn calc_final_points(
+    scores: &[Score; SEAT],
+    ranks: &[usize; SEAT],
+    n_seat: usize,
+    rule: &Rule,
+) -> [Point; SEAT] {
+    let oka = (rule.return_score - rule.initial_score) * n_seat as Point;
+    let uma: &[Point] = if n_seat == 3 { &rule.uma3 } else { &rule.uma };
+    let mut final_points = [0; SEAT];
+    for s in 0..n_seat {
+        final_points[s] = scores[s] - rule.return_score + uma[ranks[s]];
+        if ranks[s] == 0 {
+            final_points[s] += oka;
+        }
+    }
+    final_points
+}
+
+// 流し満貫 (捨て牌がすべて么九牌かつ鳴かれていない)
+fn is_nagashimangan(pl: &Player) -> bool {
+    !pl.discards.is_empty()
+        && pl
+            .discards
+            .iter()
+            .all(|d| d.tile.is_end() && d.meld.is_none())
+}
+
+pub fn create_wall(seed: u64, is_3p: bool, rule: &Rule) -> Vec<Tile> {
     let mut wall = Vec::new();
     for ti in 0..TYPE {
         for ni in 1..TNUM {
             if ti == TZ && ni > DR {
                 break;
             }
+            if is_3p && ti == TM && 1 < ni && ni < 9 {
+                continue; // 三麻は萬子の2~8を使用しない
+            }
             for n in 0..TILE {
-                let ni2 = if ti != TZ && ni == 5 && n == 0 { 0 } else { ni }; // 赤5
+                let ni2 = if ti != TZ && ni == 5 && n < rule.red5[ti] {
+                    0 // 赤5
+                } else {
+                    ni
+                };
                 wall.push(Tile(ti, ni2));
             }
         }
@@ -812,3 +1274,480 @@ pub fn create_wall(seed: u64) -> Vec<Tile> {
     wall.shuffle(&mut rng);
     wall
 }
+
+// [Test Utility]
+// 通知されたEventを記録するListener (テスト用)
+#[cfg(test)]
+struct EventRecorder(std::sync::Arc<std::sync::Mutex<Vec<Event>>>);
+
+#[cfg(test)]
+impl Listener for EventRecorder {
+    fn notify_event(&mut self, _stg: &Stage, event: &Event) {
+        // EventはCloneを実装していないのでjsonを経由して複製
+        let e = serde_json::from_value(json!(event)).unwrap();
+        self.0.lock().unwrap().push(e);
+    }
+}
+
+// 局開始イベントを適用したエンジンを生成 (テスト用)
+// 以降の処理はhandle_event, do_call_operation等を直接呼び出して進める
+#[cfg(test)]
+fn start_test_kyoku(names: [&str; SEAT], event: EventNew) -> MahjongEngine {
+    let actors = names.map(create_actor);
+    let mut eng = MahjongEngine::new(0, event.mode, &event.rule, actors, vec![]);
+    eng.do_event_begin();
+    eng.handle_event(Event::New(event));
+    eng
+}
+
+// start_test_kyokuと同じ (通知されたEventを記録)
+#[cfg(test)]
+fn start_recorded_test_kyoku(
+    names: [&str; SEAT],
+    event: EventNew,
+) -> (MahjongEngine, std::sync::Arc<std::sync::Mutex<Vec<Event>>>) {
+    let events = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    let actors = names.map(create_actor);
+    let listeners: Vec<Box<dyn Listener>> = vec![Box::new(EventRecorder(events.clone()))];
+    let mut eng = MahjongEngine::new(0, event.mode, &event.rule, actors, listeners);
+    eng.do_event_begin();
+    eng.handle_event(Event::New(event));
+    (eng, events)
+}
+
+// 局を精算して各座席の得点変動を返却 (テスト用)
+#[cfg(test)]
+fn settle_test_kyoku(eng: &mut MahjongEngine) -> [Point; SEAT] {
+    let before = eng.get_stage().get_scores();
+    eng.do_event_win_draw();
+    let after = eng.get_stage().get_scores();
+    [0, 1, 2, 3].map(|s| after[s] - before[s])
+}
+
+#[cfg(test)]
+fn take_last_event(events: &std::sync::Arc<std::sync::Mutex<Vec<Event>>>) -> Event {
+    events.lock().unwrap().pop().unwrap()
+}
+
+#[test]
+fn test_nagashimangan() {
+    let discard = |tile, meld| crate::model::Discard {
+        step: 0,
+        tile: tile,
+        drawn: false,
+        meld: meld,
+    };
+
+    let mut pl = Player::default();
+    assert!(!is_nagashimangan(&pl));
+    pl.discards.push(discard(Tile(TM, 1), None));
+    pl.discards.push(discard(Tile(TZ, DW), None));
+    pl.discards.push(discard(Tile(TS, 9), None));
+    assert!(is_nagashimangan(&pl));
+
+    // 鳴かれた么九牌がある
+    pl.discards[1].meld = Some((1, 0));
+    assert!(!is_nagashimangan(&pl));
+
+    // 中張牌がある
+    pl.discards[1].meld = None;
+    pl.discards.push(discard(Tile(TP, 5), None));
+    assert!(!is_nagashimangan(&pl));
+}
+
+#[test]
+fn test_final_points() {
+    let rule = Rule::default();
+
+    // オカ: (30000 - 25000) * 4 = 20000
+    let scores = [40000, 30000, 20000, 10000];
+    let fp = calc_final_points(&scores, &calc_ranks(&scores, SEAT), SEAT, &rule);
+    assert_eq!(fp, [50000, 10000, -20000, -40000]);
+    assert_eq!(fp.iter().sum::<Point>(), 0);
+
+    // 同点の場合は起家に近い座席が上位
+    let scores = [20000, 35000, 10000, 35000];
+    let fp = calc_final_points(&scores, &calc_ranks(&scores, SEAT), SEAT, &rule);
+    assert_eq!(fp, [-20000, 45000, -40000, 15000]);
+
+    // 三麻: ウマはuma3を使用, オカ: (30000 - 25000) * 3 = 15000
+    let scores = [45000, 20000, 10000, 0];
+    let fp = calc_final_points(&scores, &calc_ranks(&scores, 3), 3, &rule);
+    assert_eq!(fp, [45000, -10000, -35000, 0]);
+    assert_eq!(fp.iter().sum::<Point>(), 0);
+
+    // ウマの合計が0でないルールは不正
+    assert!(rule.validate().is_ok());
+    let rule = Rule {
+        uma3: [20000, 10000, -10000],
+        ..Default::default()
+    };
+    assert!(rule.validate().is_err());
+}
+
+#[test]
+fn test_sanchaho() {
+    // 親(座席0)の第1打の5sに座席1,2,3がロン (いずれも断么九)
+    let hands = [
+        "m19p19s19z1234567s5",
+        "m234p456678s3488",
+        "m34588p567s23467",
+        "m678p234345s5666",
+    ];
+    let run = |double_ron: bool, sanchaho: bool| {
+        let mut e = EventNew::from_hands(1, hands);
+        e.rule.double_ron = double_ron;
+        e.rule.sanchaho = sanchaho;
+        let names = ["Nop", "EfficiencyBot", "EfficiencyBot", "EfficiencyBot"];
+        let (mut eng, events) = start_recorded_test_kyoku(names, e);
+        eng.handle_event(Event::discard(0, Tile(TS, 5), true, false));
+        eng.do_call_operation();
+        (eng, events)
+    };
+
+    // 三家和なし: トリロン
+    let (eng, _) = run(true, false);
+    assert!(matches!(&eng.kyoku_result, Some(KyokuResult::Ron(v)) if v == &vec![1, 2, 3]));
+
+    // 三家和なし,ダブロンなし: 頭ハネ
+    let (eng, _) = run(false, false);
+    assert!(matches!(&eng.kyoku_result, Some(KyokuResult::Ron(v)) if v == &vec![1]));
+
+    // 三家和あり: 和了者の手牌を公開して途中流局, 親は連荘
+    let (mut eng, events) = run(false, true);
+    assert!(matches!(
+        eng.kyoku_result,
+        Some(KyokuResult::Draw(DrawType::Sanchaho))
+    ));
+    eng.do_event_win_draw();
+    match take_last_event(&events) {
+        Event::Draw(e) => {
+            assert_eq!(e.type_, DrawType::Sanchaho);
+            assert!(e.hands[0].is_empty());
+            for s in 1..SEAT {
+                assert_eq!(e.hands[s].len(), 13);
+            }
+            assert_eq!(e.tenpais, [false, true, true, true]);
+            assert_eq!(e.points, [0; SEAT]);
+        }
+        e => panic!("unexpected event: {:?}", e),
+    }
+    let next = &eng.kyoku_next;
+    assert_eq!((next.bakaze, next.kyoku, next.honba), (0, 0, 1));
+    assert_eq!(next.scores, [25000; SEAT]);
+}
+
+#[test]
+fn test_kyushukyuhai() {
+    // 南1局1本場 親(座席0)の配牌が九種九牌
+    let mut e = EventNew::from_hands(2, ["m19p19s19z123m2345z7", "", "", ""]);
+    e.bakaze = 1;
+    e.honba = 1;
+    e.kyoutaku = 1;
+    e.scores[3] = 24000;
+    let (mut eng, events) = start_recorded_test_kyoku(["Nop"; SEAT], e);
+    let acts = calc_possible_turn_actions(eng.get_stage(), &None);
+    assert!(acts.contains(&Action::kyushukyuhai()));
+    eng.kyoku_result = Some(KyokuResult::Draw(DrawType::Kyushukyuhai));
+    eng.do_event_win_draw();
+
+    // 宣言者の手牌のみ公開 (聴牌扱いにはしない)
+    match take_last_event(&events) {
+        Event::Draw(e) => {
+            assert_eq!(e.type_, DrawType::Kyushukyuhai);
+            assert_eq!(e.hands[0].len(), 14);
+            for s in 1..SEAT {
+                assert!(e.hands[s].is_empty());
+            }
+            assert_eq!(e.tenpais, [false; SEAT]);
+            assert_eq!(e.points, [0; SEAT]);
+        }
+        e => panic!("unexpected event: {:?}", e),
+    }
+
+    // 親は連荘, 積み棒を加算, 供託は持ち越し
+    let next = &eng.kyoku_next;
+    assert_eq!(
+        (next.bakaze, next.kyoku, next.honba, next.kyoutaku),
+        (1, 0, 2, 1)
+    );
+    assert!(!eng.is_end);
+}
+
+#[test]
+fn test_suuchariichi() {
+    // 東2局1本場 4人目(座席0)のリーチ宣言牌が通った時点で四家立直 (4本目の供託も成立)
+    let hands = [
+        "m123p456s789z1122",
+        "m123p456s789z3344p9",
+        "m789p123s456z5566",
+        "m456p789s123z7712",
+    ];
+    let mut e = EventNew::from_hands(2, hands);
+    e.kyoku = 1;
+    e.honba = 1;
+    let (mut eng, events) = start_recorded_test_kyoku(["Nop"; SEAT], e);
+    for (s, t) in [
+        (1, Tile(TP, 9)),
+        (2, Tile(TS, 9)),
+        (3, Tile(TM, 9)),
+        (0, Tile(TP, 9)),
+    ] {
+        if s != 1 {
+            eng.handle_event(Event::deal(s, t));
+        }
+        eng.handle_event(Event::discard(s, t, true, true));
+    }
+
+    eng.do_call_operation();
+    assert!(matches!(
+        eng.kyoku_result,
+        Some(KyokuResult::Draw(DrawType::Suuchariichi))
+    ));
+    eng.do_event_win_draw();
+
+    // 全員の手牌を公開
+    match take_last_event(&events) {
+        Event::Draw(e) => {
+            assert_eq!(e.type_, DrawType::Suuchariichi);
+            for s in 0..SEAT {
+                assert_eq!(e.hands[s].len(), 13);
+            }
+            assert_eq!(e.tenpais, [true; SEAT]);
+            assert_eq!(e.points, [0; SEAT]);
+        }
+        e => panic!("unexpected event: {:?}", e),
+    }
+
+    // 親(座席1)は連荘, 4本の供託は次局に持ち越し
+    let next = &eng.kyoku_next;
+    assert_eq!(
+        (next.bakaze, next.kyoku, next.honba, next.kyoutaku),
+        (0, 1, 2, 4)
+    );
+    assert_eq!(next.scores, [24000; SEAT]);
+}
+
+#[test]
+fn test_pao_payment() {
+    // 親(座席0)が白(座席1),發(座席2),中(座席3)をポン → 座席3が大三元の責任払い
+    // hand0: 大三元のみ 2m5m待ち, hand1: 大三元+字一色 西単騎
+    // discards: 各ポンの後の親の打牌, win: 和了牌
+    let hand0 = "m123459s9z556677s1";
+    let hand1 = "m19s9z1113556677s1";
+    let run = |hand: &str, discards: [Tile; 3], win: Tile, is_tsumo: bool| {
+        let mut e = EventNew::from_hands(1, [hand, "", "", ""]);
+        e.honba = 2;
+        e.kyoutaku = 1;
+        e.scores[3] = 24000;
+        let mut eng = start_test_kyoku(["EfficiencyBot", "Nop", "Nop", "Nop"], e);
+        eng.handle_event(Event::discard(0, Tile(TS, 1), true, false));
+        for (i, (&ni, &d)) in [DW, DG, DR].iter().zip(discards.iter()).enumerate() {
+            let t = Tile(TZ, ni);
+            eng.handle_event(Event::deal(i + 1, Z8));
+            eng.handle_event(Event::discard(i + 1, t, false, false));
+            eng.handle_event(Event::meld(0, MeldType::Pon, vec![t, t]));
+            eng.handle_event(Event::discard(0, d, false, false));
+        }
+
+        eng.ura_dora_wall = vec![Tile(TM, 1); 5];
+        if is_tsumo {
+            for s in 1..SEAT {
+                eng.handle_event(Event::deal(s, Z8));
+                eng.handle_event(Event::discard(s, Tile(TM, 7), true, false));
+            }
+            eng.handle_event(Event::deal(0, win));
+            eng.do_turn_operation();
+        } else {
+            eng.handle_event(Event::deal(1, Z8));
+            eng.handle_event(Event::discard(1, win, true, false));
+            eng.do_call_operation();
+        }
+        assert!(eng.kyoku_result.is_some());
+        settle_test_kyoku(&mut eng)
+    };
+    let d0 = [Tile(TS, 9), Tile(TM, 9), Tile(TM, 1)];
+    let d1 = [Tile(TS, 9), Tile(TM, 1), Tile(TM, 9)];
+
+    // ツモ: 責任者がロン和了と同額(48000)と積み棒をすべて支払う (供託1000は和了者)
+    let d = run(hand0, d0, Tile(TM, 5), true);
+    assert_eq!(d, [48000 + 600 + 1000, 0, 0, -48000 - 600]);
+
+    // ロン: 放銃者と責任者が折半 (積み棒は放銃者)
+    let d = run(hand0, d0, Tile(TM, 5), false);
+    assert_eq!(d, [48000 + 600 + 1000, -24000 - 600, 0, -24000]);
+
+    // 複合役満のツモ: 大三元分(48000)のみ責任者, 字一色分は通常のツモ和了として分配
+    let d = run(hand1, d1, Tile(TZ, WW), true);
+    assert_eq!(
+        d,
+        [96000 + 600 + 1000, -16000, -16000, -48000 - 16000 - 600]
+    );
+
+    // 複合役満のロン: 大三元分の半額(24000)のみ責任者に移動
+    let d = run(hand1, d1, Tile(TZ, WW), false);
+    assert_eq!(d, [96000 + 600 + 1000, -72000 - 600, 0, -24000]);
+}
+
+#[test]
+fn test_nagashimangan_payment() {
+    // 座席2のみ聴牌 (2s5s待ち), 座席1の1巡目の打牌は1m
+    // t0: 親(座席0)の2巡目の打牌, d1: 座席1の2巡目の打牌
+    let run = |is_3p: bool, tsumo_loss: bool, t0: Tile, d1: Tile| {
+        let (mode, n_seat, hand2) = if is_3p {
+            (11, 3, "m111p456678s3488")
+        } else {
+            (1, SEAT, "m234p456678s3488")
+        };
+        let mut e = EventNew::from_hands(mode, ["", "", hand2, ""]);
+        e.rule.tsumo_loss = tsumo_loss;
+        let mut eng = start_test_kyoku(["Nop"; SEAT], e);
+        let draws = [[Z8, Z8, Tile(TP, 3), Z8], [Z8, Z8, Tile(TP, 6), Z8]];
+        let discards = [
+            [Tile(TM, 9), Tile(TM, 1), Tile(TP, 3), Tile(TM, 4)],
+            [t0, d1, Tile(TP, 6), Tile(TP, 2)],
+        ];
+        for i in 0..2 {
+            for s in 0..n_seat {
+                if i != 0 || s != 0 {
+                    eng.handle_event(Event::deal(s, draws[i][s]));
+                }
+                eng.handle_event(Event::discard(s, discards[i][s], s == 2, false));
+            }
+        }
+
+        eng.kyoku_result = Some(KyokuResult::Draw(DrawType::Kouhaiheikyoku));
+        settle_test_kyoku(&mut eng)
+    };
+    let (s5, p1, m3, ww) = (Tile(TS, 5), Tile(TP, 1), Tile(TM, 3), Tile(TZ, WW));
+
+    // 流し満貫なし: 聴牌料
+    assert_eq!(run(false, true, s5, m3), [-1000, -1000, 3000, -1000]);
+
+    // 子の流し満貫: 聴牌料の代わりに子の満貫ツモ(親4000,子2000)
+    assert_eq!(run(false, true, s5, ww), [-4000, 8000, -2000, -2000]);
+
+    // 親子の流し満貫: 親の満貫ツモ(4000オール)と子の満貫ツモの合計
+    assert_eq!(run(false, true, p1, ww), [8000, 4000, -6000, -6000]);
+
+    // 三麻(ツモ損): 北家の支払い分(2000)は無くなる
+    assert_eq!(run(true, true, s5, ww), [-4000, 6000, -2000, 0]);
+
+    // 三麻(北家折半): 北家の支払い分を100点単位に切り上げて折半 (2000 / 2 = 1000)
+    assert_eq!(run(true, false, s5, ww), [-5000, 8000, -3000, 0]);
+
+    // 三麻(北家折半)の親子の流し満貫: 親の満貫ツモは(4000 + 2000)オール
+    assert_eq!(run(true, false, p1, ww), [7000, 2000, -9000, 0]);
+}
+
+#[test]
+fn test_create_wall_3p() {
+    let rule = Rule::default();
+    assert_eq!(create_wall(1, false, &rule).len(), 136);
+
+    // 三麻: 萬子の2~8を除く108枚 (赤5は筒子,索子のみ)
+    let wall = create_wall(1, true, &rule);
+    assert_eq!(wall.len(), 108);
+    assert!(!wall.iter().any(|t| t.0 == TM && t.1 != 1 && t.1 != 9));
+    assert_eq!(wall.iter().filter(|&&t| t == Tile(TM, 1)).count(), 4);
+    assert_eq!(wall.iter().filter(|&&t| t == Tile(TM, 9)).count(), 4);
+    assert_eq!(wall.iter().filter(|&&t| t == Tile(TZ, WN)).count(), 4);
+    assert_eq!(wall.iter().filter(|t| t.1 == 0).count(), 2);
+}
+
+#[test]
+fn test_3p_tsumo_payment() {
+    // 三麻 座席1(子)の平和ツモ 20符2翻 (子400,親700)
+    let run = |tsumo_loss: bool| {
+        let mut e = EventNew::from_hands(11, ["", "p123456s2245789", "", ""]);
+        e.doras = vec![Tile(TZ, WE)];
+        e.rule.tsumo_loss = tsumo_loss;
+        let mut eng = start_test_kyoku(["Nop", "EfficiencyBot", "Nop", "Nop"], e);
+        eng.handle_event(Event::discard(0, Tile(TM, 9), true, false));
+        eng.handle_event(Event::deal(1, Tile(TS, 6)));
+
+        eng.ura_dora_wall = vec![Tile(TM, 1); 5];
+        eng.do_turn_operation();
+        assert!(matches!(eng.kyoku_result, Some(KyokuResult::Tsumo)));
+        settle_test_kyoku(&mut eng)
+    };
+
+    // ツモ損: 北家の支払い分(400)は無くなる
+    assert_eq!(run(true), [-700, 1100, -400, 0]);
+
+    // 北家折半: 北家の支払い分を100点単位に切り上げて折半 (400 / 2 = 200)
+    assert_eq!(run(false), [-900, 1500, -600, 0]);
+}
+
+#[test]
+fn test_atamahane_tobi() {
+    // 座席2の5sに座席1,3がロン (いずれも断么九)
+    let run = |double_ron: bool, tobi: bool| {
+        let hands = ["", "m234p456678s3488", "", "m678p234345s5666"];
+        let mut e = EventNew::from_hands(1, hands);
+        e.scores[2] = 1000;
+        e.rule.double_ron = double_ron;
+        e.rule.tobi = tobi;
+        let names = ["Nop", "EfficiencyBot", "Nop", "EfficiencyBot"];
+        let mut eng = start_test_kyoku(names, e);
+        eng.handle_event(Event::discard(0, Tile(TP, 7), true, false));
+        eng.handle_event(Event::deal(1, Tile(TP, 1)));
+        eng.handle_event(Event::discard(1, Tile(TP, 1), true, false));
+        eng.handle_event(Event::deal(2, Z8));
+        eng.handle_event(Event::discard(2, Tile(TS, 5), true, false));
+
+        eng.ura_dora_wall = vec![Tile(TM, 1); 5];
+        eng.do_call_operation();
+        let rons = match &eng.kyoku_result {
+            Some(KyokuResult::Ron(v)) => v.clone(),
+            r => panic!("unexpected result: {:?}", r),
+        };
+        eng.do_event_win_draw();
+        (rons, eng.is_end)
+    };
+
+    // ダブロン
+    assert_eq!(run(true, true).0, vec![1, 3]);
+    // 頭ハネ: 放銃者(座席2)の下家側から近い座席3のみ和了
+    assert_eq!(run(false, true).0, vec![3]);
+
+    // 飛び: 持ち点が0未満になった場合に終局
+    assert!(run(false, true).1);
+    assert!(!run(false, false).1);
+}
+
+#[test]
+fn test_suufuurenda() {
+    // 全員の第1打が同じ風牌 (d3: 座席3の第1打)
+    let run = |d3: Tile| {
+        let e = EventNew::from_hands(1, ["", "", "", ""]);
+        let mut eng = start_test_kyoku(["Nop"; SEAT], e);
+        for s in 0..SEAT {
+            if s != 0 {
+                eng.handle_event(Event::deal(s, Z8));
+            }
+            let t = if s == 3 { d3 } else { Tile(TZ, WE) };
+            eng.handle_event(Event::discard(s, t, false, false));
+
+            // 全員の打牌が揃うまでは判定しない
+            eng.do_call_operation();
+            if s < 3 {
+                assert!(eng.kyoku_result.is_none());
+            }
+        }
+        eng
+    };
+
+    let mut eng = run(Tile(TZ, WE));
+    assert!(matches!(
+        eng.kyoku_result,
+        Some(KyokuResult::Draw(DrawType::Suufuurenda))
+    ));
+    assert_eq!(settle_test_kyoku(&mut eng), [0; SEAT]);
+    let next = &eng.kyoku_next;
+    assert_eq!((next.kyoku, next.honba), (0, 1));
+
+    // 座席3の第1打が異なる風牌
+    let eng = run(Tile(TZ, WS));
+    assert!(eng.kyoku_result.is_none());
+}
@@ -0,0 +1,260 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use rand::prelude::*;
+use serde_json::json;
+
+use super::engine::MahjongEngine;
+use super::replay::read_records;
+use crate::actor::create_actor;
+use crate::controller::*;
+use crate::convert::feature::*;
+use crate::listener::FeatureCollector;
+use crate::model::*;
+use crate::util::common::*;
+
+use crate::{error, warn};
+
+// [App]
+// 自己対戦または牌譜から(特徴量, 選択可能な操作, 選択した操作)を抽出して.npy形式で出力
+// 出力先: {out_dir}/{chunk番号}.{features,masks,actions,seats}.npy, {out_dir}/meta.json
+#[derive(Debug)]
+pub struct FeatureApp {
+    file_path: String,
+    out_dir: String,
+    chunk_size: usize, // 1ファイルあたりの最大サンプル数
+    seed: u64,
+    mode: usize,
+    n_game: u32,
+    rule: Rule,
+    names: [String; SEAT], // actor names
+}
+
+impl FeatureApp {
+    pub fn new(args: Vec<String>) -> Self {
+        use std::process::exit;
+
+        let mut app = Self {
+            file_path: String::new(),
+            out_dir: "feature".to_string(),
+            chunk_size: 20000,
+            seed: 0,
+            mode: 1,
+            n_game: 0,
+            rule: Rule::default(),
+            names: [
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+                "".to_string(),
+            ],
+        };
+
+        let mut rule_path = "".to_string();
+        let mut it = args.iter();
+        while let Some(s) = it.next() {
+            match s.as_str() {
+                "-f" => app.file_path = next_value(&mut it, "-f"),
+                "-o" => app.out_dir = next_value(&mut it, "-o"),
+                "-chunk" => app.chunk_size = next_value(&mut it, "-chunk"),
+                "-s" => app.seed = next_value(&mut it, "-s"),
+                "-m" => app.mode = next_value(&mut it, "-m"),
+                "-r" => rule_path = next_value(&mut it, "-r"),
+                "-g" => app.n_game = next_value(&mut it, "-g"),
+                "-0" => app.names[0] = next_value(&mut it, "-0"),
+                "-1" => app.names[1] = next_value(&mut it, "-1"),
+                "-2" => app.names[2] = next_value(&mut it, "-2"),
+                "-3" => app.names[3] = next_value(&mut it, "-3"),
+                opt => {
+                    error!("unknown option: {}", opt);
+                    exit(0);
+                }
+            }
+        }
+
+        if app.file_path.is_empty() && app.n_game == 0 {
+            error!("file(-f) or game count(-g) not specified");
+            exit(0);
+        }
+        if app.chunk_size == 0 {
+            error!("invalid chunk size: 0");
+            exit(0);
+        }
+
+        if app.mode > 10 && app.names[3].is_empty() {
+            app.names[3] = "Null".to_string(); // 三麻は座席3を使用しない
+        }
+
+        if !rule_path.is_empty() {
+            app.rule = Rule::from_file(&rule_path).unwrap_or_else(error_exit);
+        }
+
+        if app.file_path.is_empty() && app.seed == 0 {
+            app.seed = unixtime_now();
+            warn!(
+                "Random seed is not specified. Unix timestamp '{}' is used as seed.",
+                app.seed
+            );
+        }
+
+        app
+    }
+
+    pub fn run(&mut self) {
+        let dataset = Arc::new(Mutex::new(FeatureDataset::new()));
+        let mut writer = ChunkWriter::new(&self.out_dir, self.chunk_size);
+        if !self.file_path.is_empty() {
+            self.run_records(&dataset, &mut writer);
+        } else {
+            self.run_games(&dataset, &mut writer);
+        }
+        writer
+            .flush(&mut dataset.lock().unwrap())
+            .unwrap_or_else(error_exit);
+        writer.write_meta().unwrap_or_else(error_exit);
+        println!(
+            "{} samples, {} chunks -> {}",
+            writer.n_sample, writer.n_chunk, self.out_dir
+        );
+    }
+
+    // 牌譜(ファイルまたはディレクトリ)から抽出
+    fn run_records(&self, dataset: &Arc<Mutex<FeatureDataset>>, writer: &mut ChunkWriter) {
+        let path = Path::new(&self.file_path);
+        let paths: Vec<PathBuf> = if path.is_dir() {
+            get_paths(path)
+                .unwrap_or_else(error_exit)
+                .into_iter()
+                .filter(|p| match p.extension() {
+                    Some(ext) => ext == "json" || ext == "mjson" || ext == "mjlog" || ext == "xml",
+                    None => false,
+                })
+                .collect()
+        } else {
+            vec![path.to_path_buf()]
+        };
+
+        for p in paths {
+            let contents = std::fs::read_to_string(&p).unwrap_or_else(error_exit);
+            let records = match read_records(&contents) {
+                Ok(r) => r,
+                Err(e) => {
+                    warn!("{}: {}", p.display(), e);
+                    continue;
+                }
+            };
+            for record in records {
+                let actors = [0, 1, 2, 3].map(|_| create_actor("Nop"));
+                let listeners: Vec<Box<dyn Listener>> =
+                    vec![Box::new(FeatureCollector::new(dataset.clone()))];
+                let mut ctrl = StageController::new(actors, listeners);
+                for e in &record {
+                    ctrl.handle_event(e);
+                }
+            }
+            writer
+                .write_full_chunks(&mut dataset.lock().unwrap())
+                .unwrap_or_else(error_exit);
+        }
+    }
+
+    // 自己対戦から抽出
+    fn run_games(&self, dataset: &Arc<Mutex<FeatureDataset>>, writer: &mut ChunkWriter) {
+        println!("seed: {}", self.seed);
+        let mut actors = [0, 1, 2, 3].map(|s| create_actor(&self.names[s]));
+        for (s, a) in actors.iter().enumerate() {
+            println!("actor{}: {:?}", s, a);
+        }
+
+        let mut rng: StdRng = SeedableRng::seed_from_u64(self.seed);
+        for _ in 0..self.n_game {
+            for a in actors.iter_mut() {
+                a.reset();
+            }
+            let listeners: Vec<Box<dyn Listener>> =
+                vec![Box::new(FeatureCollector::new(dataset.clone()))];
+            let mut game =
+                MahjongEngine::new(rng.next_u64(), self.mode, &self.rule, actors, listeners);
+            game.run();
+            actors = game.take_actors();
+            writer
+                .write_full_chunks(&mut dataset.lock().unwrap())
+                .unwrap_or_else(error_exit);
+        }
+    }
+}
+
+// datasetをchunk_size単位でファイルに書き出し
+struct ChunkWriter {
+    out_dir: String,
+    chunk_size: usize,
+    n_chunk: usize,
+    n_sample: usize,
+}
+
+impl ChunkWriter {
+    fn new(out_dir: &str, chunk_size: usize) -> Self {
+        Self {
+            out_dir: out_dir.to_string(),
+            chunk_size: chunk_size,
+            n_chunk: 0,
+            n_sample: 0,
+        }
+    }
+
+    // chunk_size以上のサンプルがある場合に書き出し (端数はdatasetに残す)
+    fn write_full_chunks(&mut self, dataset: &mut FeatureDataset) -> Result<(), String> {
+        while dataset.len() >= self.chunk_size {
+            let rest = dataset.split_off(self.chunk_size);
+            self.flush(dataset)?;
+            *dataset = rest;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self, dataset: &mut FeatureDataset) -> Result<(), String> {
+        if dataset.is_empty() {
+            return Ok(());
+        }
+        dataset.write_npy(&self.out_dir, &format!("{:05}", self.n_chunk))?;
+        self.n_chunk += 1;
+        self.n_sample += dataset.len();
+        dataset.clear();
+        Ok(())
+    }
+
+    fn write_meta(&self) -> Result<(), String> {
+        let meta = json!({
+            "version": FEATURE_VERSION,
+            "n_channel": N_CHANNEL,
+            "n_tile": N_TILE,
+            "action_size": ACTION_SIZE,
+            "n_chunk": self.n_chunk,
+            "n_sample": self.n_sample,
+        });
+        let path = format!("{}/meta.json", self.out_dir);
+        std::fs::create_dir_all(&self.out_dir).map_err(|e| e.to_string())?;
+        std::fs::write(&path, serde_json::to_string_pretty(&meta).unwrap())
+            .map_err(|e| format!("{}: {}", e, path))
+    }
+}
+
+#[test]
+fn test_feature_collector() {
+    let dataset = Arc::new(Mutex::new(FeatureDataset::new()));
+    let actors = [0, 1, 2, 3].map(|_| create_actor("EfficiencyBot"));
+    let listeners: Vec<Box<dyn Listener>> = vec![Box::new(FeatureCollector::new(dataset.clone()))];
+    let mut game = MahjongEngine::new(1, 1, &Rule::default(), actors, listeners);
+    game.run();
+
+    // 選択した操作は選択可能な操作に含まれる
+    let ds = dataset.lock().unwrap();
+    assert!(ds.len() > 0);
+    assert_eq!(ds.features.len(), ds.len() * FEATURE_SIZE);
+    for i in 0..ds.len() {
+        let a = ds.actions[i] as usize;
+        let mask = &ds.masks[i * ACTION_SIZE..(i + 1) * ACTION_SIZE];
+        assert_eq!(mask[a], 1, "sample {}: action {}", i, a);
+        assert!(mask.iter().filter(|&&m| m == 1).count() > 1);
+    }
+}
@@ -0,0 +1,290 @@
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use super::engine::MahjongEngine;
+use crate::actor::{create_actor, fallback_action, is_valid_action};
+use crate::controller::*;
+use crate::model::*;
+
+use crate::error;
+
+// [MahjongEnv]
+// 強化学習用のgym形式の環境
+// 1つの座席(学習対象)の操作を外部から与え, その他の座席は指定したActorが操作する
+// 試合はスレッドで実行し, 学習対象の座席に操作が要求される度にstepの呼び出しを待機する
+//
+//   let mut env = MahjongEnv::new(1, &Rule::default(), 0, actors)?;
+//   let mut obs = env.reset(seed);
+//   loop {
+//       let act = ...; // obs.legal_actions から選択
+//       let (o, reward, done) = env.step(&act)?;
+//       ...
+//   }
+//
+// reward: 前回の観測からの学習対象の座席の得点の増減
+pub struct MahjongEnv {
+    mode: usize,
+    rule: Rule,
+    seat: Seat,
+    actors: Option<[Box<dyn Actor>; SEAT]>, // 学習対象の座席の要素は使用しない (試合中はNone)
+    game: Option<GameThread>,
+    obs: Option<Observation>, // 最新の観測
+    done: bool,
+}
+
+// 学習対象の座席から見た観測
+#[derive(Debug, Clone)]
+pub struct Observation {
+    pub seat: Seat,
+    pub stage: Stage,               // 卓情報 (他家の手牌,ツモ牌は非公開)
+    pub legal_actions: Vec<Action>, // 選択可能な操作 (終局後は空)
+}
+
+struct GameThread {
+    handle: thread::JoinHandle<[Box<dyn Actor>; SEAT]>,
+    act_tx: mpsc::Sender<Action>,
+    obs_rx: mpsc::Receiver<Observation>,
+}
+
+impl MahjongEnv {
+    // mode: 1: 東風戦, 2: 半荘戦, 4: 一荘戦 (三麻の場合は+10)
+    // actors: 学習対象以外の座席のActor
+    pub fn new(
+        mode: usize,
+        rule: &Rule,
+        seat: Seat,
+        mut actors: [Box<dyn Actor>; SEAT],
+    ) -> Result<Self, String> {
+        let n_seat = if mode > 10 { 3 } else { SEAT };
+        if seat >= n_seat {
+            return Err(format!("invalid seat: {}", seat));
+        }
+        if mode > 10 {
+            actors[3] = create_actor("Null"); // 三麻は座席3を使用しない
+        }
+
+        Ok(Self {
+            mode: mode,
+            rule: rule.clone(),
+            seat: seat,
+            actors: Some(actors),
+            game: None,
+            obs: None,
+            done: true,
+        })
+    }
+
+    // シード値seedで新しい試合を開始して最初の観測を返却
+    // 実行中の試合は学習対象の座席をツモ切り,鳴きのスキップで終局まで進めてから破棄する
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.close();
+
+        let (act_tx, act_rx) = mpsc::channel();
+        let (obs_tx, obs_rx) = mpsc::channel();
+        let mut actors = self.actors.take().unwrap();
+        actors[self.seat] = Box::new(EnvActor::new(obs_tx, act_rx));
+        for a in actors.iter_mut() {
+            a.reset();
+        }
+
+        let mode = self.mode;
+        let rule = self.rule.clone();
+        let handle = thread::spawn(move || {
+            let mut game = MahjongEngine::new(seed, mode, &rule, actors, vec![]);
+            game.run();
+            game.take_actors()
+        });
+        self.game = Some(GameThread {
+            handle,
+            act_tx,
+            obs_rx,
+        });
+        self.done = false;
+
+        let obs = self.recv();
+        self.obs = Some(obs.clone());
+        obs
+    }
+
+    // 学習対象の座席が選択可能な操作
+    pub fn legal_actions(&self) -> Vec<Action> {
+        match &self.obs {
+            Some(obs) => obs.legal_actions.clone(),
+            None => vec![],
+        }
+    }
+
+    // 操作を実行して次の観測(学習対象の座席に操作が要求された時点,または終局時)を返却
+    pub fn step(&mut self, action: &Action) -> Result<(Observation, f32, bool), String> {
+        if self.done {
+            return Err("game is not running (call reset)".to_string());
+        }
+        let prev = self.obs.as_ref().unwrap();
+        if !is_valid_action(&prev.stage, self.seat, &prev.legal_actions, action) {
+            return Err(format!(
+                "action={:?} is not contained in legal_actions={:?}",
+                action, prev.legal_actions
+            ));
+        }
+        let prev_score = prev.stage.players[self.seat].score;
+
+        self.game
+            .as_ref()
+            .unwrap()
+            .act_tx
+            .send(action.clone())
+            .unwrap();
+        let obs = self.recv();
+        let reward = (obs.stage.players[obs.seat].score - prev_score) as f32;
+        self.done = obs.legal_actions.is_empty();
+        if self.done {
+            self.close();
+        }
+        self.obs = Some(obs.clone());
+        Ok((obs, reward, self.done))
+    }
+
+    fn recv(&mut self) -> Observation {
+        match self.game.as_ref().unwrap().obs_rx.recv() {
+            Ok(obs) => obs,
+            Err(_) => {
+                // 観測の送信前に試合のスレッドが終了した場合 (panic)
+                let g = self.game.take().unwrap();
+                match g.handle.join() {
+                    Ok(_) => panic!("game finished without end event"),
+                    Err(e) => std::panic::resume_unwind(e),
+                }
+            }
+        }
+    }
+
+    // 試合のスレッドを終了してActorを回収
+    fn close(&mut self) {
+        if let Some(g) = self.game.take() {
+            drop(g.act_tx); // EnvActorは以降の操作を代替の操作で行う
+            drop(g.obs_rx);
+            match g.handle.join() {
+                Ok(mut actors) => {
+                    actors[self.seat] = create_actor("Null");
+                    self.actors = Some(actors);
+                }
+                Err(e) => std::panic::resume_unwind(e),
+            }
+        }
+        self.done = true;
+    }
+}
+
+impl Drop for MahjongEnv {
+    fn drop(&mut self) {
+        if !thread::panicking() {
+            self.close();
+        }
+    }
+}
+
+// [EnvActor]
+// 学習対象の座席のActor
+// 操作の要求をObservationとして環境に送信し, stepで与えられた操作を受信する
+#[derive(Clone)]
+struct EnvActor {
+    config: Config,
+    seat: Seat,
+    obs_tx: mpsc::Sender<Observation>,
+    act_rx: Arc<Mutex<mpsc::Receiver<Action>>>,
+    is_closed: bool, // 環境が破棄された
+}
+
+impl EnvActor {
+    fn new(obs_tx: mpsc::Sender<Observation>, act_rx: mpsc::Receiver<Action>) -> Self {
+        Self {
+            config: Config {
+                name: "Env".to_string(),
+                args: vec![],
+            },
+            seat: NO_SEAT,
+            obs_tx: obs_tx,
+            act_rx: Arc::new(Mutex::new(act_rx)),
+            is_closed: false,
+        }
+    }
+}
+
+impl Actor for EnvActor {
+    fn init(&mut self, seat: Seat) {
+        self.seat = seat;
+    }
+
+    fn select_action(&mut self, stg: &Stage, acts: &Vec<Action>) -> Action {
+        if !self.is_closed {
+            let obs = Observation {
+                seat: self.seat,
+                stage: stg.clone(),
+                legal_actions: acts.clone(),
+            };
+            if self.obs_tx.send(obs).is_ok() {
+                if let Ok(act) = self.act_rx.lock().unwrap().recv() {
+                    return act;
+                }
+            }
+            self.is_closed = true;
+        }
+        fallback_action(stg, self.seat, acts).unwrap_or_else(|e| {
+            error!("{}", e);
+            Action::nop()
+        })
+    }
+
+    fn get_config(&self) -> &Config {
+        &self.config
+    }
+}
+
+impl Listener for EnvActor {
+    fn notify_event(&mut self, stg: &Stage, event: &Event) {
+        if let Event::End(_) = event {
+            let obs = Observation {
+                seat: self.seat,
+                stage: stg.clone(),
+                legal_actions: vec![],
+            };
+            self.obs_tx.send(obs).ok();
+        }
+    }
+}
+
+#[test]
+fn test_env() {
+    let actors = [0, 1, 2, 3].map(|_| create_actor("RandomDiscard"));
+    let mut env = MahjongEnv::new(1, &Rule::default(), 1, actors).unwrap();
+    assert!(env.step(&Action::nop()).is_err());
+
+    for seed in 1..3 {
+        let mut obs = env.reset(seed);
+        let initial_score = obs.stage.players[1].score;
+        let mut total = 0.0;
+        let mut n_step = 0;
+        loop {
+            assert_eq!(obs.legal_actions, env.legal_actions());
+            // ツモ切り,鳴きのスキップ (鳴き後は先頭の打牌可能な牌)
+            let act = fallback_action(&obs.stage, 1, &obs.legal_actions).unwrap();
+            let (o, reward, done) = env.step(&act).unwrap();
+            total += reward;
+            n_step += 1;
+            obs = o;
+            if done {
+                break;
+            }
+        }
+        assert!(n_step > 0);
+        assert!(env.legal_actions().is_empty());
+        assert_eq!(total, (obs.stage.players[1].score - initial_score) as f32);
+        assert!(env.step(&Action::nop()).is_err());
+    }
+
+    // 試合の途中でのreset
+    env.reset(3);
+    let obs = env.reset(4);
+    assert!(!obs.legal_actions.is_empty());
+}
@@ -402,7 +402,15 @@ impl Mahjongsoul {
         }
 
         self.handle_event(Event::new(
-            bakaze, kyoku, honba, kyoutaku, doras, scores, hands, mode,
+            bakaze,
+            kyoku,
+            honba,
+            kyoutaku,
+            doras,
+            scores,
+            hands,
+            mode,
+            Rule::default(),
         ));
     }
 
@@ -495,7 +503,7 @@ impl Mahjongsoul {
             let fu = as_usize(&win["fu"]);
             let fan = if is_yakuman { 0 } else { count };
             let yakuman_times = if is_yakuman { count } else { 0 };
-            let score_title = get_score_title(fu, fan, yakuman_times);
+            let score_title = get_score_title(fu, fan, yakuman_times, &self.get_stage().rule);
             let points = (
                 as_i32(&win["point_rong"]),
                 as_i32(&win["point_zimo_xian"]),
@@ -599,12 +607,14 @@ impl Mahjongsoul {
             }
         }
 
-        self.handle_event(Event::draw(
-            DrawType::Kouhaiheikyoku,
-            hands,
-            tenpais,
-            points,
-        ));
+        // 流し満貫
+        let type_ = if data["liujumanguan"].as_bool() == Some(true) {
+            DrawType::Nagashimangan
+        } else {
+            DrawType::Kouhaiheikyoku
+        };
+
+        self.handle_event(Event::draw(type_, hands, tenpais, points));
     }
 }
 
@@ -0,0 +1,322 @@
+use std::io;
+use std::io::prelude::*;
+use std::net::TcpStream;
+
+use serde_json::json;
+
+use crate::actor::{create_actor, fallback_action};
+use crate::controller::*;
+use crate::convert::mjai::*;
+use crate::listener::{EventWriter, StageSender};
+use crate::model::*;
+use crate::util::common::*;
+use crate::util::server::Server;
+
+use crate::{error, info};
+
+use ActionType::*;
+
+// [App]
+// 外部のmjaiサーバにクライアントとして接続して対戦
+#[derive(Debug)]
+pub struct MjaiClientApp {
+    addr: String,
+    room: String,
+    name: String,
+    write: bool,
+    gui_port: u32,
+    debug: bool,
+    actor_name: String,
+}
+
+impl MjaiClientApp {
+    pub fn new(args: Vec<String>) -> Self {
+        use std::process::exit;
+
+        let mut app = Self {
+            addr: "127.0.0.1:11600".to_string(),
+            room: "default".to_string(),
+            name: "".to_string(),
+            write: false,
+            gui_port: super::GUI_PORT,
+            debug: false,
+            actor_name: "".to_string(),
+        };
+
+        let mut it = args.iter();
+        while let Some(s) = it.next() {
+            match s.as_str() {
+                "-a" => app.addr = next_value(&mut it, "-a"),
+                "-room" => app.room = next_value(&mut it, "-room"),
+                "-name" => app.name = next_value(&mut it, "-name"),
+                "-w" => app.write = true,
+                "-gui-port" => app.gui_port = next_value(&mut it, "-gui-port"),
+                "-d" => app.debug = true,
+                "-0" => app.actor_name = next_value(&mut it, "-0"),
+                opt => {
+                    error!("unknown option: {}", opt);
+                    exit(0);
+                }
+            }
+        }
+
+        app
+    }
+
+    pub fn run(&mut self) {
+        let actor = create_actor(&self.actor_name);
+        println!("actor: {:?}", actor);
+        let name = if self.name.is_empty() {
+            actor.get_config().name.clone()
+        } else {
+            self.name.clone()
+        };
+
+        let mut listeners: Vec<Box<dyn Listener>> = vec![];
+        let server = Server::new_ws_server(&format!("localhost:{}", self.gui_port));
+        listeners.push(Box::new(StageSender::new(server)));
+        if self.write {
+            listeners.push(Box::new(EventWriter::new()));
+        }
+
+        let mut client = MjaiClient::new(actor, listeners);
+        match self.connect(&mut client, &name) {
+            Ok(_) => info!("connection closed"),
+            Err(e) => error!("{}", e),
+        }
+    }
+
+    fn connect(&self, client: &mut MjaiClient, name: &str) -> io::Result<()> {
+        let stream = TcpStream::connect(&self.addr)?;
+        info!("connected to {}", self.addr);
+        let mut writer = stream.try_clone()?;
+        let mut reader = io::BufReader::new(stream);
+
+        let mut buf = String::new();
+        loop {
+            buf.clear();
+            if reader.read_line(&mut buf)? == 0 {
+                return Ok(());
+            }
+            if self.debug {
+                println!("<- {}", buf.trim_end());
+            }
+
+            let record: MjaiEvent = match serde_json::from_str(&buf) {
+                Ok(r) => r,
+                Err(e) => {
+                    error!("{}: {}", e, buf.trim_end());
+                    continue;
+                }
+            };
+            let is_end = matches!(record, MjaiEvent::EndGame { .. });
+
+            let act = match record {
+                MjaiEvent::Hello { .. } => MjaiAction::Join {
+                    name: name.to_string(),
+                    room: self.room.clone(),
+                },
+                _ => client.apply(record),
+            };
+
+            let msg = json!(act).to_string();
+            if self.debug {
+                println!("-> {}", msg);
+            }
+            flush();
+            writer.write_all((msg + "\n").as_bytes())?;
+
+            if is_end {
+                return Ok(());
+            }
+        }
+    }
+}
+
+// [MjaiClient]
+#[derive(Debug)]
+struct MjaiClient {
+    ctrl: StageController,
+    deserializer: MjaiDeserializer,
+    seat: Seat, // my seat
+    actor: Box<dyn Actor>,
+    riichi: Option<Tile>, // reach送信後に打牌する牌
+}
+
+impl MjaiClient {
+    fn new(actor: Box<dyn Actor>, listeners: Vec<Box<dyn Listener>>) -> Self {
+        // start_gameで座席が判明した際にスワップする
+        let nop = create_actor("Nop");
+        let actors: [Box<dyn Actor>; SEAT] = [
+            nop.clone_box(),
+            nop.clone_box(),
+            nop.clone_box(),
+            nop.clone_box(),
+        ];
+        Self {
+            ctrl: StageController::new(actors, listeners),
+            deserializer: MjaiDeserializer::new(),
+            seat: NO_SEAT,
+            actor: actor,
+            riichi: None,
+        }
+    }
+
+    #[inline]
+    fn get_stage(&self) -> &Stage {
+        self.ctrl.get_stage()
+    }
+
+    // サーバから受信したイベントを処理して応答を返却
+    fn apply(&mut self, record: MjaiEvent) -> MjaiAction {
+        if let MjaiEvent::StartGame { id, .. } = record {
+            if self.seat != NO_SEAT {
+                self.ctrl.swap_actor(self.seat, &mut self.actor);
+            }
+            self.seat = id;
+            self.riichi = None;
+            self.ctrl.swap_actor(self.seat, &mut self.actor);
+        }
+
+        match self.deserializer.push_record(record.clone()) {
+            Ok(events) => {
+                for e in &events {
+                    self.ctrl.handle_event(e);
+                }
+            }
+            Err(e) => {
+                error!("{}", e);
+                return MjaiAction::None {};
+            }
+        }
+        if self.seat == NO_SEAT {
+            return MjaiAction::None {};
+        }
+
+        let s = self.seat;
+        match record {
+            MjaiEvent::Tsumo { actor, .. } if actor == s => self.select_turn_action(None),
+            MjaiEvent::Chi {
+                actor, consumed, ..
+            } if actor == s => match tiles_from_mjai(&consumed) {
+                Ok(cs) => self.select_turn_action(Some(Action::chi(cs))),
+                Err(e) => {
+                    error!("{}", e);
+                    MjaiAction::None {}
+                }
+            },
+            MjaiEvent::Pon {
+                actor, consumed, ..
+            } if actor == s => match tiles_from_mjai(&consumed) {
+                Ok(cs) => self.select_turn_action(Some(Action::pon(cs))),
+                Err(e) => {
+                    error!("{}", e);
+                    MjaiAction::None {}
+                }
+            },
+            MjaiEvent::Reach { actor } if actor == s => match self.riichi.take() {
+                Some(t) => self.dahai(t),
+                None => {
+                    error!("riichi tile not selected");
+                    MjaiAction::None {}
+                }
+            },
+            MjaiEvent::Dahai { actor, .. } if actor != s => self.select_call_action(true),
+            // 槍槓, 北抜きに対するロン
+            MjaiEvent::Kakan { actor, .. } | MjaiEvent::Nukidora { actor, .. } if actor != s => {
+                self.select_call_action(false)
+            }
+            _ => MjaiAction::None {},
+        }
+    }
+
+    fn select_turn_action(&mut self, melding: Option<Action>) -> MjaiAction {
+        let s = self.seat;
+        let stg = self.get_stage();
+        if stg.turn != s {
+            return MjaiAction::None {};
+        }
+        let acts = calc_possible_turn_actions(stg, &melding);
+        let act = self.ctrl.select_action(s, &acts);
+        let stg = self.get_stage();
+        match act.0 {
+            Nop => {
+                // ツモ切り, 鳴き後に打牌が選択されなかった場合は喰い替えにならない牌を捨てる
+                match fallback_action(stg, s, &acts) {
+                    Ok(Action(Discard, cs)) => self.dahai(cs[0]),
+                    Ok(_) => match stg.players[s].drawn {
+                        Some(t) => self.dahai(t),
+                        None => MjaiAction::None {},
+                    },
+                    Err(e) => {
+                        error!("{}", e);
+                        MjaiAction::None {}
+                    }
+                }
+            }
+            Discard => self.dahai(act.1[0]),
+            Riichi => {
+                self.riichi = Some(act.1[0]);
+                MjaiAction::Reach { actor: s }
+            }
+            _ => MjaiAction::from_action(stg, s, &act).unwrap_or(MjaiAction::None {}),
+        }
+    }
+
+    fn select_call_action(&mut self, can_meld: bool) -> MjaiAction {
+        let s = self.seat;
+        let acts = &calc_possible_call_actions(self.get_stage(), can_meld)[s];
+        if acts.len() == 1 {
+            return MjaiAction::None {}; // Nop
+        }
+        let act = self.ctrl.select_action(s, acts);
+        MjaiAction::from_action(self.get_stage(), s, &act).unwrap_or(MjaiAction::None {})
+    }
+
+    fn dahai(&self, t: Tile) -> MjaiAction {
+        let pl = &self.get_stage().players[self.seat];
+        let tn = t.to_normal();
+        MjaiAction::Dahai {
+            actor: self.seat,
+            pai: tile_to_mjai(t),
+            tsumogiri: pl.drawn == Some(t) && pl.hand[tn.0][tn.1] == 1,
+        }
+    }
+}
+
+#[test]
+fn test_mjai_client() {
+    // 座席0から見た対局 (他家の配牌, ツモは非公開)
+    let log = r#"{"type":"start_game","id":0,"names":["a","b","c","d"]}
+{"type":"start_kyoku","bakaze":"E","dora_marker":"1s","kyoku":1,"honba":0,"kyotaku":0,"oya":0,"tehais":[["1m","2m","3m","4p","5p","6p","7s","8s","9s","S","C","C","C"],["?","?","?","?","?","?","?","?","?","?","?","?","?"],["?","?","?","?","?","?","?","?","?","?","?","?","?"],["?","?","?","?","?","?","?","?","?","?","?","?","?"]],"scores":[25000,25000,25000,25000]}
+{"type":"tsumo","actor":0,"pai":"W"}
+{"type":"dahai","actor":0,"pai":"W","tsumogiri":true}
+{"type":"tsumo","actor":1,"pai":"?"}
+{"type":"dahai","actor":1,"pai":"C","tsumogiri":false}
+{"type":"pon","actor":0,"target":1,"pai":"C","consumed":["C","C"]}
+{"type":"dahai","actor":0,"pai":"S","tsumogiri":false}
+{"type":"tsumo","actor":1,"pai":"?"}
+{"type":"dahai","actor":1,"pai":"S","tsumogiri":false}"#;
+
+    let actor = create_actor("Nop");
+    let mut client = MjaiClient::new(actor, vec![]);
+    let mut acts = vec![];
+    for line in log.lines() {
+        acts.push(json!(client.apply(serde_json::from_str(line).unwrap())));
+    }
+
+    assert_eq!(client.seat, 0);
+    assert_eq!(
+        acts[2],
+        json!({"type": "dahai", "actor": 0, "pai": "W", "tsumogiri": true})
+    );
+    // Nopは鳴かない
+    assert_eq!(acts[5], json!({"type": "none"}));
+    // ポン後の打牌 (手牌の最後の中は喰い替えになるため南を捨てる)
+    assert_eq!(
+        acts[6],
+        json!({"type": "dahai", "actor": 0, "pai": "S", "tsumogiri": false})
+    );
+    assert_eq!(acts[9], json!({"type": "none"}));
+    assert_eq!(client.get_stage().players[0].melds.len(), 1);
+}
@@ -1,12 +1,20 @@
 mod calculator;
 mod engine;
+mod feature;
+mod gym;
 mod mahjongsoul;
+mod mjai_client;
 mod replay;
+mod tournament;
 
 const MSC_PORT: u32 = 52000;
 const GUI_PORT: u32 = 52001;
 
 pub use calculator::CalculatorApp;
-pub use engine::EngineApp;
+pub use engine::{calc_final_points, EngineApp, MahjongEngine};
+pub use feature::FeatureApp;
+pub use gym::{MahjongEnv, Observation};
 pub use mahjongsoul::MahjongsoulApp;
+pub use mjai_client::MjaiClientApp;
 pub use replay::ReplayApp;
+pub use tournament::TournamentApp;
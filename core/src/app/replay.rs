@@ -2,6 +2,9 @@ use std::path::{Path, PathBuf};
 
 use crate::actor::create_actor;
 use crate::controller::*;
+use crate::convert::mjai::{MjaiDeserializer, MjaiEvent};
+use crate::convert::mjlog::MjlogDeserializer;
+use crate::convert::tenhou::{TenhouDeserializer, TenhouLog};
 use crate::listener::{Prompt, StageSender, StageStepPrinter};
 use crate::model::*;
 use crate::util::common::*;
@@ -95,7 +98,7 @@ impl ReplayApp {
                 .unwrap_or_else(error_exit)
                 .into_iter()
                 .filter(|p| match p.extension() {
-                    Some(ext) => ext == "json",
+                    Some(ext) => ext == "json" || ext == "mjson" || ext == "mjlog" || ext == "xml",
                     None => false,
                 })
                 .collect()
@@ -122,17 +125,44 @@ impl ReplayApp {
         let mut game = Replay::new(actors, enabled_actors, listeners);
         for p in paths {
             let contents = std::fs::read_to_string(p).unwrap_or_else(error_exit);
-            let record: Vec<Event> = serde_json::from_str(&contents).unwrap();
-
-            if let Event::New(e) = &record[0] {
-                if (e.bakaze, e.kyoku, e.honba) < rkh {
-                    continue;
+            for record in read_records(&contents).unwrap_or_else(error_exit) {
+                if let Event::New(e) = &record[0] {
+                    if (e.bakaze, e.kyoku, e.honba) < rkh {
+                        continue;
+                    }
                 }
+
+                game.run(record);
             }
+        }
+    }
+}
+
+// 牌譜ファイルの内容を局ごとのEventのリストに変換
+// EventWriterの出力(1局分のEventのリスト), mjai形式の牌譜, 天鳳形式の牌譜(json, mjlog)に対応
+pub(super) fn read_records(contents: &str) -> Result<Vec<Vec<Event>>, String> {
+    if let Ok(record) = serde_json::from_str::<Vec<Event>>(contents) {
+        return Ok(vec![record]);
+    }
 
-            game.run(record);
+    let first_line = contents.trim_start().lines().next().unwrap_or("");
+    let events = if contents.trim_start().starts_with('<') {
+        MjlogDeserializer::new().deserialize(contents)?
+    } else if serde_json::from_str::<MjaiEvent>(first_line).is_ok() {
+        MjaiDeserializer::new().deserialize(contents)?
+    } else {
+        let log: TenhouLog = contents.parse()?;
+        TenhouDeserializer::new().deserialize(&log)?
+    };
+    let mut records: Vec<Vec<Event>> = vec![];
+    for e in events {
+        match e {
+            Event::Begin(_) | Event::End(_) => {}
+            Event::New(_) => records.push(vec![e]),
+            _ => records.last_mut().unwrap().push(e),
         }
     }
+    Ok(records)
 }
 
 #[derive(Debug)]
@@ -231,6 +261,7 @@ impl Replay {
         let turn = stg.turn;
         let acts = calc_possible_turn_actions(stg, &self.melding);
         let act = self.ctrl.select_action(turn, &acts);
+        self.melding = None;
 
         let e = self.get_event();
         let act2 = match e {
@@ -262,8 +293,14 @@ impl Replay {
                 self.is_kyoku_end = true;
                 Action::tsumo()
             }
-            Event::Draw(_) => Action::kyushukyuhai(),
-            Event::Kita(_) => Action::kita(),
+            Event::Draw(_) => {
+                self.is_kyoku_end = true;
+                Action::kyushukyuhai()
+            }
+            Event::Kita(_) => {
+                self.melding = Some(Action::kita());
+                Action::kita()
+            }
             _ => panic!(),
         };
 
@@ -278,13 +315,13 @@ impl Replay {
                 self.is_kyoku_end = true;
             }
             Event::Meld(e) => {
-                // self.melding =
-                match e.meld_type {
-                    MeldType::Chi => {}
-                    MeldType::Pon => {}
-                    MeldType::Minkan => {}
+                let a = match e.meld_type {
+                    MeldType::Chi => Action::chi(e.consumed.clone()),
+                    MeldType::Pon => Action::pon(e.consumed.clone()),
+                    MeldType::Minkan => Action::minkan(e.consumed.clone()),
                     _ => panic!(),
-                }
+                };
+                self.melding = Some(a);
             }
             _ => return,
         }
@@ -300,7 +337,8 @@ impl Replay {
             Event::Draw(_) => {
                 self.is_kyoku_end = true;
             }
-            Event::Meld(_) => return, // Chi, pon
+            Event::Meld(_) => return,    // Chi, pon
+            Event::Discard(_) => return, // Chi, Pon後の打牌
             _ => panic!(),
         }
         self.handle_event();
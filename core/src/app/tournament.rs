@@ -0,0 +1,324 @@
+use std::collections::BTreeMap;
+
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::engine::{run_games, GameResult, GameTask};
+use crate::actor::create_actor;
+use crate::controller::*;
+use crate::listener::{print_stats_table, ActorStats};
+use crate::model::*;
+use crate::util::common::*;
+
+use crate::{error, info, warn};
+
+const INITIAL_RATING: f64 = 1500.0;
+
+// [App]
+// 複数のActor(ロスター)から卓を組んで対戦させ,レーティングを算出
+#[derive(Debug)]
+pub struct TournamentApp {
+    seed: u64,
+    mode: usize,
+    n_game: u32,
+    n_thread: u32,
+    rule: Rule,
+    rating_path: String,
+    roster: Vec<String>, // actor names
+}
+
+impl TournamentApp {
+    pub fn new(args: Vec<String>) -> Self {
+        use std::process::exit;
+
+        let mut app = Self {
+            seed: 0,
+            mode: 2,
+            n_game: 100,
+            n_thread: 16,
+            rule: Rule::default(),
+            rating_path: "rating.json".to_string(),
+            roster: vec![],
+        };
+
+        let mut rule_path = "".to_string();
+        let mut roster_path = "".to_string();
+        let mut it = args.iter();
+        while let Some(s) = it.next() {
+            match s.as_str() {
+                "-s" => app.seed = next_value(&mut it, "-s"),
+                "-m" => app.mode = next_value(&mut it, "-m"),
+                "-r" => rule_path = next_value(&mut it, "-r"),
+                "-g" => app.n_game = next_value(&mut it, "-g"),
+                "-t" => app.n_thread = next_value(&mut it, "-t"),
+                "-f" => roster_path = next_value(&mut it, "-f"),
+                "-a" => app.roster.push(next_value(&mut it, "-a")),
+                "-rating" => app.rating_path = next_value(&mut it, "-rating"),
+                opt => {
+                    error!("unknown option: {}", opt);
+                    exit(0);
+                }
+            }
+        }
+
+        if !rule_path.is_empty() {
+            app.rule = Rule::from_file(&rule_path).unwrap_or_else(error_exit);
+        }
+
+        if !roster_path.is_empty() {
+            // 1行に1つのActor ('#'以降はコメント)
+            let contents = std::fs::read_to_string(&roster_path).unwrap_or_else(error_exit);
+            for line in contents.lines() {
+                let name = line.split('#').next().unwrap().trim();
+                if !name.is_empty() {
+                    app.roster.push(name.to_string());
+                }
+            }
+        }
+
+        let n_seat = if app.mode > 10 { 3 } else { SEAT };
+        if app.roster.len() < n_seat {
+            error!("at least {} actors are required", n_seat);
+            exit(0);
+        }
+
+        if app.seed == 0 {
+            app.seed = unixtime_now();
+            warn!(
+                "Random seed is not specified. Unix timestamp '{}' is used as seed.",
+                app.seed
+            );
+        }
+
+        app
+    }
+
+    pub fn run(&mut self) {
+        println!("seed: {}", self.seed);
+
+        let actors: Vec<Box<dyn Actor>> = self.roster.iter().map(|n| create_actor(n)).collect();
+        for (i, a) in actors.iter().enumerate() {
+            println!("p{}: {:?}", i, a);
+        }
+
+        let mut ratings = RatingTable::load(&self.rating_path).unwrap_or_else(error_exit);
+        for name in &self.roster {
+            ratings.get_or_insert(name);
+        }
+
+        let start = std::time::Instant::now();
+        let mode = self.mode;
+        let n_seat = if mode > 10 { 3 } else { SEAT };
+        let mut rng: rand::rngs::StdRng = rand::SeedableRng::seed_from_u64(self.seed);
+        let mut n_game = 0;
+        let mut n_played = vec![0; actors.len()]; // 卓の割り当て回数
+        let mut stats = vec![ActorStats::new(); actors.len()];
+        let mut pending = BTreeMap::new(); // レーティングの計算順を固定するためのバッファ
+        let mut next_id = 0;
+
+        // 対戦回数が少ないActorから優先して卓を組む (同数の場合はランダム)
+        let next_task = || {
+            if n_game == self.n_game {
+                return None;
+            }
+
+            let mut order: Vec<usize> = (0..actors.len()).collect();
+            order.shuffle(&mut rng);
+            order.sort_by_key(|&i| n_played[i]);
+            let mut members = order[..n_seat].to_vec();
+            members.shuffle(&mut rng);
+
+            let mut table = [0; SEAT];
+            for (s, &i) in members.iter().enumerate() {
+                n_played[i] += 1;
+                table[s] = i;
+            }
+
+            let task = GameTask {
+                id: n_game as usize,
+                seed: rng.next_u64(),
+                table: table,
+            };
+            n_game += 1;
+            Some(task)
+        };
+
+        let on_result = |res: GameResult| {
+            let ms = res.elapsed.as_nanos() / 1000000;
+            print!("{:5},{:4}ms,{:20}", res.id, ms, res.seed);
+            for s in 0..n_seat {
+                let i = res.table[s];
+                stats[i].add(&res.stats[s], self.rule.initial_score);
+                print!(", p{}:{:5}({})", i, res.scores[s], res.ranks[s] + 1);
+            }
+            println!();
+
+            pending.insert(res.id, res);
+            while let Some(res) = pending.remove(&next_id) {
+                let names: Vec<&str> = (0..n_seat)
+                    .map(|s| self.roster[res.table[s]].as_str())
+                    .collect();
+                ratings.update(
+                    &names,
+                    &res.ranks[..n_seat],
+                    &res.scores[..n_seat],
+                    &res.final_points[..n_seat],
+                    &self.rule,
+                );
+                next_id += 1;
+            }
+        };
+
+        run_games(
+            mode,
+            &self.rule,
+            self.n_thread,
+            false,
+            &actors,
+            next_task,
+            on_result,
+        );
+
+        let names: Vec<String> = actors.iter().map(|a| format!("{:?}", a)).collect();
+        print_stats_table(&names, &stats, n_seat);
+        println!();
+        ratings.print(n_seat);
+
+        match ratings.save(&self.rating_path) {
+            Ok(_) => info!("rating written to {}", self.rating_path),
+            Err(e) => error!("{}: {}", e, self.rating_path),
+        }
+        println!(
+            "total elapsed time: {:8.3}sec",
+            start.elapsed().as_nanos() as f32 / 1000000000.0
+        );
+    }
+}
+
+// [Rating]
+// 天鳳方式のレーティング
+// 変動 = (順位点 + (卓の平均R - 自分のR) / 40) * 試合数補正
+// 順位点: 4人戦 [30, 10, -10, -30], 3人戦 [30, 0, -30]
+// 試合数補正: 1 - 試合数 * 0.002 (下限 0.2)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerRating {
+    pub name: String, // actor name (create_actorの引数)
+    pub rating: f64,
+    pub game: u32,
+    pub ranks: [u32; SEAT], // 各順位の回数
+    pub score_delta: i64,   // 得点の増減の合計
+    #[serde(default)]
+    pub final_point: i64, // 最終ポイントの合計
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RatingTable {
+    pub players: Vec<PlayerRating>,
+}
+
+impl RatingTable {
+    // ファイルが存在しない場合は空のテーブル
+    pub fn load(path: &str) -> Result<Self, String> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).map_err(|e| e.to_string()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let data = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, data).map_err(|e| e.to_string())
+    }
+
+    pub fn get_or_insert(&mut self, name: &str) -> &mut PlayerRating {
+        let i = match self.players.iter().position(|p| p.name == name) {
+            Some(i) => i,
+            None => {
+                self.players.push(PlayerRating {
+                    name: name.to_string(),
+                    rating: INITIAL_RATING,
+                    game: 0,
+                    ranks: [0; SEAT],
+                    score_delta: 0,
+                    final_point: 0,
+                });
+                self.players.len() - 1
+            }
+        };
+        &mut self.players[i]
+    }
+
+    // 1試合の結果を反映 (names, ranks, scores, final_pointsは座席順)
+    pub fn update(
+        &mut self,
+        names: &[&str],
+        ranks: &[usize],
+        scores: &[Score],
+        final_points: &[Point],
+        rule: &Rule,
+    ) {
+        let rank_points: &[f64] = if names.len() == 3 {
+            &[30.0, 0.0, -30.0]
+        } else {
+            &[30.0, 10.0, -10.0, -30.0]
+        };
+
+        let olds: Vec<f64> = names.iter().map(|n| self.get_or_insert(n).rating).collect();
+        let avg = olds.iter().sum::<f64>() / olds.len() as f64;
+        for (s, name) in names.iter().enumerate() {
+            let p = self.get_or_insert(name);
+            let coef = (1.0 - p.game as f64 * 0.002).max(0.2);
+            p.rating += (rank_points[ranks[s]] + (avg - olds[s]) / 40.0) * coef;
+            p.game += 1;
+            p.ranks[ranks[s]] += 1;
+            p.score_delta += (scores[s] - rule.initial_score) as i64;
+            p.final_point += final_points[s] as i64;
+        }
+    }
+
+    pub fn print(&self, n_seat: usize) {
+        let mut players: Vec<&PlayerRating> = self.players.iter().collect();
+        players.sort_by(|a, b| b.rating.partial_cmp(&a.rating).unwrap());
+
+        println!(
+            "{:>4} {:>8} {:>6} {:>8} {:>10} {:>9}  name",
+            "#", "rating", "game", "avg_rank", "avg_delta", "avg_point"
+        );
+        for (i, p) in players.iter().enumerate() {
+            let n = p.game.max(1) as f64;
+            let rank_sum: u32 = (0..n_seat).map(|r| (r as u32 + 1) * p.ranks[r]).sum();
+            println!(
+                "{:4} {:8.1} {:6} {:8.3} {:10.0} {:9.1}  {}",
+                i + 1,
+                p.rating,
+                p.game,
+                rank_sum as f64 / n,
+                p.score_delta as f64 / n,
+                p.final_point as f64 / n / 1000.0,
+                p.name,
+            );
+        }
+    }
+}
+
+#[test]
+fn test_rating_update() {
+    let rule = Rule::default();
+    let mut rt = RatingTable::default();
+    rt.get_or_insert("a").rating = 1600.0;
+    let names = ["a", "b", "c", "d"];
+    let scores = [0, 50000, 30000, 20000];
+    let final_points = [-50000, 60000, 10000, -20000];
+    rt.update(&names, &[3, 0, 1, 2], &scores, &final_points, &rule);
+
+    // 平均R = 1525
+    let a = rt.get_or_insert("a");
+    assert!((a.rating - (1600.0 - 30.0 - 75.0 / 40.0)).abs() < 1e-9);
+    assert_eq!(a.ranks[3], 1);
+    assert_eq!(a.score_delta, -25000);
+    assert_eq!(a.final_point, -50000);
+    let b = rt.get_or_insert("b");
+    assert!((b.rating - (1500.0 + 30.0 + 25.0 / 40.0)).abs() < 1e-9);
+    assert_eq!(b.game, 1);
+}
@@ -10,11 +10,20 @@ pub struct Config {
     pub args: Vec<Arg>,
 }
 
-// Actor trait
+/// ゲームの操作を行う主体(Bot)
+/// select_actionで渡される卓情報は自分の座席から見た情報(他家の手牌はマスク)
 pub trait Actor: Listener + ActorClone + Send {
     fn init(&mut self, _seat: Seat) {}
+    // 試合の開始前(init前)に呼び出される
+    // 同じインスタンスが複数の試合で再利用される場合があるので前の試合の状態を初期化する
+    fn reset(&mut self) {}
     fn select_action(&mut self, stage: &Stage, actions: &Vec<Action>) -> Action;
     fn get_config(&self) -> &Config;
+    // select_action, notify_eventで卓情報を参照しない場合はfalse
+    // falseの場合は座席ごとの卓情報の更新を省略する (Nopなど)
+    fn uses_stage(&self) -> bool {
+        true
+    }
 }
 
 impl fmt::Debug for dyn Actor {
@@ -2,6 +2,8 @@ use std::fmt;
 
 use crate::model::*;
 
+/// Eventの通知を受け取る (牌譜の出力, 集計, GUIへの送信など)
+/// Actorとは異なりすべての情報が公開された卓情報が渡される
 pub trait Listener: Send {
     fn notify_event(&mut self, _stg: &Stage, _event: &Event) {}
 }
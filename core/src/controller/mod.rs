@@ -7,3 +7,6 @@ pub use actor::{Actor, Config};
 pub use listener::Listener;
 pub use possible_actions::{calc_possible_call_actions, calc_possible_turn_actions};
 pub use stage_controller::StageController;
+
+#[cfg(test)]
+pub(crate) use stage_controller::create_test_controller;
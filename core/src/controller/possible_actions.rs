@@ -8,11 +8,12 @@ use crate::model::*;
 pub fn calc_possible_turn_actions(stg: &Stage, melding: &Option<Action>) -> Vec<Action> {
     let mut acts = vec![Action::nop()];
     if !stg.players[stg.turn].is_riichi {
-        if let Some(act) = melding {
-            // 鳴き後に捨てられない牌を追加
-            acts.push(Action(ActionType::Discard, calc_prohibited_discards(act)));
-        } else {
-            acts.push(Action(ActionType::Discard, vec![]))
+        match melding {
+            Some(act) if !stg.rule.kuikae => {
+                // 鳴き後に捨てられない牌を追加
+                acts.push(Action(ActionType::Discard, calc_prohibited_discards(act)));
+            }
+            _ => acts.push(Action(ActionType::Discard, vec![])),
         }
     }
 
@@ -222,7 +223,8 @@ pub fn calc_possible_call_actions(stg: &Stage, can_meld: bool) -> [Vec<Action>;
 }
 
 fn check_chi(stg: &Stage) -> Vec<(Seat, Action)> {
-    if stg.left_tile_count == 0 {
+    // 三麻はチー不可
+    if stg.is_3p || stg.left_tile_count == 0 {
         return vec![];
     }
 
@@ -404,3 +406,85 @@ fn calc_prohibited_discards(act: &Action) -> Vec<Tile> {
 
     v
 }
+
+#[test]
+fn test_3p_call_actions() {
+    use crate::controller::create_test_controller;
+
+    // 三麻: 座席2は北単騎でリーチ
+    let hands = [
+        "m199p129s19z123567",
+        "p13456s2245789z7",
+        "m111p456678s345z4",
+        "",
+    ];
+    let mut ctrl = create_test_controller(EventNew::from_hands(11, hands));
+    ctrl.handle_event(&Event::discard(0, Tile(TP, 2), false, false));
+
+    // チー不可 (4人打ちであれば座席1は1p3pでチー可能)
+    let acts = calc_possible_call_actions(ctrl.get_stage(), true);
+    assert_eq!(acts[1], vec![Action::nop()]);
+
+    ctrl.handle_event(&Event::deal(1, Tile(TP, 9)));
+    ctrl.handle_event(&Event::discard(1, Tile(TP, 9), true, false));
+    ctrl.handle_event(&Event::deal(2, Tile(TS, 9)));
+    ctrl.handle_event(&Event::discard(2, Tile(TS, 9), true, true));
+    ctrl.handle_event(&Event::deal(0, Tile(TZ, WN)));
+    let acts = calc_possible_turn_actions(ctrl.get_stage(), &None);
+    assert!(acts.contains(&Action::kita()));
+
+    // 抜き北に対するロン
+    ctrl.handle_event(&Event::kita(0, true));
+    let stg = ctrl.get_stage();
+    assert_eq!(stg.players[0].kitas.len(), 1);
+    let acts = calc_possible_call_actions(stg, false);
+    assert!(acts[2].contains(&Action::ron()));
+    assert!(!stg.players[2].is_furiten_other);
+
+    // ロンせずに嶺上牌をツモした場合は見逃しフリテン
+    ctrl.handle_event(&Event::deal(0, Tile(TZ, WE)));
+    assert!(ctrl.get_stage().players[2].is_furiten_other);
+}
+
+#[test]
+fn test_kuitan_kuikae() {
+    use crate::controller::create_test_controller;
+
+    // 座席1: 座席0の2pをポンして断么九のみの2s5s待ち
+    let e = EventNew::from_hands(1, ["m19p129s19z1234567", "m234p22567s3488z4", "", ""]);
+    let mut ctrl = create_test_controller(e);
+    ctrl.handle_event(&Event::discard(0, Tile(TP, 2), false, false));
+
+    // 喰い替え: ポンした牌と同じ牌は喰い替えなしの場合のみ打牌不可
+    let pon = Action::pon(vec![Tile(TP, 2), Tile(TP, 2)]);
+    ctrl.handle_event(&Event::meld(1, MeldType::Pon, pon.1.clone()));
+    let mut stg = ctrl.get_stage().clone();
+    stg.rule.kuikae = false;
+    let acts = calc_possible_turn_actions(&stg, &Some(pon.clone()));
+    assert!(acts.contains(&Action(ActionType::Discard, vec![Tile(TP, 2)])));
+    stg.rule.kuikae = true;
+    let acts = calc_possible_turn_actions(&stg, &Some(pon));
+    assert!(acts.contains(&Action(ActionType::Discard, vec![])));
+
+    ctrl.handle_event(&Event::discard(1, Tile(TZ, WN), false, false));
+    ctrl.handle_event(&Event::deal(2, Z8));
+    ctrl.handle_event(&Event::discard(2, Tile(TS, 5), true, false));
+
+    // 喰いタン: なしの場合は役無しでロン不可
+    let mut stg = ctrl.get_stage().clone();
+    stg.rule.kuitan = true;
+    assert!(calc_possible_call_actions(&stg, true)[1].contains(&Action::ron()));
+    stg.rule.kuitan = false;
+    assert!(!calc_possible_call_actions(&stg, true)[1].contains(&Action::ron()));
+
+    // 喰い替え: チーした両面の筋(赤5を含む)も打牌不可
+    let chi = Action::chi(vec![Tile(TS, 3), Tile(TS, 4)]);
+    stg.rule.kuikae = false;
+    assert_eq!(
+        calc_possible_turn_actions(&stg, &Some(chi))[1],
+        Action(
+            ActionType::Discard,
+            vec![Tile(TS, 2), Tile(TS, 5), Tile(TS, 0)]
+        )
+    );
+}
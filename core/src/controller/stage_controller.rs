@@ -1,13 +1,17 @@
 use super::*;
 use crate::hand::*;
 use crate::model::*;
-use crate::util::common::rank_by_rank_vec;
+use crate::util::common::calc_ranks;
 
 use TileStateType::*;
 
+/// Eventを卓情報に反映して,ActorとListenerに通知する
+/// ゲームの進行(Eventの生成)は行わないので,エンジンや牌譜のリプレイから使用する
 #[derive(Debug)]
 pub struct StageController {
     stage: Stage,
+    views: [Stage; SEAT], // 各座席から見た卓情報 (他家の手牌,ツモ牌は非公開, 必要な座席のみ更新)
+    omniscient: [bool; SEAT], // trueの場合はActorにすべての情報が公開された卓情報を渡す (解析用)
     actors: [Box<dyn Actor>; SEAT],
     listeners: Vec<Box<dyn Listener>>,
 }
@@ -17,6 +21,8 @@ impl StageController {
         let stage = Stage::default();
         Self {
             stage,
+            views: Default::default(),
+            omniscient: [false; SEAT],
             actors,
             listeners,
         }
@@ -26,41 +32,105 @@ impl StageController {
         std::mem::swap(&mut self.actors[seat], actor);
     }
 
+    pub fn set_omniscient(&mut self, seat: Seat, flag: bool) {
+        self.omniscient[seat] = flag;
+    }
+
     pub fn get_stage(&self) -> &Stage {
         &self.stage
     }
 
     pub fn handle_event(&mut self, event: &Event) {
-        let stg = &mut self.stage;
-        stg.step += 1;
-        match event {
-            Event::Begin(e) => {
-                for s in 0..SEAT {
-                    self.actors[s].init(s);
-                }
-                event_begin(stg, e);
+        if let Event::Begin(_) = event {
+            for s in 0..SEAT {
+                self.actors[s].init(s);
             }
-            Event::New(e) => event_new(stg, e),
-            Event::Deal(e) => event_deal(stg, e),
-            Event::Discard(e) => event_discard(stg, e),
-            Event::Meld(e) => event_meld(stg, e),
-            Event::Kita(e) => event_kita(stg, e),
-            Event::Dora(e) => event_dora(stg, e),
-            Event::Win(e) => event_win(stg, e),
-            Event::Draw(e) => event_draw(stg, e),
-            Event::End(e) => event_end(stg, e),
         }
 
-        for a in &mut self.actors {
-            a.notify_event(stg, &event);
+        update_stage(&mut self.stage, event);
+        for s in 0..SEAT {
+            if self.omniscient[s] {
+                self.actors[s].notify_event(&self.stage, event);
+                continue;
+            }
+
+            // 座席ごとの卓情報の更新はEventごとに卓情報を1つ更新するのと同じコストがかかるので,
+            // 卓情報を参照しないActorの座席では省略する (マスクされたイベントのみ通知)
+            // 局の途中でActorを入れ替えた場合は次の局の開始まで卓情報が正しくないことに注意
+            let view = &mut self.views[s];
+            let masked = mask_event(event, s);
+            let event = masked.as_ref().unwrap_or(event);
+            if !self.actors[s].uses_stage() {
+                self.actors[s].notify_event(view, event);
+                continue;
+            }
+            update_stage(view, event);
+            if let Event::Win(e) = event {
+                // ツモ和了の場合は和了牌を公開
+                for (s2, _, _) in &e.contexts {
+                    if view.turn == *s2 {
+                        view.players[*s2].drawn = self.stage.players[*s2].drawn;
+                    }
+                }
+            }
+            self.actors[s].notify_event(view, event);
         }
         for a in &mut self.listeners {
-            a.notify_event(stg, &event);
+            a.notify_event(&self.stage, event);
         }
     }
 
     pub fn select_action(&mut self, seat: Seat, acts: &Vec<Action>) -> Action {
-        self.actors[seat].select_action(&self.stage, &acts)
+        let stg = if self.omniscient[seat] {
+            &self.stage
+        } else {
+            &self.views[seat]
+        };
+        self.actors[seat].select_action(stg, acts)
+    }
+}
+
+fn update_stage(stg: &mut Stage, event: &Event) {
+    stg.step += 1;
+    match event {
+        Event::Begin(e) => event_begin(stg, e),
+        Event::New(e) => event_new(stg, e),
+        Event::Deal(e) => event_deal(stg, e),
+        Event::Discard(e) => event_discard(stg, e),
+        Event::Meld(e) => event_meld(stg, e),
+        Event::Kita(e) => event_kita(stg, e),
+        Event::Dora(e) => event_dora(stg, e),
+        Event::Win(e) => event_win(stg, e),
+        Event::Draw(e) => event_draw(stg, e),
+        Event::End(e) => event_end(stg, e),
+    }
+}
+
+// seatから見えない情報(他家の配牌,ツモ牌)をZ8に置き換えたイベントを返却
+// 置き換えが不要な場合はNone
+fn mask_event(event: &Event, seat: Seat) -> Option<Event> {
+    match event {
+        Event::New(e) => {
+            let mut hands = e.hands.clone();
+            for (s, h) in hands.iter_mut().enumerate() {
+                if s != seat {
+                    *h = vec![Z8; h.len()];
+                }
+            }
+            Some(Event::new(
+                e.bakaze,
+                e.kyoku,
+                e.honba,
+                e.kyoutaku,
+                e.doras.clone(),
+                e.scores,
+                hands,
+                e.mode,
+                e.rule.clone(),
+            ))
+        }
+        Event::Deal(e) if e.seat != seat && e.tile != Z8 => Some(Event::deal(e.seat, Z8)),
+        _ => None,
     }
 }
 
@@ -74,12 +144,14 @@ fn event_new(stg: &mut Stage, event: &EventNew) {
     stg.honba = event.honba;
     stg.kyoutaku = event.kyoutaku;
     stg.turn = event.kyoku;
-    stg.left_tile_count = 69;
+    stg.is_3p = event.mode > 10; // 11: 3人東, 12: 3人南
+    stg.left_tile_count = if stg.is_3p { 54 } else { 69 };
     stg.doras = event.doras.clone();
+    stg.rule = event.rule.clone();
     update_scores(stg, &event.scores);
 
-    // プレイヤー情報
-    for s in 0..SEAT {
+    // プレイヤー情報 (三麻の場合は座席3を使用しない)
+    for s in 0..stg.get_seat_count() {
         let ph = &event.hands[s];
         let pl = &mut stg.players[s];
         pl.seat = s;
@@ -109,7 +181,7 @@ fn event_new(stg: &mut Stage, event: &EventNew) {
     for &d in &event.doras {
         table_edit(stg, d, U, R);
     }
-    for s in 0..SEAT {
+    for s in 0..stg.get_seat_count() {
         let ph = &event.hands[s];
         if stg.players[s].is_shown {
             for &t in ph {
@@ -233,6 +305,37 @@ fn event_discard(stg: &mut Stage, event: &EventDiscard) {
     stg.last_tile = Some((s, ActionType::Discard, t));
 }
 
+// 責任払い(包)の判定 (大三元,大四喜,四槓子を確定させるポン・大明槓をさせた場合)
+fn update_pao(stg: &mut Stage, seat: Seat, from: Seat) {
+    let rule = &stg.rule;
+    let pl = &mut stg.players[seat];
+    if pl.pao.is_some() {
+        return;
+    }
+
+    let m = pl.melds.last().unwrap();
+    let t = m.tiles[0];
+    let pons: Vec<&Meld> = pl
+        .melds
+        .iter()
+        .filter(|m| m.type_ != MeldType::Chi)
+        .collect();
+    let n_kan = pons.iter().filter(|m| m.tiles.len() == 4).count();
+    let name = if rule.pao
+        && t.is_doragon()
+        && pons.iter().filter(|m| m.tiles[0].is_doragon()).count() == 3
+    {
+        "大三元"
+    } else if rule.pao && t.is_wind() && pons.iter().filter(|m| m.tiles[0].is_wind()).count() == 4 {
+        "大四喜"
+    } else if rule.pao_suukantsu && m.type_ == MeldType::Minkan && n_kan == 4 {
+        "四槓子"
+    } else {
+        return;
+    };
+    pl.pao = Some((from, name.to_string()));
+}
+
 fn event_meld(stg: &mut Stage, event: &EventMeld) {
     // リーチ一発や槍槓, フリテンなどに必要な前処理
     update_after_discard_completed(stg);
@@ -268,6 +371,9 @@ fn event_meld(stg: &mut Stage, event: &EventMeld) {
             let &(prev_s, prev_i) = stg.discards.last().unwrap();
             stg.players[prev_s].discards[prev_i].meld = Some((s, idx));
             stg.players[s].melds.push(m);
+            if event.meld_type != MeldType::Chi {
+                update_pao(stg, s, lt.0);
+            }
         }
         MeldType::Ankan => {
             pl.is_rinshan = true;
@@ -340,6 +446,7 @@ fn event_kita(stg: &mut Stage, event: &EventKita) {
     }
 
     stg.players[s].kitas.push(k);
+    stg.last_tile = Some((s, ActionType::Kita, t)); // 抜き北に対するロン+フリテン用
 }
 
 fn event_dora(stg: &mut Stage, event: &EventDora) {
@@ -354,10 +461,19 @@ fn event_win(stg: &mut Stage, event: &EventWin) {
 }
 
 fn event_draw(stg: &mut Stage, event: &EventDraw) {
+    // 打牌が通った後の途中流局の場合は流局直前のリーチを成立させる
+    match event.type_ {
+        DrawType::Suufuurenda | DrawType::Suukansanra | DrawType::Suuchariichi => {
+            update_after_discard_completed(stg);
+        }
+        _ => {}
+    }
     update_scores(stg, &event.points);
 }
 
-fn event_end(_stg: &mut Stage, _event: &EventEnd) {}
+fn event_end(stg: &mut Stage, event: &EventEnd) {
+    update_scores(stg, &event.points);
+}
 
 // [Utility]
 fn table_edit(stg: &mut Stage, tile: Tile, old: TileStateType, new: TileStateType) {
@@ -376,9 +492,9 @@ fn disable_ippatsu(stg: &mut Stage) {
 }
 
 fn update_after_discard_completed(stg: &mut Stage) {
-    // 他のプレイヤーの捨て牌,または加槓した牌の見逃しフリテン
+    // 他のプレイヤーの捨て牌,加槓した牌または抜き北の見逃しフリテン
     if let Some((s, tp, t)) = stg.last_tile {
-        if tp == ActionType::Discard || tp == ActionType::Kakan {
+        if tp == ActionType::Discard || tp == ActionType::Kakan || tp == ActionType::Kita {
             for s2 in 0..SEAT {
                 if s2 != s {
                     if stg.players[s2].win_tiles.contains(&t) {
@@ -403,8 +519,8 @@ fn update_scores(stg: &mut Stage, points: &[Point; SEAT]) {
         pl.score = pl.score + points[s];
     }
 
-    let scores = stg.players.iter().map(|pl| pl.score).collect();
-    let ranks = rank_by_rank_vec(&scores);
+    // 三麻の場合は座席3を除いて順位を計算
+    let ranks = calc_ranks(&stg.get_scores(), stg.get_seat_count());
     for s in 0..SEAT {
         stg.players[s].rank = ranks[s];
     }
@@ -446,3 +562,95 @@ fn get_win_tiles(pl: &Player) -> Vec<Tile> {
     }
     win_tiles
 }
+
+// [Test Utility]
+// 局開始イベントを適用したStageControllerを生成 (テスト用, ActorはすべてNop)
+#[cfg(test)]
+pub(crate) fn create_test_controller(event: EventNew) -> StageController {
+    let actors = [0, 1, 2, 3].map(|_| crate::actor::create_actor("Nop"));
+    let mut ctrl = StageController::new(actors, vec![]);
+    ctrl.handle_event(&Event::begin());
+    ctrl.handle_event(&Event::New(event));
+    ctrl
+}
+
+#[test]
+fn test_stage_view() {
+    use crate::actor::create_actor;
+    use crate::convert::mjai::MjaiDeserializer;
+
+    let log = r#"{"type":"start_game","id":0,"names":["a","b","c","d"]}
+{"type":"start_kyoku","bakaze":"E","dora_marker":"1s","kyoku":1,"honba":0,"kyotaku":0,"oya":0,"tehais":[["1m","2m","3m","4p","5p","6p","7s","8s","9s","E","E","S","N"],["1m","2m","3m","4p","5p","6p","7s","8s","9s","S","S","W","N"],["1p","2p","3p","4s","5s","6s","7m","8m","9m","W","W","P","F"],["1s","2s","3s","4m","5m","6m","7p","8p","9p","P","F","C","C"]],"scores":[25000,25000,25000,25000]}
+{"type":"tsumo","actor":0,"pai":"W"}
+{"type":"dahai","actor":0,"pai":"W","tsumogiri":true}
+{"type":"tsumo","actor":1,"pai":"C"}"#;
+
+    let actors = [0, 1, 2, 3].map(|s| create_actor(if s < 2 { "RandomDiscard" } else { "Nop" }));
+    let mut ctrl = StageController::new(actors, vec![]);
+    let mut de = MjaiDeserializer::new();
+    for line in log.lines() {
+        for e in de.push_record(serde_json::from_str(line).unwrap()).unwrap() {
+            ctrl.handle_event(&e);
+        }
+    }
+
+    let c = Tile(TZ, DR);
+    let full = &ctrl.stage;
+    assert_eq!(full.players[1].drawn, Some(c));
+    assert!(full.tile_states[TZ][DR].contains(&H(1)));
+
+    // 座席0からは座席1の手牌,ツモ牌は見えない
+    let view = &ctrl.views[0];
+    assert!(view.players[0].is_shown);
+    assert!(!view.players[1].is_shown);
+    assert_eq!(view.players[1].drawn, Some(Z8));
+    assert_eq!(view.players[1].hand[TZ][UK], 14);
+    assert!(!view.tile_states[TZ][DR].contains(&H(1)));
+    assert!(view.tile_states[TZ][WW].contains(&D(0, 0)));
+
+    // 座席1は自身の手牌のみ公開
+    let view = &ctrl.views[1];
+    assert!(!view.players[0].is_shown);
+    assert_eq!(view.players[1].drawn, Some(c));
+    assert_eq!(view.step, full.step);
+
+    // 卓情報を参照しないActor(Nop)の座席は更新しない
+    assert_eq!(ctrl.views[2].step, 0);
+}
+
+#[test]
+fn test_pao() {
+    use crate::actor::create_actor;
+    use crate::convert::mjai::MjaiDeserializer;
+
+    // 座席0が白,發をポンした後,座席3から中をポン → 座席3が大三元の責任払い
+    let log = r#"{"type":"start_game","id":0,"names":["a","b","c","d"]}
+{"type":"start_kyoku","bakaze":"E","dora_marker":"1s","kyoku":1,"honba":0,"kyotaku":0,"oya":0,"tehais":[["P","P","F","F","C","C","1m","2m","3m","4m","5m","6m","9s"],["1m","2m","3m","4p","5p","6p","7s","8s","9s","S","S","P","N"],["1p","2p","3p","4s","5s","6s","7m","8m","9m","W","W","F","E"],["1s","2s","3s","4m","5m","6m","7p","8p","9p","E","S","C","N"]],"scores":[25000,25000,25000,25000]}
+{"type":"tsumo","actor":0,"pai":"1s"}
+{"type":"dahai","actor":0,"pai":"1s","tsumogiri":true}
+{"type":"tsumo","actor":1,"pai":"9p"}
+{"type":"dahai","actor":1,"pai":"P","tsumogiri":false}
+{"type":"pon","actor":0,"target":1,"pai":"P","consumed":["P","P"]}
+{"type":"dahai","actor":0,"pai":"9s","tsumogiri":false}
+{"type":"tsumo","actor":2,"pai":"9p"}
+{"type":"dahai","actor":2,"pai":"F","tsumogiri":false}
+{"type":"pon","actor":0,"target":2,"pai":"F","consumed":["F","F"]}
+{"type":"dahai","actor":0,"pai":"1m","tsumogiri":false}
+{"type":"tsumo","actor":3,"pai":"9p"}
+{"type":"dahai","actor":3,"pai":"C","tsumogiri":false}"#;
+
+    let nop = create_actor("Nop");
+    let actors = [0, 1, 2, 3].map(|_| nop.clone_box());
+    let mut ctrl = StageController::new(actors, vec![]);
+    let mut de = MjaiDeserializer::new();
+    for line in log.lines() {
+        for e in de.push_record(serde_json::from_str(line).unwrap()).unwrap() {
+            ctrl.handle_event(&e);
+        }
+    }
+    assert_eq!(ctrl.stage.players[0].pao, None);
+
+    let c = Tile(TZ, DR);
+    ctrl.handle_event(&Event::meld(0, MeldType::Pon, vec![c, c]));
+    assert_eq!(ctrl.stage.players[0].pao, Some((3, "大三元".to_string())));
+}
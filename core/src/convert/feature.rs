@@ -0,0 +1,312 @@
+use crate::hand::calc_unseen_tile_table;
+use crate::model::*;
+
+use ActionType::*;
+
+// [Feature]
+// 機械学習用に座席から見た卓情報を固定長の数値の配列(平面)に変換
+// 各平面は牌の種類(34)を要素とする 牌の種類のindex: 萬子 0~8, 筒子 9~17, 索子 18~26, 字牌 27~33
+// 座席から見えない情報(他家の手牌,ツモ牌)は使用しないので,マスクされていないStageを渡してもよい
+//
+// レイアウト (FEATURE_VERSION = 1)
+//   0~3   : 手牌の枚数 (平面n: n+1枚以上の牌が1)
+//   4     : 手牌の赤5
+//   5     : ツモ牌
+//   6     : 鳴き・ロンの対象の牌 (直前の捨て牌,加槓,北抜き)
+//   7~38  : 各プレイヤーの情報 (自分,下家,対面,上家の順に8平面ずつ, 三麻の場合の4人目は0)
+//           +0: 捨て牌の枚数, +1: ツモ切りした捨て牌の枚数, +2: リーチ宣言牌,
+//           +3: 副露した牌の枚数, +4: 北抜きの数(全体), +5: リーチ(全体),
+//           +6: 得点/100000(全体), +7: 親(全体)
+//   39    : ドラ表示牌の枚数
+//   40    : 場風
+//   41    : 自風
+//   42    : 本場/10 (全体)
+//   43    : 供託/10 (全体)
+//   44    : 牌山の残り枚数/70 (全体)
+//   45    : 見えていない牌の枚数/4
+//
+// 操作のindex (ACTION_SIZE = 146)
+//   0~33   : 打牌 (ツモ切りを含む)
+//   34~67  : リーチ宣言+打牌
+//   68~101 : 暗槓
+//   102~135: 加槓
+//   136: ツモ, 137: ロン, 138~140: チー (鳴いた牌が順子の左,中央,右), 141: ポン, 142: 大明槓,
+//   143: 北抜き, 144: 九種九牌, 145: スキップ(鳴き,ロンをしない)
+// バージョンはレイアウトを変更した際に更新する
+
+pub const FEATURE_VERSION: u32 = 1;
+pub const N_TILE: usize = 34;
+pub const N_CHANNEL: usize = 46;
+pub const FEATURE_SIZE: usize = N_CHANNEL * N_TILE;
+pub const ACTION_SIZE: usize = 146;
+
+const CH_PLAYER: usize = 7;
+const N_CH_PLAYER: usize = 8;
+
+// 牌の種類のindex
+pub fn tile_index(t: Tile) -> usize {
+    let t = t.to_normal();
+    t.0 * 9 + t.1 - 1
+}
+
+// 座席seatから見た卓情報をN_CHANNEL*N_TILEの配列に変換
+pub fn encode_stage(stg: &Stage, seat: Seat) -> Vec<f32> {
+    let mut f = vec![0.0; FEATURE_SIZE];
+
+    let pl = &stg.players[seat];
+    for ti in 0..TYPE {
+        for ni in 1..TNUM {
+            if ti == TZ && ni > DR {
+                break;
+            }
+            let i = tile_index(Tile(ti, ni));
+            for n in 0..pl.hand[ti][ni].min(TILE) {
+                f[n * N_TILE + i] = 1.0;
+            }
+        }
+        if ti != TZ && pl.hand[ti][0] > 0 {
+            f[4 * N_TILE + tile_index(Tile(ti, 5))] = 1.0;
+        }
+    }
+    if let Some(t) = pl.drawn {
+        f[5 * N_TILE + tile_index(t)] = 1.0;
+    }
+    if let Some((s, _, t)) = stg.last_tile {
+        if s != seat {
+            f[6 * N_TILE + tile_index(t)] = 1.0;
+        }
+    }
+
+    let n_seat = stg.get_seat_count();
+    for i in 0..n_seat {
+        let s = (seat + i) % n_seat;
+        let p = &stg.players[s];
+        let ch = CH_PLAYER + i * N_CH_PLAYER;
+        for d in &p.discards {
+            add(&mut f, ch, tile_index(d.tile), 1.0);
+            if d.drawn {
+                add(&mut f, ch + 1, tile_index(d.tile), 1.0);
+            }
+        }
+        if let Some(r) = p.riichi {
+            f[(ch + 2) * N_TILE + tile_index(p.discards[r].tile)] = 1.0;
+        }
+        for m in &p.melds {
+            for &t in &m.tiles {
+                add(&mut f, ch + 3, tile_index(t), 1.0);
+            }
+        }
+        fill(&mut f, ch + 4, p.kitas.len() as f32);
+        fill(&mut f, ch + 5, if p.is_riichi { 1.0 } else { 0.0 });
+        fill(&mut f, ch + 6, p.score as f32 / 100000.0);
+        fill(&mut f, ch + 7, if stg.is_dealer(s) { 1.0 } else { 0.0 });
+    }
+
+    for &d in &stg.doras {
+        add(&mut f, 39, tile_index(d), 1.0);
+    }
+    f[40 * N_TILE + tile_index(Tile(TZ, stg.get_prevalent_wind()))] = 1.0;
+    f[41 * N_TILE + tile_index(Tile(TZ, stg.get_seat_wind(seat)))] = 1.0;
+    fill(&mut f, 42, stg.honba as f32 / 10.0);
+    fill(&mut f, 43, stg.kyoutaku as f32 / 10.0);
+    fill(&mut f, 44, stg.left_tile_count as f32 / 70.0);
+
+    let unseen = calc_unseen_tile_table(stg, seat);
+    for ti in 0..TYPE {
+        for ni in 1..TNUM {
+            if ti == TZ && ni > DR {
+                break;
+            }
+            f[45 * N_TILE + tile_index(Tile(ti, ni))] = unseen[ti][ni] as f32 / 4.0;
+        }
+    }
+
+    f
+}
+
+fn add(f: &mut [f32], ch: usize, i: usize, v: f32) {
+    f[ch * N_TILE + i] += v;
+}
+
+fn fill(f: &mut [f32], ch: usize, v: f32) {
+    for i in 0..N_TILE {
+        f[ch * N_TILE + i] = v;
+    }
+}
+
+// 座席seatの操作をindexに変換
+// stgは操作を選択した時点の卓情報 (ツモ切り,チーの判定に使用)
+pub fn encode_action(stg: &Stage, seat: Seat, act: &Action) -> Option<usize> {
+    let Action(tp, cs) = act;
+    Some(match tp {
+        Nop => {
+            if stg.turn == seat {
+                tile_index(stg.players[seat].drawn?) // ツモ切り
+            } else {
+                145
+            }
+        }
+        Discard => tile_index(*cs.first()?),
+        Riichi => 34 + tile_index(*cs.first()?),
+        Ankan => 68 + tile_index(*cs.last()?),
+        Kakan => 102 + tile_index(*cs.first()?),
+        Tsumo => 136,
+        Ron => 137,
+        Chi => {
+            let (_, _, t) = stg.last_tile?;
+            let n = t.to_normal().1;
+            let (n0, n1) = (cs.first()?.to_normal().1, cs.get(1)?.to_normal().1);
+            if n < n0.min(n1) {
+                138
+            } else if n < n0.max(n1) {
+                139
+            } else {
+                140
+            }
+        }
+        Pon => 141,
+        Minkan => 142,
+        Kita => 143,
+        Kyushukyuhai => 144,
+    })
+}
+
+// 選択可能な操作の一覧をACTION_SIZEのマスク(選択可能な操作が1)に変換
+// 打牌(Discard)は手牌にある牌のうち,喰い替えで捨てられない牌(Action.1)以外が選択可能
+pub fn encode_legal_actions(stg: &Stage, seat: Seat, acts: &Vec<Action>) -> Vec<u8> {
+    let mut mask = vec![0; ACTION_SIZE];
+    for act in acts {
+        if act.0 == Discard {
+            let pl = &stg.players[seat];
+            for ti in 0..TYPE {
+                for ni in 1..TNUM {
+                    let t = Tile(ti, ni);
+                    if pl.hand[ti][ni] > 0 && !act.1.iter().any(|x| x.to_normal() == t) {
+                        mask[tile_index(t)] = 1;
+                    }
+                }
+            }
+        } else if let Some(i) = encode_action(stg, seat, act) {
+            mask[i] = 1;
+        }
+    }
+    mask
+}
+
+// [Dataset]
+// (特徴量, 選択可能な操作, 選択した操作)の組の集合
+#[derive(Debug, Default)]
+pub struct FeatureDataset {
+    pub features: Vec<f32>, // N * FEATURE_SIZE
+    pub masks: Vec<u8>,     // N * ACTION_SIZE
+    pub actions: Vec<i32>,  // N
+    pub seats: Vec<u8>,     // N
+}
+
+impl FeatureDataset {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.actions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    // 先頭からn個を残して残りを返却
+    pub fn split_off(&mut self, n: usize) -> Self {
+        Self {
+            features: self.features.split_off(n * FEATURE_SIZE),
+            masks: self.masks.split_off(n * ACTION_SIZE),
+            actions: self.actions.split_off(n),
+            seats: self.seats.split_off(n),
+        }
+    }
+
+    pub fn push(&mut self, stg: &Stage, seat: Seat, acts: &Vec<Action>, act: &Action) {
+        let i = match encode_action(stg, seat, act) {
+            Some(i) => i,
+            None => return,
+        };
+        // 赤牌の有無のみが異なる操作は同じindexになるため,選択肢が実質1つの場合は追加しない
+        let mut mask = encode_legal_actions(stg, seat, acts);
+        if mask.iter().filter(|&&m| m == 1).count() <= 1 {
+            return;
+        }
+        self.features.append(&mut encode_stage(stg, seat));
+        self.masks.append(&mut mask);
+        self.actions.push(i as i32);
+        self.seats.push(seat as u8);
+    }
+
+    // {dir}/{name}.{features,masks,actions,seats}.npy に書き出し
+    pub fn write_npy(&self, dir: &str, name: &str) -> Result<(), String> {
+        let n = self.len();
+        let path = |kind: &str| format!("{}/{}.{}.npy", dir, name, kind);
+        let features: Vec<u8> = self.features.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let actions: Vec<u8> = self.actions.iter().flat_map(|v| v.to_le_bytes()).collect();
+        write_npy(&path("features"), "<f4", &[n, N_CHANNEL, N_TILE], &features)?;
+        write_npy(&path("masks"), "|u1", &[n, ACTION_SIZE], &self.masks)?;
+        write_npy(&path("actions"), "<i4", &[n], &actions)?;
+        write_npy(&path("seats"), "|u1", &[n], &self.seats)?;
+        Ok(())
+    }
+}
+
+// numpyの.npy形式(version 1.0)で書き出し
+pub fn write_npy(path: &str, descr: &str, shape: &[usize], data: &[u8]) -> Result<(), String> {
+    let shape_str = match shape.len() {
+        1 => format!("({},)", shape[0]),
+        _ => format!(
+            "({})",
+            shape
+                .iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        descr, shape_str
+    );
+    // magic(6) + version(2) + header_len(2) + header が64の倍数になるように空白で埋める
+    while (10 + header.len() + 1) % 64 != 0 {
+        header.push(' ');
+    }
+    header.push('\n');
+
+    let mut buf = b"\x93NUMPY\x01\x00".to_vec();
+    buf.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    buf.extend_from_slice(header.as_bytes());
+    buf.extend_from_slice(data);
+
+    if let Some(dir) = std::path::Path::new(path).parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(path, buf).map_err(|e| format!("{}: {}", e, path))
+}
+
+#[test]
+fn test_npy_header() {
+    let path = std::env::temp_dir().join("mahjong_test_npy_header.npy");
+    let path = path.to_str().unwrap();
+    write_npy(path, "<i4", &[3], &[1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0]).unwrap();
+    let buf = std::fs::read(path).unwrap();
+    std::fs::remove_file(path).ok();
+
+    let header_len = u16::from_le_bytes([buf[8], buf[9]]) as usize;
+    assert_eq!(&buf[..8], b"\x93NUMPY\x01\x00");
+    assert_eq!((10 + header_len) % 64, 0);
+    assert_eq!(buf[10 + header_len - 1], b'\n');
+    let header = std::str::from_utf8(&buf[10..10 + header_len]).unwrap();
+    assert!(header.starts_with("{'descr': '<i4', 'fortran_order': False, 'shape': (3,), }"));
+    assert_eq!(buf.len(), 10 + header_len + 12);
+}
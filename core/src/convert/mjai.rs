@@ -1,13 +1,18 @@
 use serde::{Deserialize, Serialize};
 
+use crate::hand::{get_points, get_score_title};
 use crate::model::*;
+use crate::util::common::{calc_ranks, vec_remove};
+
+// start_kyoku, tsumoで全員の手牌,ツモ牌を公開する場合のid (牌譜出力用)
+pub const MJAI_OBSERVER: Seat = SEAT;
 
 // [MjaiEvent]
 // サーバ側から送信する情報
 // id: 自分の座席
 // seat: 行動を行ったプレイヤーの座席
 // target: 行動の対象となるプレイヤー(ロン, チー, ポン, 槓など)
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 #[serde(rename_all = "snake_case")]
 pub enum MjaiEvent {
@@ -16,16 +21,21 @@ pub enum MjaiEvent {
         protocol_version: usize,
     },
     StartGame {
+        #[serde(default)]
         id: Seat,
         names: [String; SEAT],
+        #[serde(default = "default_kyoku_first")]
         kyoku_first: usize, // 0: 4人南, 4: 4人東 (EventNew.modeとは割当が異なることに注意)
-        aka_flag: bool,     // true: 赤ドラあり
+        #[serde(default = "default_aka_flag")]
+        aka_flag: bool, // true: 赤ドラあり
     },
     StartKyoku {
         bakaze: String,
         dora_marker: String,
         kyoku: usize, // counts from 1
+        #[serde(default)]
         honba: usize,
+        #[serde(default)]
         kyotaku: usize,
         oya: Seat,
         tehais: [Vec<String>; SEAT],
@@ -67,6 +77,10 @@ pub enum MjaiEvent {
         actor: Seat,
         consumed: Vec<String>,
     },
+    Nukidora {
+        actor: Seat,
+        pai: String,
+    },
     Dora {
         dora_marker: String,
     },
@@ -84,7 +98,7 @@ pub enum MjaiEvent {
         pai: String,
         uradora_markers: Vec<String>,
         hora_tehais: Vec<String>,
-        yakus: Vec<String>,
+        yakus: Vec<(String, usize)>,
         fu: usize,
         fan: usize,
         hora_points: Point,
@@ -93,6 +107,7 @@ pub enum MjaiEvent {
     },
     Ryukyoku {
         reason: String,
+        #[serde(default)]
         tehais: Vec<Vec<String>>,
         tenpais: [bool; SEAT],
         deltas: [Point; SEAT],
@@ -157,7 +172,7 @@ impl MjaiEvent {
     }
 
     pub fn tsumo(id: Seat, seat: Seat, tile: Tile) -> Self {
-        let t = if id == seat {
+        let t = if id == seat || id == MJAI_OBSERVER {
             tile_to_mjai(tile)
         } else {
             "?".to_string()
@@ -232,6 +247,41 @@ impl MjaiEvent {
         }
     }
 
+    pub fn meld(stg: &Stage, event: &EventMeld) -> Self {
+        match event.meld_type {
+            MeldType::Chi => {
+                let lt = stg.last_tile.unwrap();
+                Self::chi(event.seat, &event.consumed, lt.2, lt.0)
+            }
+            MeldType::Pon => {
+                let lt = stg.last_tile.unwrap();
+                Self::pon(event.seat, &event.consumed, lt.2, lt.0)
+            }
+            MeldType::Minkan => {
+                let lt = stg.last_tile.unwrap();
+                Self::daiminkan(event.seat, &event.consumed, lt.2, lt.0)
+            }
+            MeldType::Ankan => Self::ankan(event.seat, &event.consumed),
+            MeldType::Kakan => {
+                let c = event.consumed[0];
+                let t = c.to_normal();
+                let t0 = if t.is_suit() && t.1 == 5 && c.1 != 0 {
+                    Tile(t.0, 0)
+                } else {
+                    t
+                };
+                Self::kakan(event.seat, &event.consumed, &vec![t, t, t0])
+            }
+        }
+    }
+
+    pub fn nukidora(seat: Seat) -> Self {
+        Self::Nukidora {
+            actor: seat,
+            pai: tile_to_mjai(Tile(TZ, WN)),
+        }
+    }
+
     pub fn dora(tile: Tile) -> Self {
         Self::Dora {
             dora_marker: tile_to_mjai(tile),
@@ -247,34 +297,47 @@ impl MjaiEvent {
         deltas: &[Point; SEAT],
         scores: &[Score; SEAT],
     ) -> Self {
+        // 和了牌を除いた手牌
+        let mut hand = context.hand.clone();
+        if let Some(i) = hand.iter().rposition(|&t| t == tile) {
+            hand.remove(i);
+        } else if let Some(i) = hand.iter().rposition(|t| t.to_normal() == tile.to_normal()) {
+            hand.remove(i);
+        }
+
+        // 役満の飜数は1倍につき13飜とする
+        let fan = if context.yakuman_times > 0 {
+            context.yakuman_times * 13
+        } else {
+            context.fan
+        };
+
         Self::Hora {
             actor: seat,
             target: target,
             pai: tile_to_mjai(tile),
             uradora_markers: tiles_to_mjai(ura_doras),
-            hora_tehais: vec![], // TODO
-            yakus: vec![],       // TODO
+            hora_tehais: tiles_to_mjai(&hand),
+            yakus: yakus_to_mjai(&context.yakus),
             fu: context.fu,
-            fan: context.fan,
+            fan: fan,
             hora_points: context.points.0,
             deltas: deltas.clone(),
             scores: scores.clone(),
         }
     }
 
+    // hands: 各プレイヤーの手牌 (非公開の牌はZ8)
     pub fn ryukyoku(
         type_: DrawType,
+        hands: &[Vec<Tile>; SEAT],
         is_tenpai: &[bool; SEAT],
         deltas: &[Point; SEAT],
         scores: &[Score; SEAT],
     ) -> Self {
-        let reason = match type_ {
-            DrawType::Kouhaiheikyoku => "fanpai",
-            _ => "",
-        };
         Self::Ryukyoku {
-            reason: reason.to_string(), // TODO
-            tehais: vec![],             // TODO
+            reason: draw_type_to_mjai(type_).to_string(),
+            tehais: hands.iter().map(tiles_to_mjai).collect(),
             tenpais: is_tenpai.clone(),
             deltas: deltas.clone(),
             scores: scores.clone(),
@@ -346,6 +409,10 @@ pub enum MjaiAction {
         actor: Seat,
         reason: String,
     },
+    Nukidora {
+        actor: Seat,
+        pai: String,
+    },
     None {},
 }
 
@@ -388,7 +455,10 @@ impl MjaiAction {
                 actor: seat,
                 reason: "kyushukyuhai".to_string(),
             },
-            ActionType::Kita => panic!(),
+            ActionType::Kita => Self::Nukidora {
+                actor: seat,
+                pai: tile_to_mjai(Tile(TZ, WN)),
+            },
             ActionType::Chi => {
                 let (target_seat, _, target_tile) = stage.last_tile.unwrap();
                 Self::Chi {
@@ -427,21 +497,21 @@ impl MjaiAction {
         })
     }
 
-    pub fn to_action(&self, is_turn: bool) -> Action {
-        match self {
+    pub fn to_action(&self, is_turn: bool) -> Result<Action, String> {
+        Ok(match self {
             Self::Join { .. } => panic!(),
             Self::Dahai { pai, tsumogiri, .. } => {
                 if *tsumogiri {
                     Action::nop()
                 } else {
-                    Action::discard(tile_from_mjai(pai))
+                    Action::discard(tile_from_mjai(pai)?)
                 }
             }
-            Self::Chi { consumed, .. } => Action::chi(tiles_from_mjai(consumed)),
-            Self::Pon { consumed, .. } => Action::pon(tiles_from_mjai(consumed)),
-            Self::Kakan { pai, .. } => Action::kakan(tile_from_mjai(pai)),
-            Self::Daiminkan { consumed, .. } => Action::minkan(tiles_from_mjai(consumed)),
-            Self::Ankan { consumed, .. } => Action::ankan(tiles_from_mjai(consumed)),
+            Self::Chi { consumed, .. } => Action::chi(tiles_from_mjai(consumed)?),
+            Self::Pon { consumed, .. } => Action::pon(tiles_from_mjai(consumed)?),
+            Self::Kakan { pai, .. } => Action::kakan(tile_from_mjai(pai)?),
+            Self::Daiminkan { consumed, .. } => Action::minkan(tiles_from_mjai(consumed)?),
+            Self::Ankan { consumed, .. } => Action::ankan(tiles_from_mjai(consumed)?),
             Self::Reach { .. } => panic!(),
             Self::Hora { .. } => {
                 if is_turn {
@@ -451,14 +521,477 @@ impl MjaiAction {
                 }
             }
             Self::Ryukyoku { .. } => Action::kyushukyuhai(),
+            Self::Nukidora { .. } => Action::kita(),
             Self::None {} => Action::nop(),
+        })
+    }
+}
+
+// [MjaiSerializer]
+// Eventをmjai形式の牌譜(1行1イベントのjson)に変換 (全員の手牌を公開)
+#[derive(Debug)]
+pub struct MjaiSerializer {
+    names: [String; SEAT],
+    is_new_game: bool,
+    try_riichi: Option<Seat>,
+    records: Vec<MjaiEvent>,
+}
+
+impl MjaiSerializer {
+    pub fn new(names: [String; SEAT]) -> Self {
+        Self {
+            names: names,
+            is_new_game: false,
+            try_riichi: None,
+            records: vec![],
+        }
+    }
+
+    pub fn push_event(&mut self, stg: &Stage, event: &Event) {
+        match event {
+            Event::Begin(_) => {
+                self.records.clear();
+                self.is_new_game = true;
+            }
+            Event::New(e) => {
+                if self.is_new_game {
+                    self.is_new_game = false;
+                    self.records.push(MjaiEvent::StartGame {
+                        id: 0,
+                        names: self.names.clone(),
+                        kyoku_first: if e.mode % 10 == 2 { 0 } else { 4 },
+                        aka_flag: e.rule.red5 != [0; 3],
+                    });
+                }
+                self.try_riichi = None;
+
+                // 親番の14枚目の牌は最初のツモとして扱うので取り除く
+                let mut hands = e.hands.clone();
+                let d = stg.players[e.kyoku].drawn.unwrap();
+                vec_remove(&mut hands[e.kyoku], &d);
+
+                self.records.push(MjaiEvent::start_kyoku(
+                    MJAI_OBSERVER,
+                    e.bakaze,
+                    e.kyoku,
+                    e.honba,
+                    e.kyoutaku,
+                    &e.doras,
+                    &hands,
+                    &e.scores,
+                ));
+                self.records
+                    .push(MjaiEvent::tsumo(MJAI_OBSERVER, e.kyoku, d));
+            }
+            Event::Deal(e) => {
+                self.confirm_riichi_accepted(stg);
+                self.records
+                    .push(MjaiEvent::tsumo(MJAI_OBSERVER, e.seat, e.tile));
+            }
+            Event::Discard(e) => {
+                if e.is_riichi {
+                    self.records.push(MjaiEvent::reach(e.seat));
+                }
+                self.records
+                    .push(MjaiEvent::dahai(e.seat, e.tile, e.is_drawn));
+                if e.is_riichi {
+                    self.try_riichi = Some(e.seat);
+                }
+            }
+            Event::Meld(e) => {
+                self.confirm_riichi_accepted(stg);
+                self.records.push(MjaiEvent::meld(stg, e));
+            }
+            Event::Kita(e) => {
+                self.records.push(MjaiEvent::nukidora(e.seat));
+            }
+            Event::Dora(e) => {
+                self.records.push(MjaiEvent::dora(e.tile));
+            }
+            Event::Win(e) => {
+                for (seat, deltas, ctx) in &e.contexts {
+                    let (target, tile) = get_hora_target(stg, *seat);
+                    self.records.push(MjaiEvent::hora(
+                        *seat,
+                        target,
+                        tile,
+                        &e.ura_doras,
+                        ctx,
+                        deltas,
+                        &stg.get_scores(),
+                    ));
+                }
+                self.records.push(MjaiEvent::end_kyoku());
+            }
+            Event::Draw(e) => {
+                self.records.push(MjaiEvent::ryukyoku(
+                    e.type_,
+                    &get_draw_hands(stg, e),
+                    &e.tenpais,
+                    &e.points,
+                    &stg.get_scores(),
+                ));
+                self.records.push(MjaiEvent::end_kyoku());
+            }
+            Event::End(_) => {
+                self.records.push(MjaiEvent::end_game(&stg.get_scores()));
+            }
+        }
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut res = String::new();
+        for r in &self.records {
+            res += &serde_json::to_string(r).unwrap();
+            res += "\n";
+        }
+        res
+    }
+
+    fn confirm_riichi_accepted(&mut self, stg: &Stage) {
+        if let Some(s) = self.try_riichi {
+            self.try_riichi = None;
+            self.records
+                .push(MjaiEvent::reach_accepted(s, &stg.get_scores()));
+        }
+    }
+}
+
+// [MjaiDeserializer]
+// mjai形式の牌譜をEventに変換
+#[derive(Debug)]
+pub struct MjaiDeserializer {
+    pub names: [String; SEAT],
+    mode: usize,
+    rule: Rule,
+    n_seat: usize,
+    events: Vec<Event>,
+    start: Option<MjaiEvent>, // 親の最初のツモまで局開始を保留
+    win: Option<Event>,       // ダブロンに対応するため局終了まで和了を保留
+    bakaze: usize,
+    oya: Seat,
+    riichi: Option<Seat>,
+    last_drawns: [Option<Tile>; SEAT],
+    scores: [Score; SEAT], // 直前の局終了時の得点
+}
+
+impl Default for MjaiDeserializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MjaiDeserializer {
+    pub fn new() -> Self {
+        Self {
+            names: Default::default(),
+            mode: 1,
+            rule: Rule::default(),
+            n_seat: SEAT,
+            events: vec![],
+            start: None,
+            win: None,
+            bakaze: 0,
+            oya: 0,
+            riichi: None,
+            last_drawns: [None; SEAT],
+            scores: [0; SEAT],
+        }
+    }
+
+    pub fn deserialize(&mut self, contents: &str) -> Result<Vec<Event>, String> {
+        let mut records = vec![];
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let r: MjaiEvent =
+                serde_json::from_str(line).map_err(|e| format!("line {}: {}", i + 1, e))?;
+            records.push(r);
+        }
+
+        // 南場以降の局があれば半荘として扱う
+        let is_hanchan = records.iter().any(|r| match r {
+            MjaiEvent::StartKyoku { bakaze, .. } => bakaze != "E",
+            _ => false,
+        });
+
+        let mut events = vec![];
+        for mut r in records {
+            if let MjaiEvent::StartGame { kyoku_first, .. } = &mut r {
+                if is_hanchan {
+                    *kyoku_first = 0;
+                }
+            }
+            events.append(&mut self.push_record(r)?);
+        }
+        self.flush_win();
+        events.append(&mut self.events);
+        Ok(events)
+    }
+
+    // 1イベントずつ変換 (対戦中の逐次処理用). 変換後のEventを返却
+    pub fn push_record(&mut self, record: MjaiEvent) -> Result<Vec<Event>, String> {
+        self.do_record(record)?;
+        Ok(std::mem::take(&mut self.events))
+    }
+
+    fn do_record(&mut self, record: MjaiEvent) -> Result<(), String> {
+        match record {
+            MjaiEvent::StartGame {
+                names,
+                kyoku_first,
+                aka_flag,
+                ..
+            } => {
+                self.names = names;
+                self.mode = if kyoku_first == 0 { 2 } else { 1 };
+                self.rule = Rule::default();
+                if !aka_flag {
+                    self.rule.red5 = [0; 3];
+                }
+                self.events.push(Event::begin());
+            }
+            MjaiEvent::StartKyoku {
+                ref bakaze,
+                oya,
+                ref tehais,
+                ..
+            } => {
+                self.flush_win();
+                // 座席3の配牌がなければ三麻 (tehaisは4要素固定のため要素数の不足はjsonの読み込み時にエラー)
+                self.n_seat = if tehais[3].is_empty() { 3 } else { 4 };
+                if oya >= self.n_seat {
+                    return Err(format!("invalid oya: {}", oya));
+                }
+                self.mode = self.mode % 10 + if self.n_seat == 3 { 10 } else { 0 };
+                self.bakaze = wind_from_mjai(bakaze)?;
+                self.oya = oya;
+                self.riichi = None;
+                self.last_drawns = [None; SEAT];
+                self.start = Some(record);
+            }
+            MjaiEvent::Tsumo { actor, pai } => {
+                self.check_actor(actor)?;
+                let t = tile_from_mjai(&pai)?;
+                self.last_drawns[actor] = Some(t);
+                if let Some(start) = self.start.take() {
+                    self.do_start_kyoku(start, actor, t)?;
+                } else {
+                    self.events.push(Event::deal(actor, t));
+                }
+            }
+            MjaiEvent::Dahai {
+                actor,
+                pai,
+                tsumogiri,
+            } => {
+                self.check_actor(actor)?;
+                let is_riichi = self.riichi == Some(actor);
+                self.riichi = None;
+                self.events.push(Event::discard(
+                    actor,
+                    tile_from_mjai(&pai)?,
+                    tsumogiri,
+                    is_riichi,
+                ));
+            }
+            MjaiEvent::Reach { actor } => {
+                self.check_actor(actor)?;
+                self.riichi = Some(actor);
+            }
+            MjaiEvent::Chi {
+                actor, consumed, ..
+            } => self.push_meld(actor, MeldType::Chi, &consumed)?,
+            MjaiEvent::Pon {
+                actor, consumed, ..
+            } => self.push_meld(actor, MeldType::Pon, &consumed)?,
+            MjaiEvent::Daiminkan {
+                actor, consumed, ..
+            } => self.push_meld(actor, MeldType::Minkan, &consumed)?,
+            MjaiEvent::Kakan { actor, pai, .. } => {
+                self.push_meld(actor, MeldType::Kakan, &[pai])?
+            }
+            MjaiEvent::Ankan { actor, consumed } => {
+                self.push_meld(actor, MeldType::Ankan, &consumed)?
+            }
+            MjaiEvent::Nukidora { actor, .. } => {
+                self.check_actor(actor)?;
+                let is_drawn = self.last_drawns[actor] == Some(Tile(TZ, WN));
+                self.events.push(Event::kita(actor, is_drawn));
+            }
+            MjaiEvent::Dora { dora_marker } => {
+                self.events.push(Event::dora(tile_from_mjai(&dora_marker)?));
+            }
+            MjaiEvent::Hora {
+                actor,
+                pai,
+                uradora_markers,
+                hora_tehais,
+                yakus,
+                fu,
+                deltas,
+                scores,
+                ..
+            } => {
+                self.check_actor(actor)?;
+                self.scores = scores;
+                let mut hand = tiles_from_mjai_unsorted(&hora_tehais)?;
+                if hand.len() % 3 == 1 {
+                    hand.push(tile_from_mjai(&pai)?);
+                }
+                let seat_wind = (actor + self.n_seat - self.oya) % self.n_seat;
+                let (yakus, fan, yakuman_times) = yakus_from_mjai(&yakus, seat_wind, self.bakaze);
+                let is_dealer = actor == self.oya;
+                let ctx = WinContext {
+                    hand: hand,
+                    yakus: yakus,
+                    fu: fu,
+                    fan: fan,
+                    yakuman_times: yakuman_times,
+                    score_title: get_score_title(fu, fan, yakuman_times, &self.rule),
+                    points: get_points(is_dealer, fu, fan, yakuman_times, &self.rule),
+                };
+
+                // ダブロンの場合は2人目以降の和了を同じイベントに追加
+                if let Some(Event::Win(e)) = &mut self.win {
+                    e.contexts.push((actor, deltas, ctx));
+                    return Ok(());
+                }
+                let ura_doras = tiles_from_mjai_unsorted(&uradora_markers)?;
+                self.win = Some(Event::win(ura_doras, vec![(actor, deltas, ctx)]));
+            }
+            MjaiEvent::Ryukyoku {
+                reason,
+                tehais,
+                tenpais,
+                deltas,
+                scores,
+                ..
+            } => {
+                self.scores = scores;
+                // 公開された手牌のみ復元
+                let mut hands = [vec![], vec![], vec![], vec![]];
+                for (s, h) in tehais.iter().enumerate().take(SEAT) {
+                    if !h.is_empty() && h.iter().all(|t| t != "?") {
+                        hands[s] = tiles_from_mjai_unsorted(h)?;
+                    }
+                }
+                self.events.push(Event::draw(
+                    draw_type_from_mjai(&reason),
+                    hands,
+                    tenpais,
+                    deltas,
+                ));
+            }
+            MjaiEvent::EndKyoku {} => self.flush_win(),
+            MjaiEvent::EndGame { scores } => {
+                self.flush_win();
+                // 最終局の後の得点変動(供託の精算)を復元 最終ポイントは牌譜に含まれないため0
+                let mut points = [0; SEAT];
+                for s in 0..self.n_seat {
+                    points[s] = scores[s] - self.scores[s];
+                }
+                let ranks = calc_ranks(&scores, self.n_seat);
+                self.events.push(Event::end(points, ranks, [0; SEAT]));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn flush_win(&mut self) {
+        if let Some(e) = self.win.take() {
+            self.events.push(e);
+        }
+    }
+
+    fn do_start_kyoku(&mut self, start: MjaiEvent, seat: Seat, tile: Tile) -> Result<(), String> {
+        if let MjaiEvent::StartKyoku {
+            kyoku,
+            honba,
+            kyotaku,
+            oya,
+            dora_marker,
+            tehais,
+            scores,
+            ..
+        } = start
+        {
+            if oya != seat || kyoku == 0 {
+                return Err("invalid start_kyoku".to_string());
+            }
+            let mut hands = [vec![], vec![], vec![], vec![]];
+            for s in 0..self.n_seat {
+                hands[s] = tiles_from_mjai_unsorted(&tehais[s])?;
+            }
+            hands[oya].push(tile);
+            self.events.push(Event::new(
+                self.bakaze,
+                kyoku - 1,
+                honba,
+                kyotaku,
+                vec![tile_from_mjai(&dora_marker)?],
+                scores,
+                hands,
+                self.mode,
+                self.rule.clone(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn push_meld(
+        &mut self,
+        seat: Seat,
+        meld_type: MeldType,
+        consumed: &[String],
+    ) -> Result<(), String> {
+        self.check_actor(seat)?;
+        let consumed = tiles_from_mjai_unsorted(consumed)?;
+        self.events.push(Event::meld(seat, meld_type, consumed));
+        Ok(())
+    }
+
+    fn check_actor(&self, actor: Seat) -> Result<(), String> {
+        if actor < self.n_seat {
+            Ok(())
+        } else {
+            Err(format!("invalid actor: {}", actor))
         }
     }
 }
 
 // [Utility]
+// 和了者に対する(放銃者, 和了牌)を返却 (ツモ和了の場合は放銃者=和了者)
+pub fn get_hora_target(stg: &Stage, seat: Seat) -> (Seat, Tile) {
+    if stg.turn == seat {
+        (seat, stg.players[seat].drawn.unwrap())
+    } else {
+        let lt = stg.last_tile.unwrap();
+        (lt.0, lt.2)
+    }
+}
+
+// 流局時の手牌を返却 (公開されていない手牌はZ8)
+pub fn get_draw_hands(stg: &Stage, event: &EventDraw) -> [Vec<Tile>; SEAT] {
+    let mut hands = [vec![], vec![], vec![], vec![]];
+    let it = stg.players.iter().zip(event.hands.iter());
+    for (h, (pl, eh)) in hands.iter_mut().zip(it).take(stg.get_seat_count()) {
+        *h = if eh.is_empty() {
+            vec![Z8; tiles_from_tile_table(&pl.hand).len()]
+        } else {
+            eh.clone()
+        };
+    }
+    hands
+}
+
 pub fn tile_to_mjai(t: Tile) -> String {
-    if t.is_hornor() {
+    if t == Z8 {
+        "?".to_string()
+    } else if t.is_hornor() {
         assert!(WE <= t.1 && t.1 <= DR);
         let hornor = ["", "E", "S", "W", "N", "P", "F", "C"];
         hornor[t.1].to_string()
@@ -473,8 +1006,8 @@ pub fn tile_to_mjai(t: Tile) -> String {
     }
 }
 
-pub fn tile_from_mjai(sym: &str) -> Tile {
-    match sym {
+pub fn tile_from_mjai(sym: &str) -> Result<Tile, String> {
+    Ok(match sym {
         "?" => Z8,
         "E" => Tile(TZ, WE),
         "S" => Tile(TZ, WS),
@@ -484,31 +1017,203 @@ pub fn tile_from_mjai(sym: &str) -> Tile {
         "F" => Tile(TZ, DG),
         "C" => Tile(TZ, DR),
         _ => {
-            let sym = sym.as_bytes();
-            let ti = match sym[1] {
+            let err = || format!("invalid tile: {}", sym);
+            let b = sym.as_bytes();
+            if b.len() != 2 && b.len() != 3 {
+                return Err(err());
+            }
+            let ti = match b[1] {
                 b'm' => 0,
                 b'p' => 1,
                 b's' => 2,
-                _ => panic!(),
+                _ => return Err(err()),
             } as Type;
-            let mut ni = (sym[0] - b'0') as Tnum;
-            if ni == 5 && sym.len() == 3 {
+            let mut ni = match b[0] {
+                b'1'..=b'9' => (b[0] - b'0') as Tnum,
+                _ => return Err(err()),
+            };
+            if b.len() == 3 {
+                // 赤5は"5mr"のように末尾に"r"
+                if ni != 5 || b[2] != b'r' {
+                    return Err(err());
+                }
                 ni = 0;
             }
-            assert!(ni < TNUM);
             Tile(ti, ni)
         }
-    }
+    })
 }
 
 fn tiles_to_mjai(v: &Vec<Tile>) -> Vec<String> {
     v.iter().map(|&t| tile_to_mjai(t)).collect()
 }
 
-fn tiles_from_mjai(v: &Vec<String>) -> Vec<Tile> {
-    let mut v2: Vec<Tile> = v.iter().map(|t| tile_from_mjai(t)).collect();
+pub fn tiles_from_mjai(v: &[String]) -> Result<Vec<Tile>, String> {
+    let mut v2 = tiles_from_mjai_unsorted(v)?;
     v2.sort();
-    v2
+    Ok(v2)
+}
+
+fn tiles_from_mjai_unsorted(v: &[String]) -> Result<Vec<Tile>, String> {
+    v.iter().map(|t| tile_from_mjai(t)).collect()
+}
+
+fn default_kyoku_first() -> usize {
+    4
+}
+
+fn default_aka_flag() -> bool {
+    true
+}
+
+fn wind_from_mjai(sym: &str) -> Result<usize, String> {
+    ["E", "S", "W", "N"]
+        .iter()
+        .position(|&w| w == sym)
+        .ok_or(format!("invalid wind: {}", sym))
+}
+
+fn draw_type_to_mjai(type_: DrawType) -> &'static str {
+    match type_ {
+        DrawType::Unknown => "",
+        DrawType::Kyushukyuhai => "kyushukyuhai",
+        DrawType::Suufuurenda => "sufonrenta",
+        DrawType::Suukansanra => "sukaikan",
+        DrawType::Suuchariichi => "suchareach",
+        DrawType::Sanchaho => "sanchaho",
+        DrawType::Kouhaiheikyoku => "fanpai",
+        DrawType::Nagashimangan => "nagashimangan",
+    }
+}
+
+fn draw_type_from_mjai(sym: &str) -> DrawType {
+    match sym {
+        "kyushukyuhai" => DrawType::Kyushukyuhai,
+        "sufonrenta" => DrawType::Suufuurenda,
+        "sukaikan" => DrawType::Suukansanra,
+        "suchareach" => DrawType::Suuchariichi,
+        "sanchaho" => DrawType::Sanchaho,
+        "fanpai" => DrawType::Kouhaiheikyoku,
+        "nagashimangan" => DrawType::Nagashimangan,
+        _ => DrawType::Unknown,
+    }
+}
+
+// (天鳳の役名, mjaiの役名)
+// mjaiに存在しない二倍役満は飜数(26飜)で区別する
+const MJAI_YAKUS: &[(&str, &str)] = &[
+    ("門前清自摸和", "menzenchin_tsumoho"),
+    ("立直", "reach"),
+    ("一発", "ippatsu"),
+    ("槍槓", "chankan"),
+    ("嶺上開花", "rinshankaiho"),
+    ("海底摸月", "haiteiraoyue"),
+    ("河底撈魚", "hoteiraoyui"),
+    ("平和", "pinfu"),
+    ("断幺九", "tanyaochu"),
+    ("一盃口", "ipeko"),
+    ("自風", "jikaze"),
+    ("場風", "bakaze"),
+    ("役牌 白", "haku"),
+    ("役牌 發", "hatsu"),
+    ("役牌 中", "chun"),
+    ("両立直", "double_reach"),
+    ("七対子", "chitoitsu"),
+    ("混全帯幺九", "honchantaiyao"),
+    ("一気通貫", "ikkitsukan"),
+    ("三色同順", "sanshokudojun"),
+    ("三色同刻", "sanshokudoko"),
+    ("三槓子", "sankantsu"),
+    ("対々和", "toitoiho"),
+    ("三暗刻", "sananko"),
+    ("小三元", "shosangen"),
+    ("混老頭", "honroto"),
+    ("二盃口", "ryanpeko"),
+    ("純全帯幺九", "junchantaiyao"),
+    ("混一色", "honiso"),
+    ("清一色", "chiniso"),
+    ("人和", "renho"),
+    ("天和", "tenho"),
+    ("地和", "chiho"),
+    ("大三元", "daisangen"),
+    ("四暗刻", "suanko"),
+    ("四暗刻単騎", "suanko"),
+    ("字一色", "tsuiso"),
+    ("緑一色", "ryuiso"),
+    ("清老頭", "chinroto"),
+    ("九蓮宝燈", "churenpoton"),
+    ("純正九蓮宝燈", "churenpoton"),
+    ("国士無双", "kokushimuso"),
+    ("国士無双１３面", "kokushimuso"),
+    ("大四喜", "daisushi"),
+    ("小四喜", "shosushi"),
+    ("四槓子", "sukantsu"),
+    ("ドラ", "dora"),
+    ("裏ドラ", "uradora"),
+    ("赤ドラ", "akadora"),
+    ("抜きドラ", "nukidora"),
+];
+
+// 役満の飜数は1倍につき13飜に変換
+fn yakus_to_mjai(yakus: &[(String, usize)]) -> Vec<(String, usize)> {
+    yakus
+        .iter()
+        .map(|(name, fan)| {
+            let key = if name.starts_with("自風") || name.starts_with("場風") {
+                name.split(' ').next().unwrap()
+            } else {
+                name
+            };
+            let sym = match MJAI_YAKUS.iter().find(|y| y.0 == key) {
+                Some(y) => y.1.to_string(),
+                None => name.clone(),
+            };
+            let fan = if *fan >= 13 { (fan - 12) * 13 } else { *fan };
+            (sym, fan)
+        })
+        .collect()
+}
+
+// (役一覧, 飜数, 役満倍率)を返却
+// seat_wind, prevalent_wind: 0: 東, 1: 南, 2: 西, 3: 北
+fn yakus_from_mjai(
+    yakus: &Vec<(String, usize)>,
+    seat_wind: usize,
+    prevalent_wind: usize,
+) -> (Vec<(String, usize)>, usize, usize) {
+    let jp_wind = ["東", "南", "西", "北"];
+    let mut res = vec![];
+    let mut fan = 0;
+    let mut yakuman_times = 0;
+    for (sym, f) in yakus {
+        let name = match sym.as_str() {
+            "jikaze" => format!("自風 {}", jp_wind[seat_wind]),
+            "bakaze" => format!("場風 {}", jp_wind[prevalent_wind]),
+            _ => {
+                let ys: Vec<&str> = MJAI_YAKUS
+                    .iter()
+                    .filter(|y| y.1 == sym)
+                    .map(|y| y.0)
+                    .collect();
+                match ys.len() {
+                    0 => sym.clone(),
+                    1 => ys[0].to_string(),
+                    _ => ys[if *f >= 26 { 1 } else { 0 }].to_string(),
+                }
+            }
+        };
+        if *f >= 13 {
+            yakuman_times += f / 13;
+            res.push((name, f / 13 + 12));
+        } else {
+            fan += f;
+            res.push((name, *f));
+        }
+    }
+    if yakuman_times > 0 {
+        fan = 0;
+    }
+    (res, fan, yakuman_times)
 }
 
 fn create_tehais(hands: &[Vec<Tile>; SEAT], seat: usize) -> [Vec<String>; SEAT] {
@@ -516,7 +1221,7 @@ fn create_tehais(hands: &[Vec<Tile>; SEAT], seat: usize) -> [Vec<String>; SEAT]
     for (seat2, hand) in hands.iter().enumerate() {
         let mut mjai_hand = vec![];
         for &t in hand {
-            if seat == seat2 {
+            if seat == seat2 || seat == MJAI_OBSERVER {
                 mjai_hand.push(tile_to_mjai(t));
             } else {
                 mjai_hand.push("?".to_string());
@@ -546,3 +1251,175 @@ fn test_mjai_message() {
         println!("{:?}", a);
     }
 }
+
+#[test]
+fn test_mjai_log_round_trip() {
+    use crate::actor::create_actor;
+    use crate::controller::StageController;
+
+    // 4人南 南2局 (チー, ポン, 加槓, 暗槓, リーチ, ロン)
+    let log = r#"{"type":"start_game","id":0,"names":["a","b","c","d"],"kyoku_first":0,"aka_flag":true}
+{"type":"start_kyoku","bakaze":"S","dora_marker":"N","kyoku":2,"honba":0,"kyotaku":0,"oya":1,"tehais":[["1m","3m","3p","5p","6p","6p","6p","9p","6s","7s","S","P","F"],["1p","2p","4p","5pr","5p","7p","3s","5s","8s","E","E","S","W"],["1m","2m","6m","8m","9m","3p","7p","8p","1s","1s","3s","5s","9s"],["1m","2m","4m","7m","7m","7m","9m","2s","4s","5sr","E","F","C"]],"scores":[26200,18200,25400,30200]}
+{"type":"tsumo","actor":1,"pai":"W"}
+{"type":"dahai","actor":1,"pai":"S","tsumogiri":false}
+{"type":"tsumo","actor":2,"pai":"P"}
+{"type":"dahai","actor":2,"pai":"P","tsumogiri":true}
+{"type":"tsumo","actor":3,"pai":"S"}
+{"type":"dahai","actor":3,"pai":"S","tsumogiri":true}
+{"type":"tsumo","actor":0,"pai":"5m"}
+{"type":"dahai","actor":0,"pai":"S","tsumogiri":false}
+{"type":"tsumo","actor":1,"pai":"6s"}
+{"type":"dahai","actor":1,"pai":"1p","tsumogiri":false}
+{"type":"tsumo","actor":2,"pai":"1m"}
+{"type":"dahai","actor":2,"pai":"9m","tsumogiri":false}
+{"type":"tsumo","actor":3,"pai":"3s"}
+{"type":"dahai","actor":3,"pai":"E","tsumogiri":false}
+{"type":"pon","actor":1,"target":3,"pai":"E","consumed":["E","E"]}
+{"type":"dahai","actor":1,"pai":"2p","tsumogiri":false}
+{"type":"tsumo","actor":2,"pai":"4s"}
+{"type":"dahai","actor":2,"pai":"9s","tsumogiri":false}
+{"type":"tsumo","actor":3,"pai":"8m"}
+{"type":"dahai","actor":3,"pai":"F","tsumogiri":false}
+{"type":"tsumo","actor":0,"pai":"P"}
+{"type":"dahai","actor":0,"pai":"F","tsumogiri":false}
+{"type":"tsumo","actor":1,"pai":"4s"}
+{"type":"dahai","actor":1,"pai":"7p","tsumogiri":false}
+{"type":"tsumo","actor":2,"pai":"7s"}
+{"type":"dahai","actor":2,"pai":"3p","tsumogiri":false}
+{"type":"tsumo","actor":3,"pai":"N"}
+{"type":"dahai","actor":3,"pai":"N","tsumogiri":true}
+{"type":"tsumo","actor":0,"pai":"7s"}
+{"type":"dahai","actor":0,"pai":"9p","tsumogiri":false}
+{"type":"tsumo","actor":1,"pai":"8m"}
+{"type":"dahai","actor":1,"pai":"8m","tsumogiri":true}
+{"type":"tsumo","actor":2,"pai":"W"}
+{"type":"dahai","actor":2,"pai":"W","tsumogiri":true}
+{"type":"tsumo","actor":3,"pai":"4p"}
+{"type":"dahai","actor":3,"pai":"C","tsumogiri":false}
+{"type":"tsumo","actor":0,"pai":"2s"}
+{"type":"dahai","actor":0,"pai":"2s","tsumogiri":true}
+{"type":"tsumo","actor":1,"pai":"C"}
+{"type":"dahai","actor":1,"pai":"C","tsumogiri":true}
+{"type":"tsumo","actor":2,"pai":"6s"}
+{"type":"dahai","actor":2,"pai":"1m","tsumogiri":false}
+{"type":"tsumo","actor":3,"pai":"6s"}
+{"type":"dahai","actor":3,"pai":"1m","tsumogiri":false}
+{"type":"tsumo","actor":0,"pai":"3p"}
+{"type":"dahai","actor":0,"pai":"1m","tsumogiri":false}
+{"type":"tsumo","actor":1,"pai":"9m"}
+{"type":"dahai","actor":1,"pai":"9m","tsumogiri":true}
+{"type":"tsumo","actor":2,"pai":"3m"}
+{"type":"dahai","actor":2,"pai":"6m","tsumogiri":false}
+{"type":"tsumo","actor":3,"pai":"N"}
+{"type":"dahai","actor":3,"pai":"N","tsumogiri":true}
+{"type":"tsumo","actor":0,"pai":"8s"}
+{"type":"dahai","actor":0,"pai":"7s","tsumogiri":false}
+{"type":"tsumo","actor":1,"pai":"E"}
+{"type":"kakan","actor":1,"pai":"E","consumed":["E","E","E"]}
+{"type":"tsumo","actor":1,"pai":"3m"}
+{"type":"dahai","actor":1,"pai":"3m","tsumogiri":true}
+{"type":"dora","dora_marker":"4p"}
+{"type":"tsumo","actor":2,"pai":"5m"}
+{"type":"dahai","actor":2,"pai":"5m","tsumogiri":true}
+{"type":"tsumo","actor":3,"pai":"7m"}
+{"type":"ankan","actor":3,"consumed":["7m","7m","7m","7m"]}
+{"type":"dora","dora_marker":"7p"}
+{"type":"tsumo","actor":3,"pai":"9s"}
+{"type":"dahai","actor":3,"pai":"9s","tsumogiri":true}
+{"type":"tsumo","actor":0,"pai":"6m"}
+{"type":"dahai","actor":0,"pai":"3m","tsumogiri":false}
+{"type":"tsumo","actor":1,"pai":"8p"}
+{"type":"dahai","actor":1,"pai":"8p","tsumogiri":true}
+{"type":"tsumo","actor":2,"pai":"5mr"}
+{"type":"dahai","actor":2,"pai":"5mr","tsumogiri":true}
+{"type":"tsumo","actor":3,"pai":"2p"}
+{"type":"dahai","actor":3,"pai":"9m","tsumogiri":false}
+{"type":"tsumo","actor":0,"pai":"7p"}
+{"type":"dahai","actor":0,"pai":"3p","tsumogiri":false}
+{"type":"chi","actor":1,"target":0,"pai":"3p","consumed":["4p","5p"]}
+{"type":"dahai","actor":1,"pai":"5pr","tsumogiri":false}
+{"type":"tsumo","actor":2,"pai":"5m"}
+{"type":"dahai","actor":2,"pai":"5m","tsumogiri":true}
+{"type":"tsumo","actor":3,"pai":"C"}
+{"type":"dahai","actor":3,"pai":"C","tsumogiri":true}
+{"type":"tsumo","actor":0,"pai":"9m"}
+{"type":"dahai","actor":0,"pai":"9m","tsumogiri":true}
+{"type":"tsumo","actor":1,"pai":"P"}
+{"type":"dahai","actor":1,"pai":"P","tsumogiri":true}
+{"type":"pon","actor":0,"target":1,"pai":"P","consumed":["P","P"]}
+{"type":"dahai","actor":0,"pai":"3p","tsumogiri":false}
+{"type":"tsumo","actor":1,"pai":"W"}
+{"type":"dahai","actor":1,"pai":"8s","tsumogiri":false}
+{"type":"tsumo","actor":2,"pai":"8s"}
+{"type":"reach","actor":2}
+{"type":"dahai","actor":2,"pai":"8m","tsumogiri":false}
+{"type":"reach_accepted","actor":2,"deltas":[0,0,-1000,0],"scores":[26200,18200,24400,30200]}
+{"type":"tsumo","actor":3,"pai":"6p"}
+{"type":"dahai","actor":3,"pai":"8m","tsumogiri":false}
+{"type":"tsumo","actor":0,"pai":"1p"}
+{"type":"dahai","actor":0,"pai":"1p","tsumogiri":true}
+{"type":"tsumo","actor":1,"pai":"C"}
+{"type":"dahai","actor":1,"pai":"C","tsumogiri":true}
+{"type":"tsumo","actor":2,"pai":"9s"}
+{"type":"dahai","actor":2,"pai":"9s","tsumogiri":true}
+{"type":"tsumo","actor":3,"pai":"2p"}
+{"type":"dahai","actor":3,"pai":"2m","tsumogiri":false}
+{"type":"tsumo","actor":0,"pai":"F"}
+{"type":"dahai","actor":0,"pai":"F","tsumogiri":true}
+{"type":"tsumo","actor":1,"pai":"2p"}
+{"type":"dahai","actor":1,"pai":"2p","tsumogiri":true}
+{"type":"tsumo","actor":2,"pai":"4m"}
+{"type":"dahai","actor":2,"pai":"4m","tsumogiri":true}
+{"type":"hora","actor":0,"target":2,"pai":"4m","uradora_markers":["S","1p","1p"],"hora_tehais":["5m","6m","5p","6p","6p","6p","7p","6s","7s","8s"],"yakus":[["haku",1],["dora",1]],"fu":30,"fan":2,"hora_points":2000,"deltas":[3000,0,-2000,0],"scores":[29200,18200,22400,30200]}
+{"type":"end_kyoku"}
+{"type":"end_game","scores":[29200,18200,22400,30200]}
+"#;
+
+    let mut deserializer = MjaiDeserializer::new();
+    let events = deserializer.deserialize(log).unwrap();
+    assert_eq!(deserializer.names[0], "a");
+
+    let nop = create_actor("Nop");
+    let actors = [
+        nop.clone_box(),
+        nop.clone_box(),
+        nop.clone_box(),
+        nop.clone_box(),
+    ];
+    let mut ctrl = StageController::new(actors, vec![]);
+    let mut serializer = MjaiSerializer::new(deserializer.names.clone());
+    for e in &events {
+        ctrl.handle_event(e);
+        serializer.push_event(ctrl.get_stage(), e);
+    }
+    assert_eq!(serializer.serialize(), log);
+}
+
+#[test]
+fn test_mjai_invalid_log() {
+    let header = r#"{"type":"start_game","id":0,"names":["a","b","c","d"],"kyoku_first":4,"aka_flag":true}
+{"type":"start_kyoku","bakaze":"E","dora_marker":"N","kyoku":1,"honba":0,"kyotaku":0,"oya":0,"tehais":[["1m","2m","3m","4m","5m","6m","7m","8m","9m","1p","2p","3p","4p"],["?","?","?","?","?","?","?","?","?","?","?","?","?"],["?","?","?","?","?","?","?","?","?","?","?","?","?"],["?","?","?","?","?","?","?","?","?","?","?","?","?"]],"scores":[25000,25000,25000,25000]}
+"#;
+    let deserialize =
+        |lines: &str| MjaiDeserializer::new().deserialize(&format!("{}{}", header, lines));
+    let tsumo = r#"{"type":"tsumo","actor":0,"pai":"5p"}
+"#;
+    assert!(deserialize(tsumo).is_ok());
+
+    // 存在しない座席のツモ
+    let res = deserialize(r#"{"type":"tsumo","actor":4,"pai":"5p"}"#);
+    assert_eq!(res.err(), Some("invalid actor: 4".to_string()));
+
+    // 不明な牌
+    for pai in ["5x", "0m", "6mr", "5", "EE"] {
+        let res = deserialize(&format!(r#"{{"type":"tsumo","actor":0,"pai":"{}"}}"#, pai));
+        assert_eq!(res.err(), Some(format!("invalid tile: {}", pai)));
+    }
+    let chi = r#"{"type":"chi","actor":1,"target":0,"pai":"4p","consumed":["5p","x"]}"#;
+    let res = deserialize(&format!("{}{}", tsumo, chi));
+    assert_eq!(res.err(), Some("invalid tile: x".to_string()));
+
+    // 配牌が4人分ない
+    let start = r#"{"type":"start_kyoku","bakaze":"E","dora_marker":"N","kyoku":1,"honba":0,"kyotaku":0,"oya":0,"tehais":[[],[],[]],"scores":[25000,25000,25000,25000]}"#;
+    assert!(MjaiDeserializer::new().deserialize(start).is_err());
+}
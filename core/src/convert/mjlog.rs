@@ -0,0 +1,686 @@
+use crate::model::*;
+use crate::util::common::calc_ranks;
+
+// [MjlogDeserializer]
+// 天鳳のmjlog(XML)形式の牌譜をEventのリストに変換する
+// 牌は0~135の番号で表される (番号/4が牌の種類, 赤ありの場合は16, 52, 88が赤5)
+// gzip圧縮されたmjlogファイルは事前に展開しておく必要がある
+#[derive(Debug)]
+pub struct MjlogDeserializer {
+    pub names: [String; SEAT], // プレイヤー名 (UNタグ)
+    mode: usize,
+    rule: Rule,
+    n_seat: usize,
+    events: Vec<Event>,
+    init: Option<XmlTag>,               // 親の配牌14枚目のツモまで局開始を保留
+    hands: [Vec<usize>; SEAT],          // 各プレイヤーの手牌 (牌番号)
+    last_drawns: [Option<usize>; SEAT], // ツモ切り判定用
+    riichis: [bool; SEAT],              // リーチ宣言 (step=1) 後の打牌待ち
+    last_tile: Option<usize>,           // ロンの和了牌
+    kan: Option<MeldType>,              // 直前の槓 (槓ドラの順序調整用)
+    kan_doras: Vec<Tile>,               // 明槓,加槓の打牌後に更新する槓ドラ
+    end: Option<Event>,                 // owari属性から作成した終局イベント
+}
+
+impl Default for MjlogDeserializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MjlogDeserializer {
+    pub fn new() -> Self {
+        Self {
+            names: Default::default(),
+            mode: 1,
+            rule: Rule::default(),
+            n_seat: SEAT,
+            events: vec![],
+            init: None,
+            hands: [vec![], vec![], vec![], vec![]],
+            last_drawns: [None; SEAT],
+            riichis: [false; SEAT],
+            last_tile: None,
+            kan: None,
+            kan_doras: vec![],
+            end: None,
+        }
+    }
+
+    // 牌譜全体を変換 (Begin, 各局のNew~Win/Draw, End)
+    pub fn deserialize(&mut self, xml: &str) -> Result<Vec<Event>, String> {
+        *self = Self::new();
+        self.events.push(Event::begin());
+        for tag in parse_xml(xml)? {
+            self.do_tag(&tag)
+                .map_err(|e| format!("{} (<{}>)", e, tag.name))?;
+        }
+        let end = self.end.take();
+        self.events
+            .push(end.unwrap_or(Event::end([0; SEAT], [0; SEAT], [0; SEAT])));
+        Ok(std::mem::take(&mut self.events))
+    }
+
+    fn do_tag(&mut self, tag: &XmlTag) -> Result<(), String> {
+        let name = tag.name.as_str();
+        match name {
+            "GO" => {
+                // 0x02: 赤なし, 0x04: 喰いタンなし, 0x08: 東南戦, 0x10: 三麻
+                let tp = tag.get_int("type")?;
+                self.mode = if tp & 0x08 != 0 { 2 } else { 1 };
+                if tp & 0x10 != 0 {
+                    self.mode += 10;
+                    self.n_seat = 3;
+                }
+                if tp & 0x02 != 0 {
+                    self.rule.red5 = [0; 3];
+                }
+                self.rule.kuitan = tp & 0x04 == 0;
+            }
+            "UN" => {
+                for s in 0..SEAT {
+                    if let Some(n) = tag.get(&format!("n{}", s)) {
+                        self.names[s] = decode_percent(n);
+                    }
+                }
+            }
+            "INIT" => {
+                self.hands = [vec![], vec![], vec![], vec![]];
+                self.last_drawns = [None; SEAT];
+                self.riichis = [false; SEAT];
+                self.last_tile = None;
+                self.kan = None;
+                self.kan_doras = vec![];
+                for s in 0..SEAT {
+                    self.hands[s] = tag.get_ints(&format!("hai{}", s))?;
+                }
+                if self.hands[3].is_empty() && self.mode < 10 {
+                    self.mode += 10; // GOタグがない場合
+                    self.n_seat = 3;
+                }
+                self.init = Some(tag.clone());
+            }
+            "N" => self.do_meld(tag)?,
+            "REACH" => {
+                if tag.get_int("step")? == 1 {
+                    self.riichis[tag.get_seat("who")?] = true;
+                }
+            }
+            "DORA" => {
+                let t = self.tile(tag.get_int("hai")?);
+                match self.kan {
+                    Some(MeldType::Minkan) | Some(MeldType::Kakan) => self.kan_doras.push(t),
+                    Some(MeldType::Ankan) if matches!(self.events.last(), Some(Event::Deal(_))) => {
+                        // 暗槓の槓ドラは嶺上牌のツモ前
+                        let i = self.events.len() - 1;
+                        self.events.insert(i, Event::dora(t));
+                    }
+                    _ => self.events.push(Event::dora(t)),
+                }
+            }
+            "AGARI" => {
+                self.do_agari(tag)?;
+                self.do_owari(tag)?;
+            }
+            "RYUUKYOKU" => {
+                self.do_ryuukyoku(tag)?;
+                self.do_owari(tag)?;
+            }
+            _ => {
+                // ツモ: T, U, V, W + 牌番号, 打牌: D, E, F, G + 牌番号
+                let mut cs = name.chars();
+                let c = cs.next().unwrap_or(' ');
+                if let Ok(id) = cs.as_str().parse::<usize>() {
+                    if let Some(s) = "TUVW".find(c) {
+                        self.do_deal(s, id)?;
+                    } else if let Some(s) = "DEFG".find(c) {
+                        self.do_discard(s, id)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn tile(&self, id: usize) -> Tile {
+        tile_from_mjlog(id, self.rule.red5 != [0; 3])
+    }
+
+    fn tiles(&self, ids: &[usize]) -> Vec<Tile> {
+        ids.iter().map(|&id| self.tile(id)).collect()
+    }
+
+    fn remove_from_hand(&mut self, seat: Seat, id: usize) -> Result<(), String> {
+        let h = &mut self.hands[seat];
+        let i = h
+            .iter()
+            .position(|&x| x == id)
+            .ok_or(format!("tile {} not found in hand {}", id, seat))?;
+        h.remove(i);
+        Ok(())
+    }
+
+    fn do_deal(&mut self, seat: Seat, id: usize) -> Result<(), String> {
+        self.hands[seat].push(id);
+        self.last_drawns[seat] = Some(id);
+
+        // 局開始 (親の最初のツモは配牌として扱う)
+        if let Some(init) = self.init.take() {
+            let seed = init.get_ints("seed")?;
+            let ten = init.get_scores("ten")?;
+            let oya = init.get_int("oya")?;
+            if seed.len() < 6 || oya != seat {
+                return Err("invalid INIT".to_string());
+            }
+            let mut scores = [0; SEAT];
+            for (s, &t) in ten.iter().enumerate().take(SEAT) {
+                scores[s] = t * 100;
+            }
+            let mut hands = [vec![], vec![], vec![], vec![]];
+            for (h, ids) in hands.iter_mut().zip(&self.hands).take(self.n_seat) {
+                *h = self.tiles(ids);
+            }
+            self.events.push(Event::new(
+                seed[0] / 4,
+                seed[0] % 4,
+                seed[1],
+                seed[2],
+                vec![self.tile(seed[5])],
+                scores,
+                hands,
+                self.mode,
+                self.rule.clone(),
+            ));
+            return Ok(());
+        }
+
+        self.events.push(Event::deal(seat, self.tile(id)));
+        Ok(())
+    }
+
+    fn do_discard(&mut self, seat: Seat, id: usize) -> Result<(), String> {
+        self.remove_from_hand(seat, id)?;
+        let is_drawn = self.last_drawns[seat] == Some(id);
+        let is_riichi = self.riichis[seat];
+        self.riichis[seat] = false;
+        self.last_drawns[seat] = None;
+        self.last_tile = Some(id);
+        self.events
+            .push(Event::discard(seat, self.tile(id), is_drawn, is_riichi));
+
+        self.kan = None;
+        for t in std::mem::take(&mut self.kan_doras) {
+            self.events.push(Event::dora(t));
+        }
+        Ok(())
+    }
+
+    fn do_meld(&mut self, tag: &XmlTag) -> Result<(), String> {
+        let seat = tag.get_seat("who")?;
+        let m = tag.get_int("m")?;
+        let (meld_type, ids, called) = decode_meld(m);
+        let last_drawn = self.last_drawns[seat].take();
+
+        let consumed: Vec<usize> = match meld_type {
+            None => {
+                // 北抜き
+                let id = ids[0];
+                let is_drawn = last_drawn == Some(id);
+                self.remove_from_hand(seat, id)?;
+                self.last_tile = Some(id);
+                self.events.push(Event::kita(seat, is_drawn));
+                return Ok(());
+            }
+            Some(MeldType::Kakan) => vec![called],
+            Some(MeldType::Ankan) => ids,
+            Some(_) => ids.into_iter().filter(|&id| id != called).collect(),
+        };
+        for &id in &consumed {
+            self.remove_from_hand(seat, id)?;
+        }
+
+        let meld_type = meld_type.unwrap();
+        if meld_type == MeldType::Kakan {
+            self.last_tile = Some(called);
+        }
+        if meld_type == MeldType::Ankan
+            || meld_type == MeldType::Minkan
+            || meld_type == MeldType::Kakan
+        {
+            self.kan = Some(meld_type);
+        }
+        let consumed = self.tiles(&consumed);
+        self.events.push(Event::meld(seat, meld_type, consumed));
+        Ok(())
+    }
+
+    fn do_agari(&mut self, tag: &XmlTag) -> Result<(), String> {
+        let seat = tag.get_seat("who")?;
+        let target = tag.get_seat("fromWho")?;
+        let ba = tag.get_ints("ba")?;
+        let honba = *ba.first().unwrap_or(&0) as Point;
+
+        // 得点変動 (sc: 点数,変動,点数,変動,... 100点単位)
+        let sc = tag.get_scores("sc")?;
+        let mut points = [0; SEAT];
+        for s in 0..std::cmp::min(sc.len() / 2, SEAT) {
+            points[s] = sc[s * 2 + 1] * 100;
+        }
+
+        // 役 (yaku: 役ID,飜数,..., yakuman: 役ID,...)
+        let mut yakus = vec![];
+        let mut fan = 0;
+        let yaku = tag.get_ints("yaku")?;
+        for y in yaku.chunks(2) {
+            if y.len() == 2 && y[1] != 0 {
+                yakus.push((yaku_name_from_mjlog(y[0]).to_string(), y[1]));
+                fan += y[1];
+            }
+        }
+        let yakuman = tag.get_ints("yakuman")?;
+        for &y in &yakuman {
+            yakus.push((yaku_name_from_mjlog(y).to_string(), 13));
+        }
+
+        // 点数 (ten: 符,点数,満貫以上の区分)
+        let ten = tag.get_ints("ten")?;
+        if ten.len() < 3 {
+            return Err("invalid ten".to_string());
+        }
+        let score_title = match ten[2] {
+            0 => "",
+            1 => "満貫",
+            2 => "跳満",
+            3 => "倍満",
+            4 => "三倍満",
+            _ if yakuman.is_empty() => "数え役満",
+            _ => "役満",
+        };
+        let total = ten[1] as Point;
+        let pts = if seat == target {
+            // ツモ和了の支払い (積み棒を除く)
+            let pay = |s: Seat| -points[s] - honba * 100;
+            let dealer = match &self
+                .events
+                .iter()
+                .rev()
+                .find(|e| matches!(e, Event::New(_)))
+            {
+                Some(Event::New(e)) => e.kyoku,
+                _ => return Err("kyoku not started".to_string()),
+            };
+            let non_dealer = (0..self.n_seat)
+                .filter(|&s| s != seat && s != dealer)
+                .map(pay)
+                .max()
+                .unwrap_or(0);
+            if seat == dealer {
+                (total, non_dealer, 0)
+            } else {
+                (total, non_dealer, pay(dealer))
+            }
+        } else {
+            (total, 0, 0)
+        };
+
+        let ctx = WinContext {
+            hand: self.tiles(&tag.get_ints("hai")?),
+            yakus,
+            fu: ten[0],
+            fan,
+            yakuman_times: yakuman.len(),
+            score_title: score_title.to_string(),
+            points: pts,
+        };
+
+        // ダブロンの場合は2人目以降の和了を同じイベントに追加
+        if let Some(Event::Win(e)) = self.events.last_mut() {
+            e.contexts.push((seat, points, ctx));
+            return Ok(());
+        }
+        let ura_doras = self.tiles(&tag.get_ints("doraHaiUra")?);
+        self.events
+            .push(Event::win(ura_doras, vec![(seat, points, ctx)]));
+        Ok(())
+    }
+
+    // 最終局のAGARI,RYUUKYOKUタグのowari属性 (最終得点,最終ポイント,... 得点は100点単位)
+    fn do_owari(&mut self, tag: &XmlTag) -> Result<(), String> {
+        let owari: Vec<f64> = tag.get_list("owari")?;
+        if owari.is_empty() {
+            return Ok(());
+        }
+
+        let sc = tag.get_scores("sc")?;
+        let mut scores = [0; SEAT];
+        let mut points = [0; SEAT];
+        let mut final_points = [0; SEAT];
+        for s in 0..std::cmp::min(owari.len() / 2, SEAT) {
+            scores[s] = owari[s * 2] as Score * 100;
+            final_points[s] = (owari[s * 2 + 1] * 1000.0).round() as Point;
+            if s * 2 + 1 < sc.len() {
+                // 局終了時の得点との差 (供託の精算)
+                points[s] = scores[s] - (sc[s * 2] + sc[s * 2 + 1]) * 100;
+            }
+        }
+        let ranks = calc_ranks(&scores, self.n_seat);
+        self.end = Some(Event::end(points, ranks, final_points));
+        Ok(())
+    }
+
+    fn do_ryuukyoku(&mut self, tag: &XmlTag) -> Result<(), String> {
+        let type_ = match tag.get("type") {
+            None => DrawType::Kouhaiheikyoku,
+            Some("nm") => DrawType::Nagashimangan,
+            Some("yao9") => DrawType::Kyushukyuhai,
+            Some("reach4") => DrawType::Suuchariichi,
+            Some("ron3") => DrawType::Sanchaho,
+            Some("kan4") => DrawType::Suukansanra,
+            Some("kaze4") => DrawType::Suufuurenda,
+            Some(_) => DrawType::Unknown,
+        };
+
+        let sc = tag.get_scores("sc")?;
+        let mut points = [0; SEAT];
+        for s in 0..std::cmp::min(sc.len() / 2, SEAT) {
+            points[s] = sc[s * 2 + 1] * 100;
+        }
+
+        // 公開された手牌 (hai0~hai3) 九種九牌以外は聴牌者の手牌
+        let mut hands = [vec![], vec![], vec![], vec![]];
+        let mut tenpais = [false; SEAT];
+        for s in 0..SEAT {
+            if tag.get(&format!("hai{}", s)).is_some() {
+                hands[s] = self.tiles(&tag.get_ints(&format!("hai{}", s))?);
+                tenpais[s] = type_ != DrawType::Kyushukyuhai;
+            }
+        }
+
+        self.events.push(Event::draw(type_, hands, tenpais, points));
+        Ok(())
+    }
+}
+
+// 鳴きの情報(m)を(種類, 構成する牌番号, 鳴いた牌(加槓の場合は加えた牌)の番号)に変換
+// 北抜きの種類はNone
+fn decode_meld(m: usize) -> (Option<MeldType>, Vec<usize>, usize) {
+    if m & 0x4 != 0 {
+        // チー
+        let t = (m & 0xFC00) >> 10;
+        let r = t % 3;
+        let t = t / 3;
+        let base = (t / 7 * 9 + t % 7) * 4;
+        let ids = vec![
+            base + ((m >> 3) & 3),
+            base + 4 + ((m >> 5) & 3),
+            base + 8 + ((m >> 7) & 3),
+        ];
+        let called = ids[r];
+        (Some(MeldType::Chi), ids, called)
+    } else if m & 0x18 != 0 {
+        // ポン, 加槓
+        let t4 = (m >> 5) & 3;
+        let t = (m & 0xFE00) >> 9;
+        let r = t % 3;
+        let base = t / 3 * 4;
+        let ids: Vec<usize> = (0..4).filter(|&i| i != t4).map(|i| base + i).collect();
+        if m & 0x8 != 0 {
+            let called = ids[r];
+            (Some(MeldType::Pon), ids, called)
+        } else {
+            (Some(MeldType::Kakan), ids, base + t4)
+        }
+    } else if m & 0x20 != 0 {
+        // 北抜き
+        let id = (m & 0xFF00) >> 8;
+        (None, vec![id], id)
+    } else {
+        // 暗槓, 大明槓
+        let called = (m & 0xFF00) >> 8;
+        let base = called / 4 * 4;
+        let ids = (base..base + 4).collect();
+        if m & 3 == 0 {
+            (Some(MeldType::Ankan), ids, called)
+        } else {
+            (Some(MeldType::Minkan), ids, called)
+        }
+    }
+}
+
+fn tile_from_mjlog(id: usize, red5: bool) -> Tile {
+    let k = id / 4;
+    let t = Tile(k / 9, k % 9 + 1);
+    if red5 && t.0 != TZ && t.1 == 5 && id.is_multiple_of(4) {
+        Tile(t.0, 0)
+    } else {
+        t
+    }
+}
+
+// 天鳳の役ID
+fn yaku_name_from_mjlog(id: usize) -> &'static str {
+    const NAMES: [&str; 55] = [
+        "門前清自摸和",
+        "立直",
+        "一発",
+        "槍槓",
+        "嶺上開花",
+        "海底摸月",
+        "河底撈魚",
+        "平和",
+        "断幺九",
+        "一盃口",
+        "自風 東",
+        "自風 南",
+        "自風 西",
+        "自風 北",
+        "場風 東",
+        "場風 南",
+        "場風 西",
+        "場風 北",
+        "役牌 白",
+        "役牌 發",
+        "役牌 中",
+        "両立直",
+        "七対子",
+        "混全帯幺九",
+        "一気通貫",
+        "三色同順",
+        "三色同刻",
+        "三槓子",
+        "対々和",
+        "三暗刻",
+        "小三元",
+        "混老頭",
+        "二盃口",
+        "純全帯幺九",
+        "混一色",
+        "清一色",
+        "人和",
+        "天和",
+        "地和",
+        "大三元",
+        "四暗刻",
+        "四暗刻単騎",
+        "字一色",
+        "緑一色",
+        "清老頭",
+        "九蓮宝燈",
+        "純正九蓮宝燈",
+        "国士無双",
+        "国士無双１３面",
+        "大四喜",
+        "小四喜",
+        "四槓子",
+        "ドラ",
+        "裏ドラ",
+        "赤ドラ",
+    ];
+    NAMES.get(id).unwrap_or(&"不明")
+}
+
+// [XML]
+// mjlogの読み込みに必要な最低限のXMLパーサ (タグ名と属性のみ)
+#[derive(Debug, Clone)]
+struct XmlTag {
+    name: String,
+    attrs: Vec<(String, String)>,
+}
+
+impl XmlTag {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn get_int(&self, key: &str) -> Result<usize, String> {
+        let v = self
+            .get(key)
+            .ok_or(format!("attribute {} not found", key))?;
+        v.parse().map_err(|_| format!("invalid {}: {}", key, v))
+    }
+
+    // 座席 (who, fromWho)
+    fn get_seat(&self, key: &str) -> Result<Seat, String> {
+        let s = self.get_int(key)?;
+        if s >= SEAT {
+            return Err(format!("invalid {}: {}", key, s));
+        }
+        Ok(s)
+    }
+
+    // カンマ区切りの数値のリスト (属性がない場合は空)
+    fn get_ints(&self, key: &str) -> Result<Vec<usize>, String> {
+        self.get_list(key)
+    }
+
+    // 点数のリスト (負の値を含む)
+    fn get_scores(&self, key: &str) -> Result<Vec<Score>, String> {
+        self.get_list(key)
+    }
+
+    fn get_list<T: std::str::FromStr>(&self, key: &str) -> Result<Vec<T>, String> {
+        match self.get(key) {
+            None | Some("") => Ok(vec![]),
+            Some(v) => v
+                .split(',')
+                .map(|x| x.parse().map_err(|_| format!("invalid {}: {}", key, v)))
+                .collect(),
+        }
+    }
+}
+
+fn parse_xml(xml: &str) -> Result<Vec<XmlTag>, String> {
+    let mut tags = vec![];
+    let mut rest = xml;
+    while let Some(i) = rest.find('<') {
+        let j = rest[i..].find('>').ok_or("unclosed tag")? + i;
+        let body = rest[i + 1..j].trim_end_matches('/').trim();
+        rest = &rest[j + 1..];
+        if body.starts_with('/') || body.starts_with('?') || body.starts_with('!') {
+            continue; // 終了タグ, XML宣言, コメント
+        }
+
+        let (name, mut attrs_str) = match body.find(char::is_whitespace) {
+            Some(k) => (&body[..k], body[k..].trim_start()),
+            None => (body, ""),
+        };
+        let mut attrs = vec![];
+        while let Some(k) = attrs_str.find("=\"") {
+            let key = attrs_str[..k].trim().to_string();
+            let v = &attrs_str[k + 2..];
+            let e = v.find('"').ok_or(format!("invalid attribute: {}", body))?;
+            attrs.push((key, v[..e].to_string()));
+            attrs_str = v[e + 1..].trim_start();
+        }
+        tags.push(XmlTag {
+            name: name.to_string(),
+            attrs,
+        });
+    }
+    Ok(tags)
+}
+
+// UNタグのプレイヤー名 (%XXでエンコードされたUTF-8)
+fn decode_percent(s: &str) -> String {
+    let bs = s.as_bytes();
+    let mut res = vec![];
+    let mut i = 0;
+    while i < bs.len() {
+        if bs[i] == b'%' && i + 2 < bs.len() {
+            let hex = std::str::from_utf8(&bs[i + 1..i + 3]).unwrap_or("");
+            if let Ok(b) = u8::from_str_radix(hex, 16) {
+                res.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        res.push(bs[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&res).to_string()
+}
+
+#[test]
+fn test_mjlog_deserialize() {
+    use crate::actor::create_actor;
+    use crate::controller::StageController;
+
+    // 4人東南 (チー, ポン, 加槓, 暗槓, リーチ, ロン)
+    let xml = r#"<mjloggm ver="2.3"><GO type="9" lobby="0"/><UN n0="%E3%81%82" n1="b" n2="c" n3="d"/><TAIKYOKU oya="0"/><INIT seed="5,0,0,0,0,120" ten="262,182,254,302" oya="1" hai0="0,8,44,53,56,57,58,68,92,96,112,124,128" hai1="36,40,48,52,54,60,80,89,100,108,109,113,116" hai2="1,4,20,28,32,45,61,64,72,73,81,90,104" hai3="2,5,12,24,25,26,33,76,84,88,110,129,132"/><U117/><E113/><V125/><F125/><W114/><G114/><T17/><D112/><U93/><E36/><V3/><F32/><W82/><G110/><N who="1" m="42602"/><E40/><V85/><F104/><W29/><G129/><T126/><D128/><U86/><E60/><V97/><F45/><W121/><G121/><T98/><D68/><U30/><E30/><V118/><F118/><W49/><G132/><T77/><D77/><U133/><E133/><V94/><F1/><W95/><G2/><T46/><D0/><U34/><E34/><V9/><F20/><W122/><G122/><T101/><D96/><U111/><N who="1" m="42610"/><U10/><E10/><DORA hai="50"/><V18/><F18/><W27/><N who="3" m="6144"/><DORA hai="62"/><W105/><G105/><T21/><D8/><U65/><E65/><V16/><F16/><W41/><G33/><T63/><D44/><N who="1" m="27911"/><E52/><V19/><F19/><W134/><G134/><T35/><D35/><U127/><E127/><N who="0" m="48681"/><D46/><U119/><E100/><V102/><REACH who="2" step="1"/><F28/><W59/><G29/><T37/><D37/><U135/><E135/><V106/><F106/><W42/><G5/><T130/><D130/><U43/><E43/><V13/><F13/><AGARI ba="0,0" hai="12,17,20,53,56,56,56,60,92,96,100" ten="30,2000,0" yaku="18,1,52,1" yakuman="" doraHaiUra="112,36,36" who="0" fromWho="2" sc="262,30,182,0,254,-20,302,0" owari="292,9.2,182,-31.8,234,-16.6,302,40.2"/></mjloggm>"#;
+
+    let mut deserializer = MjlogDeserializer::new();
+    let events = deserializer.deserialize(xml).unwrap();
+    assert_eq!(deserializer.names[0], "あ");
+
+    let nop = create_actor("Nop");
+    let actors = [
+        nop.clone_box(),
+        nop.clone_box(),
+        nop.clone_box(),
+        nop.clone_box(),
+    ];
+    let mut ctrl = StageController::new(actors, vec![]);
+    for e in &events {
+        ctrl.handle_event(e);
+    }
+
+    let stg = ctrl.get_stage();
+    let scores: Vec<Score> = stg.players.iter().map(|p| p.score).collect();
+    assert_eq!(scores, vec![29200, 18200, 22400, 30200]);
+    assert_eq!(stg.doras.len(), 3);
+    assert_eq!(stg.players[1].melds.len(), 2);
+    assert_eq!(stg.players[3].melds.len(), 1);
+
+    // 終局 (owari)
+    if let Some(Event::End(e)) = events.last() {
+        assert_eq!(e.points, [0; SEAT]);
+        assert_eq!(e.ranks, [1, 3, 2, 0]);
+        assert_eq!(e.final_points, [9200, -31800, -16600, 40200]);
+    } else {
+        panic!("end event not found");
+    }
+}
+
+#[test]
+fn test_mjlog_invalid_seat() {
+    // 座席番号が範囲外の場合はパニックせずにエラー
+    let init = r#"<INIT seed="0,0,0,0,0,120" ten="250,250,250,250" oya="0" hai0="0,1,2,3,4,5,6,7,8,9,10,11,12" hai1="13,14,15,16,17,18,19,20,21,22,23,24,25" hai2="26,27,28,29,30,31,32,33,34,35,36,37,38" hai3="39,40,41,42,43,44,45,46,47,48,49,50,51"/><T52/>"#;
+    for (key, tag) in [
+        ("who", r#"<REACH who="4" step="1"/>"#),
+        ("who", r#"<N who="7" m="42602"/>"#),
+        (
+            "fromWho",
+            r#"<AGARI ba="0,0" hai="0" ten="30,1000,0" yaku="1,1" doraHai="120" who="0" fromWho="9" sc="250,0,250,0,250,0,250,0"/>"#,
+        ),
+    ] {
+        let xml = format!(
+            r#"<mjloggm ver="2.3"><GO type="1"/>{}{}</mjloggm>"#,
+            init, tag
+        );
+        let err = MjlogDeserializer::new().deserialize(&xml).unwrap_err();
+        assert!(err.starts_with(&format!("invalid {}", key)), "{}", err);
+    }
+}
@@ -1,2 +1,4 @@
+pub mod feature;
 pub mod mjai;
+pub mod mjlog;
 pub mod tenhou;
@@ -1,7 +1,10 @@
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
+use serde_json::{json, Number, Value};
+use std::str::FromStr;
 
+use crate::hand::{calc_shanten, get_pao};
 use crate::model::*;
+use crate::util::common::calc_ranks;
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct TenhouLog {
@@ -28,9 +31,43 @@ impl TenhouLog {
         log.rule.aka = 1;
         log
     }
+
+    // 1: 4人東, 2: 4人南, 11: 3人東, 12: 3人南
+    fn get_mode(&self) -> usize {
+        let d = &self.rule.disp;
+        let m = if d.contains('南') { 2 } else { 1 };
+        if d.contains('三') {
+            m + 10
+        } else {
+            m
+        }
+    }
+
+    fn get_rule(&self) -> Rule {
+        let r = &self.rule;
+        let red5 = if r.aka51 + r.aka52 + r.aka53 != 0 {
+            [r.aka51, r.aka52, r.aka53]
+        } else {
+            [r.aka; 3]
+        };
+        Rule {
+            red5,
+            kuitan: r.disp.is_empty() || r.disp.contains('喰'),
+            ..Rule::default()
+        }
+    }
+}
+
+impl FromStr for TenhouLog {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        serde_json::from_str(s).map_err(|e| e.to_string())
+    }
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TenhouRule {
     pub disp: String,
     pub aka: usize,
@@ -110,6 +147,9 @@ impl TenhouSerializer {
                 k.scores = e.scores;
                 for s in 0..SEAT {
                     let h = &e.hands[s];
+                    if h.is_empty() {
+                        continue; // 三麻の座席3
+                    }
                     k.players[s].hand = tiles_to_tenhou(&h[..13]);
                     if h.len() == 14 {
                         k.players[s].drawns.push(json!(tile_to_tenhou(h[13])));
@@ -135,7 +175,14 @@ impl TenhouSerializer {
             Event::Meld(e) => match e.meld_type {
                 MeldType::Chi | MeldType::Pon | MeldType::Minkan => {
                     let (seat, _, d) = stg.last_tile.unwrap();
-                    let pos = 3 - (seat + SEAT - e.seat) % SEAT;
+                    // 鳴いた牌の位置 (上家: 0, 対面: 1, 下家: 2 (大明槓は3))
+                    let n = stg.get_seat_count();
+                    let pos = match (seat + n - e.seat) % n {
+                        1 if e.meld_type == MeldType::Minkan => 3,
+                        1 => 2,
+                        2 if n == SEAT => 1,
+                        _ => 0,
+                    };
                     let marker = match e.meld_type {
                         MeldType::Chi => "c",
                         MeldType::Pon => "p",
@@ -177,7 +224,9 @@ impl TenhouSerializer {
                     k.players[e.seat].discards.push(json!(meld.concat()));
                 }
             },
-            Event::Kita(_) => panic!(),
+            Event::Kita(e) => {
+                k.players[e.seat].discards.push(json!("f44"));
+            }
             Event::Dora(e) => {
                 k.doras.push(tile_to_tenhou(e.tile));
             }
@@ -188,8 +237,13 @@ impl TenhouSerializer {
                 for (seat, points, ctx) in &e.contexts {
                     k.result_detail
                         .push(points.iter().map(|&p| json!(p)).collect());
-                    let mut detail = vec![json!(seat), json!(target_seat), json!(seat)];
-                    let score_title = if ctx.score_title == "" {
+                    // 3番目の要素は責任払い(包)の対象者 (責任払いがない場合は和了者)
+                    let pao_seat = match get_pao(&stg.players[*seat], ctx) {
+                        Some((s, _)) => s,
+                        None => *seat,
+                    };
+                    let mut detail = vec![json!(seat), json!(target_seat), json!(pao_seat)];
+                    let score_title = if ctx.score_title.is_empty() {
                         format!("{}符{}飜", ctx.fu, ctx.fan)
                     } else {
                         match ctx.score_title.as_str() {
@@ -216,10 +270,11 @@ impl TenhouSerializer {
                 }
             }
             Event::Draw(e) => {
-                // TODO
-                k.result = "流局".to_string();
-                k.result_detail
-                    .push(e.points.iter().map(|&p| json!(p)).collect());
+                k.result = draw_type_to_tenhou(e.type_).to_string();
+                if e.type_ == DrawType::Kouhaiheikyoku || e.type_ == DrawType::Nagashimangan {
+                    k.result_detail
+                        .push(e.points.iter().map(|&p| json!(p)).collect());
+                }
             }
             Event::End(_) => {}
         }
@@ -232,7 +287,425 @@ impl TenhouSerializer {
 }
 
 // [TenhouDeserializer]
-// struct TenhouDeserializer {}
+// 天鳳の牌譜(json)をEventのリストに変換する
+// 天鳳の牌譜に含まれない情報(ツモ切りの北抜き, 流局時の聴牌など)は牌譜から推定する
+#[derive(Debug)]
+pub struct TenhouDeserializer {
+    n_seat: usize,
+    events: Vec<Event>,
+    hands: [TileTable; SEAT],          // 各プレイヤーの手牌
+    drawns: [Vec<Value>; SEAT],        // 牌譜のツモ(鳴きを含む)
+    discards: [Vec<Value>; SEAT],      // 牌譜の打牌(暗槓,加槓,北抜きを含む)
+    drawn_cursors: [usize; SEAT],      // drawnsの読み込み位置
+    discard_cursors: [usize; SEAT],    // discardsの読み込み位置
+    last_drawns: [Option<Tile>; SEAT], // ツモ切り判定用
+    last_tile: Option<Tile>,           // ロンの和了牌 (捨て牌,加槓した牌,抜き北)
+    doras: Vec<Tile>,
+    dora_cursor: usize,
+    kan_dora: bool, // 加槓・明槓の打牌後の槓ドラ更新用
+    turn: Seat,
+}
+
+impl Default for TenhouDeserializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TenhouDeserializer {
+    pub fn new() -> Self {
+        Self {
+            n_seat: SEAT,
+            events: vec![],
+            hands: [TileTable::default(); SEAT],
+            drawns: [vec![], vec![], vec![], vec![]],
+            discards: [vec![], vec![], vec![], vec![]],
+            drawn_cursors: [0; SEAT],
+            discard_cursors: [0; SEAT],
+            last_drawns: [None; SEAT],
+            last_tile: None,
+            doras: vec![],
+            dora_cursor: 0,
+            kan_dora: false,
+            turn: 0,
+        }
+    }
+
+    // 牌譜全体を変換 (Begin, 各局のNew~Win/Draw, End)
+    pub fn deserialize(&mut self, log: &TenhouLog) -> Result<Vec<Event>, String> {
+        let mode = log.get_mode();
+        let rule = log.get_rule();
+        let mut events = vec![Event::begin()];
+        for k in &log.log {
+            events.append(&mut self.deserialize_kyoku(k, mode, &rule)?);
+        }
+        // 最終順位は最終局の得点から計算 (供託の精算,最終ポイントは牌譜に含まれないため0)
+        let ranks = calc_ranks(&get_last_scores(&events), self.n_seat);
+        events.push(Event::end([0; SEAT], ranks, [0; SEAT]));
+        Ok(events)
+    }
+
+    // 1局分の牌譜を変換 (New~Win/Draw)
+    pub fn deserialize_kyoku(
+        &mut self,
+        kyoku: &Value,
+        mode: usize,
+        rule: &Rule,
+    ) -> Result<Vec<Event>, String> {
+        let v = kyoku.as_array().ok_or("invalid kyoku")?;
+        if v.len() < 5 {
+            return Err("invalid kyoku".to_string());
+        }
+
+        // 座席3の配牌が空の場合は三麻として扱う (ルール表記に三麻の記載がない牌譜用)
+        let is_3p = match v.get(4 + 3 * 3).and_then(|h| h.as_array()) {
+            Some(h) => h.is_empty(),
+            None => true,
+        };
+        let mode = if is_3p && mode < 10 { mode + 10 } else { mode };
+
+        *self = Self::new();
+        self.n_seat = if mode > 10 { 3 } else { SEAT };
+
+        // [局, 本場, 供託], 点数, ドラ表示牌, 裏ドラ表示牌
+        let info = as_i64_vec(&v[0])?;
+        if info.len() != 3 {
+            return Err("invalid kyoku info".to_string());
+        }
+        let (bakaze, kyoku, honba, kyoutaku) = (
+            info[0] as usize / 4,
+            info[0] as usize % 4,
+            info[1] as usize,
+            info[2] as usize,
+        );
+        if kyoku >= self.n_seat {
+            return Err(format!("invalid kyoku info: {:?}", info));
+        }
+        let mut scores = [0; SEAT];
+        for (s, &sc) in as_i64_vec(&v[1])?.iter().enumerate().take(SEAT) {
+            scores[s] = sc as Score;
+        }
+        self.doras = tiles_from_tenhou(&as_i64_vec(&v[2])?)?;
+        let ura_doras = tiles_from_tenhou(&as_i64_vec(&v[3])?)?;
+        if self.doras.is_empty() {
+            return Err("dora not found".to_string());
+        }
+        self.dora_cursor = 1;
+
+        // 各プレイヤーの配牌, ツモ, 打牌 (三麻の座席3は空)
+        let mut hands = [vec![], vec![], vec![], vec![]];
+        for (s, h) in hands.iter_mut().enumerate().take(self.n_seat) {
+            let i = 4 + s * 3;
+            let empty = json!([]);
+            let hand = tiles_from_tenhou(&as_i64_vec(v.get(i).unwrap_or(&empty))?)?;
+            self.drawns[s] = as_vec(v.get(i + 1).unwrap_or(&empty))?;
+            self.discards[s] = as_vec(v.get(i + 2).unwrap_or(&empty))?;
+            for &t in &hand {
+                tile_table_inc(&mut self.hands[s], t);
+            }
+            *h = hand;
+        }
+
+        // 親の14枚目はツモではなく配牌として扱う
+        let dealer = kyoku;
+        match self.next_drawn(dealer) {
+            Some(Value::Number(n)) => {
+                let t = tile_from_tenhou(as_i64(n)?)?;
+                tile_table_inc(&mut self.hands[dealer], t);
+                self.last_drawns[dealer] = Some(t);
+                self.drawn_cursors[dealer] += 1;
+                hands[dealer].push(t);
+            }
+            _ => return Err("dealer's first tile not found".to_string()),
+        }
+
+        let doras = vec![self.doras[0]];
+        self.events.push(Event::new(
+            bakaze,
+            kyoku,
+            honba,
+            kyoutaku,
+            doras,
+            scores,
+            hands,
+            mode,
+            rule.clone(),
+        ));
+
+        let result = as_vec(v.last().unwrap())?;
+        self.turn = dealer;
+        self.do_turn(&result, &ura_doras)?;
+
+        Ok(std::mem::take(&mut self.events))
+    }
+
+    fn next_drawn(&self, seat: Seat) -> Option<&Value> {
+        self.drawns[seat].get(self.drawn_cursors[seat])
+    }
+
+    fn next_discard(&self, seat: Seat) -> Option<&Value> {
+        self.discards[seat].get(self.discard_cursors[seat])
+    }
+
+    // ツモ番のプレイヤーの操作から局の終了までを変換
+    fn do_turn(&mut self, result: &[Value], ura_doras: &[Tile]) -> Result<(), String> {
+        loop {
+            let s = self.turn;
+            let d = match self.next_discard(s) {
+                Some(d) => d.clone(),
+                None => return self.do_end(result, ura_doras), // ツモ和了, 九種九牌
+            };
+            self.discard_cursors[s] += 1;
+
+            match &d {
+                Value::Number(n) => {
+                    self.do_discard(s, as_i64(n)?, false)?;
+                }
+                Value::String(st) if st.starts_with('r') => {
+                    let n = st[1..]
+                        .parse()
+                        .map_err(|_| format!("invalid discard: {}", st))?;
+                    self.do_discard(s, n, true)?;
+                }
+                Value::String(st) if st == "f44" => {
+                    let t = Tile(TZ, WN);
+                    let is_drawn = self.last_drawns[s] == Some(t);
+                    self.remove_from_hand(s, t)?;
+                    self.last_tile = Some(t);
+                    self.events.push(Event::kita(s, is_drawn));
+                    self.push_kan_dora();
+                    if !self.do_deal(s)? {
+                        return self.do_end(result, ura_doras); // 抜き北のロン
+                    }
+                    continue;
+                }
+                Value::String(st) => {
+                    let (c, pos, tiles) = parse_meld(st)?;
+                    match c {
+                        'a' => {
+                            for &t in &tiles {
+                                self.remove_from_hand(s, t)?;
+                            }
+                            self.events.push(Event::meld(s, MeldType::Ankan, tiles));
+                            self.push_kan_dora();
+                            self.kan_dora = true;
+                            self.push_kan_dora(); // 暗槓の槓ドラはツモ前
+                        }
+                        'k' => {
+                            let t = tiles[pos];
+                            self.remove_from_hand(s, t)?;
+                            self.last_tile = Some(t);
+                            self.events.push(Event::meld(s, MeldType::Kakan, vec![t]));
+                            self.push_kan_dora();
+                            self.kan_dora = true;
+                        }
+                        _ => return Err(format!("invalid discard: {}", st)),
+                    }
+                    if !self.do_deal(s)? {
+                        return self.do_end(result, ura_doras); // 槍槓
+                    }
+                    continue;
+                }
+                _ => return Err(format!("invalid discard: {}", d)),
+            }
+
+            // 鳴き
+            if self.do_call(s)? {
+                continue;
+            }
+
+            // 次のプレイヤーのツモ
+            let s2 = (s + 1) % self.n_seat;
+            if !self.do_deal(s2)? {
+                return self.do_end(result, ura_doras); // ロン, 流局
+            }
+        }
+    }
+
+    // 打牌 (n = 60はツモ切り)
+    fn do_discard(&mut self, seat: Seat, n: i64, is_riichi: bool) -> Result<(), String> {
+        let (t, is_drawn) = if n == 60 {
+            (self.last_drawns[seat].ok_or("drawn tile not found")?, true)
+        } else {
+            (tile_from_tenhou(n)?, false)
+        };
+        self.remove_from_hand(seat, t)?;
+        self.last_tile = Some(t);
+        self.events
+            .push(Event::discard(seat, t, is_drawn, is_riichi));
+        self.push_kan_dora();
+        Ok(())
+    }
+
+    // 打牌に対するチー,ポン,大明槓
+    fn do_call(&mut self, target: Seat) -> Result<bool, String> {
+        let n = self.n_seat;
+        for i in 1..n {
+            let s = (target + i) % n;
+            let st = match self.next_drawn(s) {
+                Some(Value::String(st)) => st.clone(),
+                _ => continue,
+            };
+            let (c, pos, mut tiles) = parse_meld(&st)?;
+
+            // 鳴いた牌の位置から鳴かれたプレイヤーを判定 (上家: 0, 対面: 1, 下家: 2, 3)
+            // 後の打牌に対する鳴きと区別するため, 鳴いた牌が直前の捨て牌と一致することも確認
+            let from = match pos {
+                0 => (s + n - 1) % n,
+                1 => (s + 2) % n,
+                _ => (s + 1) % n,
+            };
+            if from != target || Some(tiles[pos]) != self.last_tile {
+                continue;
+            }
+
+            let meld_type = match c {
+                'c' => MeldType::Chi,
+                'p' => MeldType::Pon,
+                'm' => MeldType::Minkan,
+                _ => return Err(format!("invalid meld: {}", st)),
+            };
+            tiles.remove(pos);
+            for &t in &tiles {
+                self.remove_from_hand(s, t)?;
+            }
+            self.drawn_cursors[s] += 1;
+            self.last_drawns[s] = None;
+            self.events.push(Event::meld(s, meld_type, tiles));
+            self.turn = s;
+
+            if meld_type == MeldType::Minkan {
+                // 大明槓の直後の打牌は0
+                if let Some(Value::Number(n)) = self.next_discard(s) {
+                    if n.as_i64() == Some(0) {
+                        self.discard_cursors[s] += 1;
+                    }
+                }
+                self.kan_dora = true;
+                if !self.do_deal(s)? {
+                    return Err("replacement tile not found".to_string());
+                }
+            }
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    // 手牌から牌を取り除く (手牌にない場合はエラー)
+    // 通常の5は赤5以外の5がある場合のみ取り除ける
+    fn remove_from_hand(&mut self, seat: Seat, t: Tile) -> Result<(), String> {
+        let h = &mut self.hands[seat];
+        let ok = match t.1 {
+            0 => h[t.0][0] > 0 && h[t.0][5] > 0,
+            5 => h[t.0][5] > h[t.0][0],
+            _ => h[t.0][t.1] > 0,
+        };
+        if !ok {
+            return Err(format!("tile {} not found in hand {}", t, seat));
+        }
+        tile_table_dec(h, t);
+        Ok(())
+    }
+
+    // ツモ (ツモがない場合はfalse)
+    fn do_deal(&mut self, seat: Seat) -> Result<bool, String> {
+        let t = match self.next_drawn(seat) {
+            Some(Value::Number(n)) => tile_from_tenhou(as_i64(n)?)?,
+            Some(d) => return Err(format!("unexpected meld: {}", d)),
+            None => return Ok(false),
+        };
+        self.drawn_cursors[seat] += 1;
+        tile_table_inc(&mut self.hands[seat], t);
+        self.last_drawns[seat] = Some(t);
+        self.turn = seat;
+        self.events.push(Event::deal(seat, t));
+        Ok(true)
+    }
+
+    fn push_kan_dora(&mut self) {
+        if !self.kan_dora {
+            return;
+        }
+        self.kan_dora = false;
+        if let Some(&t) = self.doras.get(self.dora_cursor) {
+            self.dora_cursor += 1;
+            self.events.push(Event::dora(t));
+        }
+    }
+
+    // 局の終了 (和了, 流局)
+    fn do_end(&mut self, result: &[Value], ura_doras: &[Tile]) -> Result<(), String> {
+        let title = result
+            .first()
+            .and_then(|v| v.as_str())
+            .ok_or("result not found")?;
+        if title == "和了" {
+            let mut contexts = vec![];
+            for i in (1..result.len()).step_by(2) {
+                let points = as_points(&result[i])?;
+                let detail = as_vec(result.get(i + 1).ok_or("win detail not found")?)?;
+                if detail.len() < 4 {
+                    return Err("invalid win detail".to_string());
+                }
+                let seat = detail[0].as_u64().ok_or("invalid win detail")? as Seat;
+                let target = detail[1].as_u64().ok_or("invalid win detail")? as Seat;
+                if seat >= self.n_seat || target >= self.n_seat {
+                    return Err(format!("invalid seat in win detail: {:?}", detail));
+                }
+                let mut hand = self.hands[seat];
+                if seat != target {
+                    tile_table_inc(&mut hand, self.last_tile.ok_or("winning tile not found")?);
+                }
+                let ctx = win_context_from_tenhou(&detail, &hand, seat == target, self.n_seat)?;
+                contexts.push((seat, points, ctx));
+            }
+            self.events.push(Event::win(ura_doras.to_vec(), contexts));
+        } else {
+            let type_ = draw_type_from_tenhou(title);
+            let points = match result.get(1) {
+                Some(v) => as_points(v)?,
+                None => [0; SEAT],
+            };
+            let mut hands = [vec![], vec![], vec![], vec![]];
+            let mut tenpais = [false; SEAT];
+            if type_ == DrawType::Kouhaiheikyoku || type_ == DrawType::Nagashimangan {
+                for s in 0..self.n_seat {
+                    tenpais[s] = calc_shanten(&self.hands[s]) == 0;
+                    if tenpais[s] {
+                        hands[s] = tiles_from_tile_table(&self.hands[s]);
+                    }
+                }
+            }
+            self.events.push(Event::draw(type_, hands, tenpais, points));
+        }
+        Ok(())
+    }
+}
+
+// 最終局終了時の得点
+fn get_last_scores(events: &[Event]) -> [Score; SEAT] {
+    let mut scores = [0; SEAT];
+    for e in events {
+        let points = match e {
+            Event::New(e) => {
+                scores = e.scores;
+                continue;
+            }
+            Event::Win(e) => e.contexts.iter().fold([0; SEAT], |mut acc, (_, p, _)| {
+                for s in 0..SEAT {
+                    acc[s] += p[s];
+                }
+                acc
+            }),
+            Event::Draw(e) => e.points,
+            _ => continue,
+        };
+        for s in 0..SEAT {
+            scores[s] += points[s];
+        }
+    }
+    scores
+}
 
 fn tile_to_tenhou(t: Tile) -> i64 {
     (match t {
@@ -242,20 +715,287 @@ fn tile_to_tenhou(t: Tile) -> i64 {
     }) as i64
 }
 
-// fn tile_from_tenhou(t: i64) -> Tile {
-//     let t = t as usize;
-//     match t {
-//         0 => Z8,
-//         11..=47 => Tile(t / 10 - 1, t % 10),
-//         51..=53 => Tile(t % 10 - 1, 0),
-//         _ => panic!("invalid tile number: {}", t),
-//     }
-// }
+// 0: 不明, 11-19: 萬子, 21-29: 筒子, 31-39: 索子, 41-47: 字牌, 51-53: 赤5
+fn tile_from_tenhou(t: i64) -> Result<Tile, String> {
+    let (ti, ni) = ((t / 10 - 1) as usize, (t % 10) as usize);
+    match t {
+        0 => Ok(Z8),
+        11..=39 if ni != 0 => Ok(Tile(ti, ni)),
+        41..=47 => Ok(Tile(ti, ni)),
+        51..=53 => Ok(Tile(ni - 1, 0)),
+        _ => Err(format!("invalid tile number: {}", t)),
+    }
+}
 
 fn tiles_to_tenhou(v: &[Tile]) -> Vec<i64> {
     v.iter().map(|&t| tile_to_tenhou(t)).collect()
 }
 
-// fn tiles_from_tenhou(v: &[i64]) -> Vec<Tile> {
-//     v.iter().map(|&t| tile_from_tenhou(t)).collect()
-// }
+fn tiles_from_tenhou(v: &[i64]) -> Result<Vec<Tile>, String> {
+    v.iter().map(|&t| tile_from_tenhou(t)).collect()
+}
+
+fn draw_type_to_tenhou(type_: DrawType) -> &'static str {
+    match type_ {
+        DrawType::Unknown | DrawType::Kouhaiheikyoku => "流局",
+        DrawType::Nagashimangan => "流し満貫",
+        DrawType::Kyushukyuhai => "九種九牌",
+        DrawType::Suufuurenda => "四風連打",
+        DrawType::Suukansanra => "四槓散了",
+        DrawType::Suuchariichi => "四家立直",
+        DrawType::Sanchaho => "三家和了",
+    }
+}
+
+fn draw_type_from_tenhou(s: &str) -> DrawType {
+    match s {
+        "流局" | "全員聴牌" | "全員不聴" => DrawType::Kouhaiheikyoku,
+        "流し満貫" => DrawType::Nagashimangan,
+        "九種九牌" => DrawType::Kyushukyuhai,
+        "四風連打" => DrawType::Suufuurenda,
+        "四槓散了" => DrawType::Suukansanra,
+        "四家立直" => DrawType::Suuchariichi,
+        "三家和了" => DrawType::Sanchaho,
+        _ => DrawType::Unknown,
+    }
+}
+
+// 鳴きの文字列を(種類, 鳴いた牌の位置, 牌のリスト)に分解
+// ex: "c275226" => ('c', 0, [7s, 赤5s, 6s]), "151515a15" => ('a', 3, [5m, 5m, 5m, 5m])
+fn parse_meld(s: &str) -> Result<(char, usize, Vec<Tile>), String> {
+    let err = || format!("invalid meld: {}", s);
+    let cs: Vec<char> = s.chars().collect();
+    let mut c = ' ';
+    let mut pos = 0;
+    let mut tiles = vec![];
+    let mut i = 0;
+    while i < cs.len() {
+        if cs[i].is_ascii_alphabetic() {
+            c = cs[i];
+            pos = tiles.len();
+            i += 1;
+        }
+        let n: String = cs.get(i..i + 2).ok_or_else(err)?.iter().collect();
+        tiles.push(tile_from_tenhou(n.parse().map_err(|_| err())?)?);
+        i += 2;
+    }
+    if c == ' ' || pos >= tiles.len() {
+        return Err(err());
+    }
+    Ok((c, pos, tiles))
+}
+
+// 和了情報 [和了者, 放銃者, 責任払い, 点数, 役...] をWinContextに変換
+// 符が記載されていない満貫以上の和了の符は0とする
+fn win_context_from_tenhou(
+    detail: &Vec<Value>,
+    hand: &TileTable,
+    is_tsumo: bool,
+    n_seat: usize,
+) -> Result<WinContext, String> {
+    let err = || format!("invalid win detail: {:?}", detail);
+
+    let mut yakus = vec![];
+    let mut fan = 0;
+    let mut yakuman_times = 0;
+    for y in &detail[4..] {
+        let y = y.as_str().ok_or_else(err)?;
+        let i = y.find('(').ok_or_else(err)?;
+        let name = y[..i].to_string();
+        let f = if y.ends_with("(役満)") {
+            13
+        } else {
+            y[i + 1..]
+                .trim_end_matches("飜)")
+                .parse()
+                .map_err(|_| err())?
+        };
+        if f >= 13 {
+            yakuman_times += f - 12;
+        } else {
+            fan += f;
+        }
+        yakus.push((name, f));
+    }
+
+    // 点数の文字列 ex: "30符1飜1000点", "満貫2000-4000点", "40符3飜2600点∀"
+    let s = detail[3].as_str().ok_or_else(err)?;
+    let s = s.trim_end_matches('∀').trim_end_matches('点');
+    let i = s.find(|c: char| c.is_ascii_digit()).ok_or_else(err)?;
+    let (mut title, pts) = s.split_at(i);
+    let mut fu = 0;
+    let mut pts = pts;
+    if let Some(j) = pts.find('符') {
+        fu = pts[..j].parse().map_err(|_| err())?;
+        let k = pts.find('飜').ok_or_else(err)?;
+        fan = pts[j + '符'.len_utf8()..k].parse().map_err(|_| err())?;
+        pts = &pts[k + '飜'.len_utf8()..];
+        title = "";
+    }
+    let pts: Vec<Point> = pts
+        .split('-')
+        .map(|p| p.parse().map_err(|_| err()))
+        .collect::<Result<_, _>>()?;
+    let points = match (is_tsumo, pts.as_slice()) {
+        (false, &[p]) => (p, 0, 0),
+        (true, &[p]) => (p * (n_seat as Point - 1), p, 0), // 親のツモ和了
+        (true, &[p1, p2]) => (p1 * (n_seat as Point - 2) + p2, p1, p2),
+        _ => return Err(err()),
+    };
+
+    Ok(WinContext {
+        hand: tiles_from_tile_table(hand),
+        yakus,
+        fu,
+        fan,
+        yakuman_times,
+        score_title: title.to_string(),
+        points,
+    })
+}
+
+fn as_vec(v: &Value) -> Result<Vec<Value>, String> {
+    v.as_array()
+        .cloned()
+        .ok_or_else(|| format!("array expected: {}", v))
+}
+
+fn as_i64(n: &Number) -> Result<i64, String> {
+    n.as_i64().ok_or_else(|| format!("integer expected: {}", n))
+}
+
+fn as_i64_vec(v: &Value) -> Result<Vec<i64>, String> {
+    as_vec(v)?
+        .iter()
+        .map(|x| x.as_i64().ok_or_else(|| format!("number expected: {}", x)))
+        .collect()
+}
+
+fn as_points(v: &Value) -> Result<[Point; SEAT], String> {
+    let mut points = [0; SEAT];
+    for (s, &p) in as_i64_vec(v)?.iter().enumerate().take(SEAT) {
+        points[s] = p as Point;
+    }
+    Ok(points)
+}
+
+fn tile_table_inc(tt: &mut TileTable, t: Tile) {
+    tt[t.0][t.1] += 1;
+    if t.1 == 0 {
+        tt[t.0][5] += 1; // 0は赤5のフラグなので本来の5もたてる
+    }
+}
+
+fn tile_table_dec(tt: &mut TileTable, t: Tile) {
+    tt[t.0][t.1] -= 1;
+    if t.1 == 0 {
+        tt[t.0][5] -= 1;
+    }
+}
+
+#[test]
+fn test_tenhou_round_trip() {
+    use crate::actor::create_actor;
+    use crate::controller::StageController;
+
+    let round_trip = |s: &str| {
+        let log = TenhouLog::from_str(s).unwrap();
+        let events = TenhouDeserializer::new().deserialize(&log).unwrap();
+
+        let nop = create_actor("Nop");
+        let actors = [
+            nop.clone_box(),
+            nop.clone_box(),
+            nop.clone_box(),
+            nop.clone_box(),
+        ];
+        let mut ctrl = StageController::new(actors, vec![]);
+        let mut serializer = TenhouSerializer::new(TenhouLog::new());
+        let mut kyokus = vec![];
+        for e in &events {
+            ctrl.handle_event(e);
+            serializer.push_event(ctrl.get_stage(), e);
+            if let Event::Win(_) | Event::Draw(_) = e {
+                kyokus.push(serializer.kyoku.to_log());
+            }
+        }
+        assert_eq!(json!(kyokus), json!(log.log));
+    };
+
+    // 4人東 (チー, ポン, 加槓, 大明槓, 暗槓, リーチ, ロン, ツモ, 流局)
+    round_trip(
+        r#"{
+            "log":[
+                [[1,0,0],[25000,25000,13700,36300],[34,13],[22,16],[13,51,16,17,19,23,27,29,36,38,39,39,43],[46,37,11,"p393939",23,45,21,31],[43,46,19,23,23,45,21,31],[11,14,15,19,23,26,29,33,37,38,38,41,41],[44,"4141p41",41,34,12,16,15,21,38],[44,29,"4141k4141",11,19,37,15,12,26],[14,27,28,28,29,31,31,32,33,34,36,41,45],[19,36,44,25,42,24,26,39],[41,45,44,28,42,19,"r14",60],[12,13,14,18,22,22,28,29,32,36,43,46,46],[35,19,"4646p46",39,26,13,"c141213",11],[43,32,19,39,29,13,18,11],["和了",[-1300,0,2300,0],[2,0,2,"40符1飜1300点","立直(1飜)"]]],
+                [[0,0,0],[25000,25000,25000,25000],[23,43],[22,25],[14,16,19,22,22,24,24,26,28,31,33,33,36],[41,24,11,19,41,27,"24m242424",25,38,21,29,47],[41,19,11,19,41,36,25,38,21,29,47],[12,14,16,18,21,25,28,31,34,38,39,43,45],[46,35,42,39,44,44,18,46,13,43,45],[43,45,42,46,44,44,25,46,39,43,45],[11,13,14,16,17,23,23,24,27,29,32,39,44],[36,37,29,41,35,29,47,17,12,43,15],[44,11,39,41,32,24,47,27,"r17",60],[51,15,17,18,21,26,27,33,34,38,42,45,47],[37,32,"c393738",14,38,46,41,19,45],[42,45,47,21,14,46,41,38,45],["和了",[-3900,-2000,8900,-2000],[2,2,2,"30符4飜2000-3900点","門前清自摸和(1飜)","立直(1飜)","裏ドラ(2飜)"]]],
+                [[0,0,0],[25000,25000,25000,25000],[35,15],[],[11,13,13,16,22,27,27,29,38,41,42,42,46],[47,22,12,21,24,43,47,26,"c232122",25,41,44,46,23,19,22,19,"c252324"],[41,46,47,29,16,43,47,38,13,27,41,44,46,22,19,27,19,26],[14,14,16,17,23,52,29,32,35,36,36,37,46],[31,27,17,16,17,24,28,26,28,53,13,34,11,25,29,34,12,17,14],[46,36,29,16,16,"r27",60,60,60,60,60,60,60,60,60,60,60,"171717a17",60],[11,15,18,21,21,26,32,35,36,41,44,44,46],[47,11,16,43,19,39,42,24,15,33,37,39,33,23,36,"c343233",51,24],[41,46,47,43,19,44,44,16,26,18,35,42,11,23,11,39,39,15],[12,21,25,26,28,34,38,42,43,43,45,45,47],[44,38,32,14,39,31,37,23,18,38,27,18,41,29,28,18,32,19],[42,44,47,21,28,31,38,23,18,38,43,43,41,29,18,18,25,19],["流局",[-1000,3000,-1000,-1000]]]
+            ],
+            "name":["","","",""],
+            "rule":{"disp":"東喰赤","aka":1}
+        }"#,
+    );
+
+    // 3人東 (北抜き, 加槓, 暗槓)
+    round_trip(
+        r#"{
+            "log":[
+                [[4,0,0],[16900,49000,9100,25000],[35,44,46],[32,38,37],[22,22,23,29,31,32,34,36,38,41,42,44,47],[47,37,11,"4747p47",11,24,47,45,36,31,21,32,38],["f44",41,42,11,11,29,"4747k4747",45,31,31,32,32,34],[19,21,27,31,33,34,37,38,43,43,43,44,45],[33,47,22,11,44,39,46,19,19,27,43,31,23,41],["f44",45,47,11,"f44",19,46,19,19,31,"434343a43",31,"r33",60],[11,19,23,26,29,29,35,36,39,39,41,45,46],[26,42,22,26,25,25,37,46,25,53],[41,11,42,45,46,19,29,46,"r29",60],[],[],[],["和了",[0,14000,-12000,0],[1,2,1,"跳満12000点","立直(1飜)","赤ドラ(1飜)","裏ドラ(3飜)","抜きドラ(2飜)"]]]
+            ],
+            "name":["","","",""],
+            "rule":{"disp":"三東喰赤","aka":1}
+        }"#,
+    );
+}
+
+#[test]
+fn test_tile_from_tenhou() {
+    assert_eq!(tile_from_tenhou(0), Ok(Z8));
+    assert_eq!(tile_from_tenhou(15), Ok(Tile(TM, 5)));
+    assert_eq!(tile_from_tenhou(47), Ok(Tile(TZ, DR)));
+    assert_eq!(tile_from_tenhou(52), Ok(Tile(TP, 0)));
+    for t in [-1, 10, 20, 30, 40, 48, 50, 54, 60] {
+        assert!(tile_from_tenhou(t).is_err(), "{}", t);
+    }
+
+    // 不正な牌番号(20)や整数でない打牌を含む牌譜はパニックせずにエラー
+    for (hand, discard) in [("20", "11"), ("12", "20"), ("12", "11.5")] {
+        let s = format!(
+            r#"{{"log":[[[0,0,0],[25000,25000,25000,25000],[11],[],[{h},12,13,14,15,16,17,18,19,21,22,23,24],[25],[{d}],[31,32,33,34,35,36,37,38,39,41,42,43,44],[],[],[31,32,33,34,35,36,37,38,39,41,42,43,44],[],[],[31,32,33,34,35,36,37,38,39,41,42,43,44],[],[],["流局",[0,0,0,0]]]],"name":["","","",""],"rule":{{"disp":"東喰赤","aka":1}}}}"#,
+            h = hand,
+            d = discard
+        );
+        let log = TenhouLog::from_str(&s).unwrap();
+        assert!(TenhouDeserializer::new().deserialize(&log).is_err());
+    }
+}
+
+#[test]
+fn test_tenhou_invalid_log() {
+    // d: 座席0の打牌, m: 座席1の鳴き, r: 局の結果
+    let create_log = |d: &str, m: &str, r: &str| {
+        format!(
+            r#"{{"log":[[[0,0,0],[25000,25000,25000,25000],[11],[],[11,12,13,14,15,16,17,18,19,21,22,23,24],[25],[{d}],[31,32,33,34,35,36,37,38,39,41,42,43,44],[{m}],[],[31,32,33,34,35,36,37,38,39,41,42,43,44],[],[],[31,32,33,34,35,36,37,38,39,41,42,43,44],[],[],{r}]],"name":["","","",""],"rule":{{"disp":"東喰赤","aka":1}}}}"#,
+            d = d,
+            m = m,
+            r = r
+        )
+    };
+    let deserialize = |s: &str| {
+        let log = TenhouLog::from_str(s).unwrap();
+        TenhouDeserializer::new().deserialize(&log)
+    };
+    let draw = r#"["流局",[0,0,0,0]]"#;
+    assert!(deserialize(&create_log("60", "", draw)).is_ok());
+
+    // 手牌にない牌の打牌
+    let res = deserialize(&create_log("29", "", draw));
+    assert_eq!(res.err(), Some("tile p9 not found in hand 0".to_string()));
+
+    // 手牌にない牌でのポン
+    let res = deserialize(&create_log("60", r#""p252525""#, draw));
+    assert_eq!(res.err(), Some("tile p5 not found in hand 1".to_string()));
+
+    // 存在しない座席の和了
+    let win = r#"["和了",[0,0,0,0],[7,0,7,"30符1飜1000点","立直(1飜)"]]"#;
+    assert!(deserialize(&create_log("60", "", win)).is_err());
+}
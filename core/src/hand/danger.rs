@@ -0,0 +1,201 @@
+use crate::model::*;
+
+use super::shanten::calc_unseen_tile_table;
+
+// [危険度推定]
+// 他家への放銃率を牌ごとに推定する
+// 現物, 筋, 壁(ノーチャンス)による両面待ちの否定と牌の見え方から大まかな値を算出する
+// 以下の数値は実戦データから集計したものではなく,手調整による目安の値である
+// 現物 = 0 < 筋 < 無筋, 中張牌の無筋 > 端牌の無筋, 生牌の字牌 > 見えている字牌,
+// といった一般に知られている危険度の大小関係を満たすように設定している
+
+pub type DangerTable = [[f32; TNUM]; TYPE]; // 牌ごとの放銃率 (index 0の赤5は5と同じ値)
+
+// 両面待ちの形1つあたりの放銃率 (数字ごと)
+const DANGER_RYANMEN: [f32; TNUM] = [
+    0.0, 0.055, 0.055, 0.055, 0.045, 0.045, 0.045, 0.055, 0.055, 0.055,
+];
+// 両面以外(嵌張, 辺張, 双碰, 単騎)の放銃率 (数字ごと)
+const DANGER_OTHER: [f32; TNUM] = [
+    0.0, 0.015, 0.025, 0.035, 0.03, 0.03, 0.03, 0.035, 0.025, 0.015,
+];
+// 字牌の放銃率 (見えていない枚数ごと)
+const DANGER_HONOR: [f32; TILE] = [0.0, 0.005, 0.03, 0.06];
+// リーチ宣言牌が手出しの場合の宣言牌のソバ(±2以内)の危険度の倍率
+const RIICHI_TILE_NEIGHBOR: f32 = 1.2;
+
+// 対戦相手targetが聴牌している確率の推定
+pub fn estimate_tenpai_prob(stg: &Stage, target: Seat) -> f32 {
+    let pl = &stg.players[target];
+    if pl.is_riichi {
+        return 1.0;
+    }
+
+    let p = 0.02 * pl.discards.len() as f32 + 0.15 * pl.melds.len() as f32;
+    p.min(0.8)
+}
+
+// 対戦相手targetの現物 (targetの捨て牌, targetのリーチ後に他家が捨てて見逃された牌)
+pub fn calc_genbutsu(stg: &Stage, target: Seat) -> TileTable {
+    let mut tt = TileTable::default();
+    let pl = &stg.players[target];
+    for d in &pl.discards {
+        let t = d.tile.to_normal();
+        tt[t.0][t.1] = 1;
+    }
+
+    if let Some(ri) = pl.riichi {
+        if let Some(pos) = stg.discards.iter().position(|&d| d == (target, ri)) {
+            for &(s, i) in &stg.discards[pos + 1..] {
+                let t = stg.players[s].discards[i].tile.to_normal();
+                tt[t.0][t.1] = 1;
+            }
+        }
+    }
+
+    tt
+}
+
+// 座席seatから見た対戦相手targetに対する各牌の放銃率 (targetの聴牌を仮定)
+pub fn estimate_danger(stg: &Stage, seat: Seat, target: Seat) -> DangerTable {
+    let mut dt = DangerTable::default();
+    let genbutsu = calc_genbutsu(stg, target);
+    let unseen = calc_unseen_tile_table(stg, seat);
+
+    // リーチ宣言牌 (手出しの場合のみ)
+    let pl = &stg.players[target];
+    let riichi_tile = match pl.riichi {
+        Some(i) if !pl.discards[i].drawn => Some(pl.discards[i].tile.to_normal()),
+        _ => None,
+    };
+
+    for ti in 0..TYPE {
+        for ni in 1..TNUM {
+            if ti == TZ && ni > DR {
+                break;
+            }
+            if genbutsu[ti][ni] != 0 {
+                continue;
+            }
+
+            if ti == TZ {
+                dt[ti][ni] = DANGER_HONOR[std::cmp::min(unseen[ti][ni], TILE - 1)];
+                continue;
+            }
+
+            // 両面待ちで放銃する形 (ni-2, ni-1), (ni+1, ni+2) のうち否定されていない形の数
+            // 筋: もう一方の待ちが現物, 壁: 形を構成する牌が見えていない牌に残っていない
+            let mut n_ryanmen = 0;
+            if ni >= 4
+                && genbutsu[ti][ni - 3] == 0
+                && unseen[ti][ni - 2] > 0
+                && unseen[ti][ni - 1] > 0
+            {
+                n_ryanmen += 1;
+            }
+            if ni <= 6
+                && genbutsu[ti][ni + 3] == 0
+                && unseen[ti][ni + 1] > 0
+                && unseen[ti][ni + 2] > 0
+            {
+                n_ryanmen += 1;
+            }
+
+            // 両面以外の待ちは見えていない枚数が少ないほど安全 (双碰には2枚必要)
+            let other = DANGER_OTHER[ni] * std::cmp::min(unseen[ti][ni], 2) as f32 / 2.0;
+            let mut d = DANGER_RYANMEN[ni] * n_ryanmen as f32 + other;
+
+            if let Some(rt) = riichi_tile {
+                if rt.0 == ti && rt.1 != ni && (rt.1 as i32 - ni as i32).abs() <= 2 {
+                    d *= RIICHI_TILE_NEIGHBOR;
+                }
+            }
+
+            dt[ti][ni] = d;
+        }
+        dt[ti][0] = dt[ti][5];
+    }
+
+    dt
+}
+
+// 座席seatから見た全対戦相手に対する各牌の放銃率 (各対戦相手の聴牌確率を考慮)
+pub fn estimate_danger_all(stg: &Stage, seat: Seat) -> DangerTable {
+    let mut safe = [[1.0; TNUM]; TYPE]; // 誰にも放銃しない確率
+    for s in 0..stg.get_seat_count() {
+        if s == seat {
+            continue;
+        }
+        let p = estimate_tenpai_prob(stg, s);
+        let dt = estimate_danger(stg, seat, s);
+        for ti in 0..TYPE {
+            for ni in 0..TNUM {
+                safe[ti][ni] *= 1.0 - p * dt[ti][ni];
+            }
+        }
+    }
+
+    let mut dt = DangerTable::default();
+    for ti in 0..TYPE {
+        for ni in 0..TNUM {
+            dt[ti][ni] = 1.0 - safe[ti][ni];
+        }
+    }
+    dt
+}
+
+#[test]
+fn test_danger() {
+    use crate::controller::create_test_controller;
+
+    // 座席0: 8sを4枚保持, 座席1: 4m切りの後に5pでツモ切りリーチ, 座席2: リーチ後に9mを捨てる
+    let hands = ["m123p123s8888z1114", "m1234p678s234z333", "", ""];
+    let mut ctrl = create_test_controller(EventNew::from_hands(1, hands));
+    for e in [
+        Event::discard(0, Tile(TZ, WN), true, false),
+        Event::deal(1, Tile(TZ, DR)),
+        Event::discard(1, Tile(TM, 4), false, false),
+        Event::deal(2, Z8),
+        Event::discard(2, Tile(TZ, DR), true, false),
+        Event::deal(3, Z8),
+        Event::discard(3, Tile(TZ, DW), true, false),
+        Event::deal(0, Tile(TZ, WS)),
+        Event::discard(0, Tile(TZ, WS), true, false),
+        Event::deal(1, Tile(TP, 5)),
+        Event::discard(1, Tile(TP, 5), true, true),
+        Event::deal(2, Z8),
+        Event::discard(2, Tile(TM, 9), true, false),
+    ] {
+        ctrl.handle_event(&e);
+    }
+    let stg = ctrl.get_stage();
+
+    // 聴牌確率: リーチ者は1.0, それ以外は捨て牌と副露の数から推定
+    assert_eq!(estimate_tenpai_prob(stg, 1), 1.0);
+    assert!(estimate_tenpai_prob(stg, 3) < estimate_tenpai_prob(stg, 2));
+
+    // 現物 (自身の捨て牌, リーチ後に見逃された牌) は0
+    let gb = calc_genbutsu(stg, 1);
+    assert_eq!((gb[TM][4], gb[TP][5], gb[TM][9], gb[TZ][DR]), (1, 1, 1, 0));
+    let dt = estimate_danger(stg, 0, 1);
+    assert_eq!(dt[TM][4], 0.0);
+    assert_eq!(dt[TP][5], 0.0);
+    assert_eq!(dt[TP][0], 0.0);
+    assert_eq!(dt[TM][9], 0.0);
+
+    // 筋 (4m現物による1m, 7m) は無筋より安全
+    assert!(dt[TM][1] < dt[TS][1]);
+    assert!(dt[TM][7] < dt[TS][7]);
+    assert!(dt[TM][7] > 0.0);
+
+    // 壁 (8sが全て見えている) により9sの両面待ちが否定される
+    assert!(dt[TS][9] < dt[TP][9]);
+    assert_eq!(dt[TS][9], DANGER_OTHER[9]);
+
+    // 無筋の中張牌は端牌より危険
+    assert!(dt[TS][1] < dt[TS][5]);
+
+    // 全体の危険度はリーチ者への放銃率が支配的
+    let dt_all = estimate_danger_all(stg, 0);
+    assert!(dt_all[TP][5] < dt_all[TS][5]);
+}
@@ -38,6 +38,7 @@ pub fn evaluate_hand_tsumo(stage: &Stage, ura_dora_wall: &Vec<Tile>) -> Option<W
     if let Some(res) = evaluate_hand(
         &pl.hand,
         &pl.melds,
+        pl.kitas.len(),
         &stage.doras,
         &ura_doras,
         pl.drawn.unwrap(),
@@ -46,6 +47,7 @@ pub fn evaluate_hand_tsumo(stage: &Stage, ura_dora_wall: &Vec<Tile>) -> Option<W
         stage.get_prevalent_wind(),
         stage.get_seat_wind(pl.seat),
         &yf,
+        &stage.rule,
     ) {
         if !res.yakus.is_empty() {
             return Some(res);
@@ -101,6 +103,7 @@ pub fn evaluate_hand_ron(
                 return None; // 暗槓のロンは国士無双のみ
             }
         }
+        ActionType::Kita => {} // 抜き北のロン (槍槓は付かない)
         _ => panic!(),
     }
 
@@ -113,6 +116,7 @@ pub fn evaluate_hand_ron(
     if let Some(res) = evaluate_hand(
         &hand,
         &pl.melds,
+        pl.kitas.len(),
         &stage.doras,
         &ura_doras,
         t,
@@ -121,6 +125,7 @@ pub fn evaluate_hand_ron(
         stage.get_prevalent_wind(),
         stage.get_seat_wind(pl.seat),
         &yf,
+        &stage.rule,
     ) {
         if !res.yakus.is_empty() {
             return Some(res);
@@ -130,12 +135,13 @@ pub fn evaluate_hand_ron(
     None
 }
 
-// 和了形である場合,最も高得点となるような役の組み合わせのSome(Result)を返却
-// 和了形でない場合,Noneを返却
-// 和了形でも無役の場合はResultの中身がyaku: [], points(0, 0, 0)となる.
+/// 和了形である場合,最も高得点となるような役の組み合わせのSome(Result)を返却
+/// 和了形でない場合,Noneを返却
+/// 和了形でも無役の場合はResultの中身がyaku: [], points(0, 0, 0)となる.
 pub fn evaluate_hand(
     hand: &TileTable,       // 手牌(鳴き以外)
     melds: &Vec<Meld>,      // 鳴き
+    n_kita: usize,          // 北抜きの数 (三麻)
     doras: &Vec<Tile>,      // ドラ表示牌 (注:ドラそのものではない)
     ura_doras: &Vec<Tile>,  // 裏ドラ表示牌 リーチしていない場合は空
     win_tile: Tile,         // 上がり牌
@@ -144,6 +150,7 @@ pub fn evaluate_hand(
     prevalent_wind: Index,  // 場風 (東: 1, 南: 2, 西: 3, 北: 4)
     seat_wind: Index,       // 自風 (同上)
     yaku_flags: &YakuFlags, // 和了形だった場合に自動的に付与される役(特殊条件役)のフラグ
+    rule: &Rule,            // ルール設定 (喰いタン, 切り上げ満貫など)
 ) -> Option<WinContext> {
     let mut wins = vec![]; // 和了形のリスト (無役を含む)
 
@@ -159,6 +166,7 @@ pub fn evaluate_hand(
             seat_wind,
             is_drawn,
             yaku_flags.clone(),
+            rule,
         );
         wins.push(ctx);
     }
@@ -173,6 +181,7 @@ pub fn evaluate_hand(
             seat_wind,
             is_drawn,
             yaku_flags.clone(),
+            rule,
         );
         wins.push(ctx);
     }
@@ -187,6 +196,7 @@ pub fn evaluate_hand(
             seat_wind,
             is_drawn,
             yaku_flags.clone(),
+            rule,
         );
         wins.push(ctx);
     }
@@ -205,10 +215,11 @@ pub fn evaluate_hand(
         }
     }
     let n_ura_dora = if yaku_flags.riichi || yaku_flags.dabururiichi {
-        count_dora(hand, melds, ura_doras)
+        count_dora(hand, melds, ura_doras) + count_kita_dora(n_kita, ura_doras)
     } else {
         0
     };
+    let n_dora = n_dora + count_kita_dora(n_kita, doras);
 
     let mut results = vec![];
     for ctx in wins {
@@ -230,7 +241,7 @@ pub fn evaluate_hand(
             })
             .collect();
         if yakuman_times == 0 {
-            fan += n_dora + n_red_dora + n_ura_dora;
+            fan += n_dora + n_red_dora + n_ura_dora + n_kita;
             if n_dora != 0 {
                 yakus.push(("ドラ".to_string(), n_dora));
             }
@@ -240,9 +251,12 @@ pub fn evaluate_hand(
             if n_ura_dora != 0 {
                 yakus.push(("裏ドラ".to_string(), n_ura_dora));
             }
+            if n_kita != 0 {
+                yakus.push(("抜きドラ".to_string(), n_kita));
+            }
         }
-        let points = get_points(is_dealer, fu, fan, yakuman_times);
-        let score_title = get_score_title(fu, fan, yakuman_times);
+        let points = get_points(is_dealer, fu, fan, yakuman_times, rule);
+        let score_title = get_score_title(fu, fan, yakuman_times, rule);
         results.push(WinContext {
             hand,
             yakus,
@@ -301,3 +315,71 @@ fn count_dora(hand: &TileTable, melds: &Vec<Meld>, doras: &Vec<Tile>) -> usize {
 
     n_dora
 }
+
+// 抜き北がドラ(表示牌が西)の場合のドラの数を勘定
+fn count_kita_dora(n_kita: usize, doras: &Vec<Tile>) -> usize {
+    let dt = create_dora_table(doras);
+    dt[TZ][WN] * n_kita
+}
+
+#[test]
+fn test_kita_dora() {
+    // 123p 456p 789s 22s 456s (6sツモ)
+    let mut hand = TileTable::default();
+    for &t in &[
+        Tile(TP, 1),
+        Tile(TP, 2),
+        Tile(TP, 3),
+        Tile(TP, 4),
+        Tile(TP, 5),
+        Tile(TP, 6),
+        Tile(TS, 7),
+        Tile(TS, 8),
+        Tile(TS, 9),
+        Tile(TS, 2),
+        Tile(TS, 2),
+        Tile(TS, 4),
+        Tile(TS, 5),
+        Tile(TS, 6),
+    ] {
+        hand[t.0][t.1] += 1;
+    }
+    let eval = |n_kita: usize, doras: &Vec<Tile>| {
+        let yf = YakuFlags::default();
+        let rule = Rule::default();
+        evaluate_hand(
+            &hand,
+            &vec![],
+            n_kita,
+            doras,
+            &vec![],
+            Tile(TS, 6),
+            true,
+            false,
+            WE,
+            WS,
+            &yf,
+            &rule,
+        )
+        .unwrap()
+    };
+    let find = |ctx: &WinContext, name: &str| {
+        ctx.yakus
+            .iter()
+            .find(|y| y.0 == name)
+            .map(|y| y.1)
+            .unwrap_or(0)
+    };
+
+    // 抜き北1枚につき1翻
+    let ctx0 = eval(0, &vec![Tile(TZ, WE)]);
+    let ctx2 = eval(2, &vec![Tile(TZ, WE)]);
+    assert_eq!(find(&ctx2, "抜きドラ"), 2);
+    assert_eq!(ctx2.fan, ctx0.fan + 2);
+
+    // ドラ表示牌が西の場合は抜き北もドラ
+    let ctx2 = eval(2, &vec![Tile(TZ, WW)]);
+    assert_eq!(find(&ctx2, "抜きドラ"), 2);
+    assert_eq!(find(&ctx2, "ドラ"), 2);
+    assert_eq!(ctx2.fan, ctx0.fan + 4);
+}
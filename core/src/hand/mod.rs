@@ -1,11 +1,22 @@
+mod danger;
 mod evaluate;
 mod parse;
 mod point;
+mod shanten;
 mod win;
+mod win_table;
 mod yaku;
 
+pub use danger::{
+    calc_genbutsu, estimate_danger, estimate_danger_all, estimate_tenpai_prob, DangerTable,
+};
 pub use evaluate::{evaluate_hand, evaluate_hand_ron, evaluate_hand_tsumo};
-pub use point::get_score_title;
+pub use point::{get_pao, get_points, get_score_title};
+pub use shanten::{
+    calc_discards_with_effective_tiles, calc_effective_tiles, calc_shanten,
+    calc_shanten_chiitoitsu, calc_shanten_kokushimusou, calc_shanten_normal,
+    calc_unseen_tile_table, count_effective_tiles,
+};
 pub use win::{
     calc_discards_to_chiitoitsu_tenpai, calc_discards_to_kokushimusou_tenpai,
     calc_discards_to_normal_tenpai, calc_tiles_to_chiitoitsu_win, calc_tiles_to_kokushimusou_win,
@@ -1,4 +1,4 @@
-use crate::model::{Point, Points};
+use crate::model::{Player, Point, Points, Rule, Seat, WinContext};
 
 // 親が他家を直撃した場合の点数表 (役満未満)
 const POINT_DEALER: [[Point; 11]; 13] = [
@@ -66,6 +66,11 @@ fn calc_fu_index(fu: usize) -> usize {
     }
 }
 
+// 切り上げ満貫の対象となる組み合わせ (30符4飜, 60符3飜)
+fn is_kiriage_mangan(fu: usize, fan: usize) -> bool {
+    (fu == 30 && fan == 4) || (fu == 60 && fan == 3)
+}
+
 fn ceil100(n: Point) -> Point {
     (n + 99) / 100 * 100
 }
@@ -104,7 +109,20 @@ fn get_points_non_dealer_yakuman(mag: usize) -> Points {
     (s, s / 4, s / 2)
 }
 
-pub fn get_points(is_dealer: bool, fu: usize, fan: usize, yakuman_times: usize) -> Points {
+pub fn get_points(
+    is_dealer: bool,
+    fu: usize,
+    fan: usize,
+    yakuman_times: usize,
+    rule: &Rule,
+) -> Points {
+    // 切り上げ満貫の場合は5飜(満貫)として計算
+    let fan = if rule.kiriage && is_kiriage_mangan(fu, fan) {
+        5
+    } else {
+        fan
+    };
+
     if is_dealer {
         if yakuman_times > 0 {
             get_points_dealer_yakuman(yakuman_times)
@@ -120,12 +138,14 @@ pub fn get_points(is_dealer: bool, fu: usize, fan: usize, yakuman_times: usize)
     }
 }
 
-pub fn get_score_title(fu: usize, fan: usize, yakuman_times: usize) -> String {
+pub fn get_score_title(fu: usize, fan: usize, yakuman_times: usize, rule: &Rule) -> String {
     let fu_index = calc_fu_index(fu);
     match yakuman_times {
         0 => {
             if fan >= 13 {
                 "数え役満"
+            } else if rule.kiriage && is_kiriage_mangan(fu, fan) {
+                "満貫"
             } else {
                 match POINT_NON_DEALER[fan][fu_index] {
                     8000 => "満貫",
@@ -147,3 +167,41 @@ pub fn get_score_title(fu: usize, fan: usize, yakuman_times: usize) -> String {
     }
     .to_string()
 }
+
+// 責任払い(包)の対象となる場合,Some((責任者の座席, 該当役満の倍数))を返却
+pub fn get_pao(pl: &Player, ctx: &WinContext) -> Option<(Seat, usize)> {
+    let (seat, name) = pl.pao.as_ref()?;
+    if ctx.yakuman_times == 0 {
+        return None;
+    }
+    ctx.yakus
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|&(_, fan)| (*seat, fan - 12))
+}
+
+#[test]
+fn test_kiriage() {
+    let mut rule = Rule::default();
+    rule.kiriage = false;
+    assert_eq!(get_points(false, 30, 4, 0, &rule).0, 7700);
+    assert_eq!(get_points(true, 30, 4, 0, &rule).0, 11600);
+    assert_eq!(get_points(false, 60, 3, 0, &rule).0, 7700);
+    assert_eq!(get_score_title(30, 4, 0, &rule), "");
+
+    // 切り上げ満貫: 30符4翻, 60符3翻を満貫として扱う
+    rule.kiriage = true;
+    assert_eq!(
+        get_points(false, 30, 4, 0, &rule),
+        get_points(false, 40, 5, 0, &rule)
+    );
+    assert_eq!(get_points(true, 30, 4, 0, &rule).0, 12000);
+    assert_eq!(get_points(false, 60, 3, 0, &rule).0, 8000);
+    assert_eq!(get_score_title(30, 4, 0, &rule), "満貫");
+    assert_eq!(get_score_title(60, 3, 0, &rule), "満貫");
+
+    // 対象外の符,翻数は変化しない
+    assert_eq!(get_points(false, 30, 3, 0, &rule).0, 3900);
+    assert_eq!(get_points(false, 50, 3, 0, &rule).0, 6400);
+    assert_eq!(get_score_title(30, 3, 0, &rule), "");
+}
@@ -0,0 +1,473 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
+
+use crate::model::*;
+
+use TileStateType::*;
+
+const MAX_SET: usize = 4; // 手牌の面子の最大数
+
+// [向聴数]
+// 和了形: -1, 聴牌: 0, 一向聴: 1, ...
+// 手牌は3n+1枚(ツモ前), 3n+2枚(ツモ後)のどちらでもよい
+// 赤5(index 0)は通常の5として扱うので考慮しない
+
+// 通常形
+pub fn calc_shanten_normal(hand: &TileTable) -> i32 {
+    let n_set = count_hand_tiles(hand) / 3; // 必要な面子の数 (副露を除く)
+
+    // 牌種ごとの(雀頭, 面子)に対する塔子の最大数を全牌種で合算
+    let mut totals = [[-1; MAX_SET + 1]; 2];
+    totals[0][0] = 0;
+    for (ti, row) in hand.iter().enumerate() {
+        let patterns = calc_row_patterns(row, ti != TZ);
+
+        let mut next = [[-1; MAX_SET + 1]; 2];
+        for (p0, row0) in totals.iter().enumerate() {
+            for (m0, &t0) in row0.iter().enumerate() {
+                if t0 < 0 {
+                    continue;
+                }
+                for (p1, row1) in patterns.iter().enumerate().take(2 - p0) {
+                    for (m1, &t1) in row1.iter().enumerate() {
+                        if t1 < 0 {
+                            continue;
+                        }
+                        let m = std::cmp::min(m0 + m1, MAX_SET);
+                        let t = std::cmp::min(t0 + t1, MAX_SET as i8);
+                        let e = &mut next[p0 + p1][m];
+                        if t > *e {
+                            *e = t;
+                        }
+                    }
+                }
+            }
+        }
+        totals = next;
+    }
+
+    let mut shanten = 2 * n_set as i32;
+    for (p, row) in totals.iter().enumerate() {
+        for (m, &t) in row.iter().enumerate() {
+            if t < 0 {
+                continue;
+            }
+            let m = std::cmp::min(m, n_set);
+            let t = std::cmp::min(t as usize, n_set - m); // 面子+塔子は必要な面子数まで
+            let s = 2 * (n_set - m) as i32 - t as i32 - p as i32;
+            if s < shanten {
+                shanten = s;
+            }
+        }
+    }
+    shanten
+}
+
+// 七対子
+pub fn calc_shanten_chiitoitsu(hand: &TileTable) -> i32 {
+    if count_hand_tiles(hand) < 13 {
+        return i32::MAX; // 副露している場合は不可
+    }
+
+    let mut n_pair = 0;
+    let mut n_kind = 0;
+    for row in hand {
+        for &n in &row[1..TNUM] {
+            if n >= 1 {
+                n_kind += 1;
+            }
+            if n >= 2 {
+                n_pair += 1;
+            }
+        }
+    }
+
+    // 同じ牌4枚は2対子として扱えないので種類の不足分を加算
+    6 - n_pair + std::cmp::max(0, 7 - n_kind)
+}
+
+// 国士無双
+pub fn calc_shanten_kokushimusou(hand: &TileTable) -> i32 {
+    if count_hand_tiles(hand) < 13 {
+        return i32::MAX; // 副露している場合は不可
+    }
+
+    let mut n_kind = 0;
+    let mut has_pair = false;
+    let mut check = |n: usize| {
+        if n >= 1 {
+            n_kind += 1;
+        }
+        if n >= 2 {
+            has_pair = true;
+        }
+    };
+    for row in &hand[..TZ] {
+        check(row[1]);
+        check(row[9]);
+    }
+    for &n in &hand[TZ][WE..=DR] {
+        check(n);
+    }
+
+    13 - n_kind - if has_pair { 1 } else { 0 }
+}
+
+// 通常形, 七対子, 国士無双のうち最小の向聴数
+pub fn calc_shanten(hand: &TileTable) -> i32 {
+    let s0 = calc_shanten_normal(hand);
+    let s1 = calc_shanten_chiitoitsu(hand);
+    let s2 = calc_shanten_kokushimusou(hand);
+    std::cmp::min(s0, std::cmp::min(s1, s2))
+}
+
+// [有効牌(受け入れ)]
+
+// 3n+1枚の手牌について向聴数を下げる牌(有効牌)のリストを返却
+pub fn calc_effective_tiles(hand: &TileTable) -> Vec<Tile> {
+    let mut hand = *hand;
+    let shanten = calc_shanten(&hand);
+    let mut res = vec![];
+    for ti in 0..TYPE {
+        for ni in 1..TNUM {
+            if ti == TZ && ni > DR {
+                break;
+            }
+            if hand[ti][ni] == TILE {
+                continue;
+            }
+            hand[ti][ni] += 1;
+            if calc_shanten(&hand) < shanten {
+                res.push(Tile(ti, ni));
+            }
+            hand[ti][ni] -= 1;
+        }
+    }
+    res
+}
+
+// 3n+2枚の手牌について打牌候補ごとに(打牌, 打牌後の向聴数, 有効牌)のリストを返却
+// 打牌候補の赤5は通常の5として返却する
+pub fn calc_discards_with_effective_tiles(hand: &TileTable) -> Vec<(Tile, i32, Vec<Tile>)> {
+    let mut hand = *hand;
+    let mut res = vec![];
+    for ti in 0..TYPE {
+        for ni in 1..TNUM {
+            if hand[ti][ni] == 0 {
+                continue;
+            }
+            // 赤5のフラグは通常の5を打牌する際には影響しないので一時的に外す
+            let red5 = hand[ti][0];
+            if ni == 5 && hand[ti][5] == red5 {
+                hand[ti][0] -= 1;
+            }
+            hand[ti][ni] -= 1;
+            let shanten = calc_shanten(&hand);
+            let tiles = calc_effective_tiles(&hand);
+            res.push((Tile(ti, ni), shanten, tiles));
+            hand[ti][ni] += 1;
+            hand[ti][0] = red5;
+        }
+    }
+    res
+}
+
+// 座席seatから見えていない牌(牌山, 他家の手牌)の枚数のテーブルを返却
+// 三麻で使用しない牌(萬子の2~8)は0枚
+pub fn calc_unseen_tile_table(stg: &Stage, seat: Seat) -> TileTable {
+    let mut tt = TileTable::default();
+    for (ti, row) in tt.iter_mut().enumerate() {
+        for (ni, n) in row.iter_mut().enumerate().skip(1) {
+            if ti == TZ && ni > DR {
+                break;
+            }
+            if stg.is_3p && ti == TM && 1 < ni && ni < 9 {
+                continue;
+            }
+            for st in &stg.tile_states[ti][ni] {
+                match st {
+                    U => *n += 1,
+                    H(s) if *s != seat => *n += 1,
+                    _ => {}
+                }
+            }
+        }
+    }
+    tt
+}
+
+// 有効牌の残り枚数の合計
+pub fn count_effective_tiles(tiles: &[Tile], unseen: &TileTable) -> usize {
+    tiles.iter().map(|t| unseen[t.0][t.1]).sum()
+}
+
+// [Utility]
+
+// 手牌(副露を除く)の枚数
+fn count_hand_tiles(hand: &TileTable) -> usize {
+    hand.iter()
+        .map(|row| row[1..TNUM].iter().sum::<usize>())
+        .sum()
+}
+
+// 牌種(1列)を面子,塔子,雀頭に分解した際の(雀頭の数, 面子の数)ごとの塔子の最大数
+// 該当する分解が存在しない場合は-1, 面子と塔子の数はMAX_SETを上限とする
+// 同じ牌の並びに対する結果はスレッドごとにキャッシュする
+type RowPatterns = [[i8; MAX_SET + 1]; 2];
+
+fn calc_row_patterns(tr: &TileRow, is_suit: bool) -> RowPatterns {
+    thread_local! {
+        static CACHE: RefCell<HashMap<u32, RowPatterns, BuildHasherDefault<KeyHasher>>> =
+            RefCell::new(HashMap::default());
+    }
+
+    let mut key = if is_suit { 1 } else { 0 };
+    for &n in &tr[1..TNUM] {
+        key = key * 5 + n as u32;
+    }
+    if let Some(res) = CACHE.with(|c| c.borrow().get(&key).cloned()) {
+        return res;
+    }
+
+    let mut res = [[-1; MAX_SET + 1]; 2];
+    for (m, t, p) in calc_row_patterns_nocache(tr, is_suit) {
+        let m = std::cmp::min(m, MAX_SET);
+        let t = std::cmp::min(t, MAX_SET) as i8;
+        if t > res[p][m] {
+            res[p][m] = t;
+        }
+    }
+    CACHE.with(|c| c.borrow_mut().insert(key, res));
+    res
+}
+
+// キャッシュのキー(u32)用のハッシュ関数 (SipHashより高速)
+#[derive(Default)]
+struct KeyHasher(u64);
+
+impl Hasher for KeyHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.write_u64(self.0 << 8 | b as u64);
+        }
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = n.wrapping_mul(0x9e3779b97f4a7c15);
+    }
+}
+
+// 牌種(1列)を面子,塔子,雀頭に分解した(面子, 塔子, 雀頭)の組み合わせを列挙
+// 他の組み合わせに劣る(全ての要素が以下となる)組み合わせは除外する
+fn calc_row_patterns_nocache(tr: &TileRow, is_suit: bool) -> Vec<(usize, usize, usize)> {
+    let i = match (1..TNUM).find(|&i| tr[i] > 0) {
+        Some(i) => i,
+        None => return vec![(0, 0, 0)],
+    };
+
+    let mut res = vec![];
+    let mut next = |ns: &[usize], (m, t, p): (usize, usize, usize)| {
+        let mut tr = *tr;
+        for &n in ns {
+            tr[n] -= 1;
+        }
+        let patterns = calc_row_patterns(&tr, is_suit);
+        for (p2, row) in patterns.iter().enumerate().take(2 - p) {
+            for (m2, &t2) in row.iter().enumerate() {
+                if t2 >= 0 {
+                    res.push((m + m2, t + t2 as usize, p + p2));
+                }
+            }
+        }
+    };
+
+    // 刻子
+    if tr[i] >= 3 {
+        next(&[i, i, i], (1, 0, 0));
+    }
+    // 順子
+    if is_suit && i + 2 < TNUM && tr[i + 1] > 0 && tr[i + 2] > 0 {
+        next(&[i, i + 1, i + 2], (1, 0, 0));
+    }
+    // 雀頭
+    if tr[i] >= 2 {
+        next(&[i, i], (0, 0, 1));
+    }
+    // 対子(塔子)
+    if tr[i] >= 2 {
+        next(&[i, i], (0, 1, 0));
+    }
+    // 両面・辺張
+    if is_suit && i + 1 < TNUM && tr[i + 1] > 0 {
+        next(&[i, i + 1], (0, 1, 0));
+    }
+    // 嵌張
+    if is_suit && i + 2 < TNUM && tr[i + 2] > 0 {
+        next(&[i, i + 2], (0, 1, 0));
+    }
+    // 孤立牌
+    next(&[i], (0, 0, 0));
+
+    // 劣る組み合わせを除外
+    let mut res2: Vec<(usize, usize, usize)> = vec![];
+    for &e in &res {
+        let is_dominated = res
+            .iter()
+            .any(|&e2| e2 != e && e2.0 >= e.0 && e2.1 >= e.1 && e2.2 >= e.2);
+        if !is_dominated && !res2.contains(&e) {
+            res2.push(e);
+        }
+    }
+    res2
+}
+
+#[test]
+fn test_shanten() {
+    let hand_from_str = |s: &str| {
+        let mut hand = TileTable::default();
+        let mut ns = vec![];
+        for c in s.chars() {
+            match c {
+                '0'..='9' => ns.push(c as usize - '0' as usize),
+                _ => {
+                    let ti = ['m', 'p', 's', 'z'].iter().position(|&x| x == c).unwrap();
+                    for &ni in &ns {
+                        hand[ti][ni] += 1;
+                    }
+                    ns.clear();
+                }
+            }
+        }
+        hand
+    };
+
+    // (手牌, 向聴数, 通常形の向聴数)
+    let cases = [
+        ("123m456p789s11z222z", -1, -1), // 和了
+        ("123m456p789s1z222z", 0, 0),    // 聴牌 (単騎)
+        ("13m456p789s11z222z", 0, 0),    // 聴牌 (嵌張)
+        ("123m456p78s", 0, 0),           // 副露あり
+        ("19m19p19s1234567z", 0, 8),     // 国士無双聴牌
+        ("1122m3344p5566s7z", 0, 3),     // 七対子聴牌
+        ("159m159p159s1234z", 3, 8),
+        ("147m258p369s1234z", 6, 8),
+    ];
+    for &(s, shanten, shanten_normal) in &cases {
+        let hand = hand_from_str(s);
+        assert_eq!(calc_shanten(&hand), shanten, "{}", s);
+        assert_eq!(calc_shanten_normal(&hand), shanten_normal, "{}", s);
+    }
+
+    let hand = hand_from_str("23m456p789s111z22z");
+    let tiles = calc_effective_tiles(&hand);
+    assert_eq!(tiles, vec![Tile(TM, 1), Tile(TM, 4)]);
+}
+
+#[test]
+fn test_row_patterns_cache() {
+    use rand::prelude::*;
+
+    // 枝刈り,キャッシュなしで全ての分解を列挙した通常形の向聴数 (比較用)
+    fn enumerate(
+        tr: &mut TileRow,
+        ni: Tnum,
+        is_suit: bool,
+        mtp: (usize, usize, usize),
+        res: &mut Vec<(usize, usize, usize)>,
+    ) {
+        let i = match (ni..TNUM).find(|&i| tr[i] > 0) {
+            Some(i) => i,
+            None => {
+                res.push(mtp);
+                return;
+            }
+        };
+        let (m, t, p) = mtp;
+        let mut next = |tr: &mut TileRow, ns: &[usize], mtp| {
+            for &n in ns {
+                tr[n] -= 1;
+            }
+            enumerate(tr, i, is_suit, mtp, res);
+            for &n in ns {
+                tr[n] += 1;
+            }
+        };
+        if tr[i] >= 3 {
+            next(tr, &[i, i, i], (m + 1, t, p));
+        }
+        if is_suit && i + 2 < TNUM && tr[i + 1] > 0 && tr[i + 2] > 0 {
+            next(tr, &[i, i + 1, i + 2], (m + 1, t, p));
+        }
+        if tr[i] >= 2 && p == 0 {
+            next(tr, &[i, i], (m, t, p + 1));
+        }
+        if tr[i] >= 2 {
+            next(tr, &[i, i], (m, t + 1, p));
+        }
+        if is_suit && i + 1 < TNUM && tr[i + 1] > 0 {
+            next(tr, &[i, i + 1], (m, t + 1, p));
+        }
+        if is_suit && i + 2 < TNUM && tr[i + 2] > 0 {
+            next(tr, &[i, i + 2], (m, t + 1, p));
+        }
+        next(tr, &[i], (m, t, p));
+    }
+    let calc_reference = |hand: &TileTable| {
+        let n_set = count_hand_tiles(hand) / 3;
+        let mut totals = vec![(0, 0, 0)];
+        for (ti, row) in hand.iter().enumerate() {
+            let mut tr = *row;
+            tr[0] = 0;
+            let mut patterns = vec![];
+            enumerate(&mut tr, 1, ti != TZ, (0, 0, 0), &mut patterns);
+            let mut next = vec![];
+            for &(m0, t0, p0) in &totals {
+                for &(m1, t1, p1) in &patterns {
+                    if p0 + p1 <= 1 {
+                        next.push((m0 + m1, t0 + t1, p0 + p1));
+                    }
+                }
+            }
+            totals = next;
+        }
+        totals
+            .iter()
+            .map(|&(m, t, p)| {
+                let m = std::cmp::min(m, n_set);
+                let t = std::cmp::min(t, n_set - m);
+                2 * (n_set - m) as i32 - t as i32 - p as i32
+            })
+            .min()
+            .unwrap()
+    };
+
+    // 乱数で生成した手牌(13枚, 14枚)で枝刈り,キャッシュありの結果と比較
+    let mut wall = vec![];
+    for ti in 0..TYPE {
+        for ni in 1..TNUM {
+            if ti != TZ || ni <= DR {
+                wall.extend([Tile(ti, ni); TILE]);
+            }
+        }
+    }
+    let mut rng: StdRng = SeedableRng::seed_from_u64(0);
+    for i in 0..2000 {
+        wall.shuffle(&mut rng);
+        let mut hand = TileTable::default();
+        for t in &wall[..13 + i % 2] {
+            hand[t.0][t.1] += 1;
+        }
+        let expected = calc_reference(&hand);
+        assert_eq!(calc_shanten_normal(&hand), expected, "{:?}", hand);
+        assert_eq!(calc_shanten_normal(&hand), expected, "{:?}", hand); // キャッシュ使用
+    }
+}
@@ -1,4 +1,5 @@
 use super::parse::parse_into_chiitoitsu_win;
+use super::win_table;
 use crate::model::*;
 
 // [完成形判定 (面子, 雀頭)]
@@ -26,6 +27,13 @@ pub fn is_sets_pair(tr: &TileRow, ti: Type) -> bool {
 
 // 面子のみで構成されているかの判定
 pub fn is_sets(tr: &TileRow, ti: Type) -> bool {
+    match win_table::is_sets(tr, ti) {
+        Some(b) => b,
+        None => is_sets_nocache(tr, ti),
+    }
+}
+
+fn is_sets_nocache(tr: &TileRow, ti: Type) -> bool {
     let (mut n0, mut n1, mut n2);
     n0 = tr[1];
     n1 = tr[2];
@@ -62,6 +70,13 @@ pub fn calc_pair_candidate_index(tr: &TileRow) -> Vec<Tnum> {
 // 牌種が完成面子+雀頭のみで構成されている場合,雀頭のリストを返す.
 // 基本的に1つだが,3113,3111113のような形の場合2つ
 pub fn calc_pair_candidate(tr: &TileRow, ti: usize) -> Vec<Tile> {
+    match win_table::calc_pairs(tr, ti) {
+        Some(v) => v,
+        None => calc_pair_candidate_nocache(tr, ti),
+    }
+}
+
+fn calc_pair_candidate_nocache(tr: &TileRow, ti: usize) -> Vec<Tile> {
     // 雀頭候補それぞれについて外してみた結果が完成面子になっているかをチェック
     let mut tr = tr.clone();
     let mut res = vec![];
@@ -70,7 +85,7 @@ pub fn calc_pair_candidate(tr: &TileRow, ti: usize) -> Vec<Tile> {
             continue;
         }
         tr[ni] -= 2;
-        if is_sets(&tr, ti) {
+        if is_sets_nocache(&tr, ti) {
             res.push(Tile(ti, ni));
         }
         tr[ni] += 2;
@@ -161,14 +176,7 @@ pub fn calc_tiles_to_normal_win(hand: &TileTable) -> Vec<Tile> {
         for i in 0..2 {
             let (ti0, ti1) = (ti_mod2[i], ti_mod2[1 - i]);
             if is_sets_pair(&hand[ti0], ti0) {
-                let mut tr = hand[ti1].clone();
-                for ni in 1..TNUM {
-                    tr[ni] += 1;
-                    if is_sets(&tr, ti1) {
-                        res.push(Tile(ti1, ni));
-                    }
-                    tr[ni] -= 1;
-                }
+                res.append(&mut calc_wait_tiles(&hand[ti1], ti1, false));
             }
         }
     }
@@ -176,14 +184,7 @@ pub fn calc_tiles_to_normal_win(hand: &TileTable) -> Vec<Tile> {
         // 雀頭候補が1つの牌種のみの場合
         for ti in 0..TYPE {
             if mods[ti] == 1 {
-                let mut tr = hand[ti].clone();
-                for ni in 1..TNUM {
-                    tr[ni] += 1;
-                    if is_sets_pair(&tr, ti) {
-                        res.push(Tile(ti, ni));
-                    }
-                    tr[ni] -= 1;
-                }
+                res.append(&mut calc_wait_tiles(&hand[ti], ti, true));
             } else {
                 if !is_sets(&hand[ti], ti) {
                     return vec![];
@@ -195,6 +196,32 @@ pub fn calc_tiles_to_normal_win(hand: &TileTable) -> Vec<Tile> {
     res
 }
 
+// 1枚加えると面子のみ(with_pair=true: 雀頭+面子)で構成される牌のリスト
+fn calc_wait_tiles(tr: &TileRow, ti: Type, with_pair: bool) -> Vec<Tile> {
+    let waits = win_table::calc_wait_tiles(tr, ti, with_pair);
+    let mut tr = *tr;
+    let mut res = vec![];
+    for ni in 1..TNUM {
+        let is_win = match &waits {
+            Some(v) if win_table::can_add_tile(&tr, ti, ni) => v.contains(&Tile(ti, ni)),
+            _ => {
+                tr[ni] += 1;
+                let b = if with_pair {
+                    !calc_pair_candidate_nocache(&tr, ti).is_empty()
+                } else {
+                    is_sets_nocache(&tr, ti)
+                };
+                tr[ni] -= 1;
+                b
+            }
+        };
+        if is_win {
+            res.push(Tile(ti, ni));
+        }
+    }
+    res
+}
+
 // 七対子
 pub fn calc_tiles_to_chiitoitsu_win(hand: &TileTable) -> Vec<Tile> {
     let mut res = vec![];
@@ -424,3 +451,45 @@ fn discards_with_red5(
     }
     res
 }
+
+#[test]
+fn test_win_table() {
+    // 各牌の枚数が0~4枚, 合計13枚以下のすべての形でテーブルを用いない場合と比較
+    let mut tr = TileRow::default();
+    for key in 0..5usize.pow(9) {
+        let mut k = key;
+        for ni in (1..TNUM).rev() {
+            tr[ni] = k % 5;
+            k /= 5;
+        }
+        if tr.iter().sum::<usize>() > 13 {
+            continue;
+        }
+        for &ti in &[TM, TZ] {
+            assert_eq!(is_sets(&tr, ti), is_sets_nocache(&tr, ti), "{:?}", tr);
+            assert_eq!(
+                calc_pair_candidate(&tr, ti),
+                calc_pair_candidate_nocache(&tr, ti),
+                "{:?}",
+                tr
+            );
+            for &with_pair in &[false, true] {
+                let mut wins = vec![];
+                let mut tr2 = tr;
+                for ni in 1..TNUM {
+                    tr2[ni] += 1;
+                    let b = if with_pair {
+                        !calc_pair_candidate_nocache(&tr2, ti).is_empty()
+                    } else {
+                        is_sets_nocache(&tr2, ti)
+                    };
+                    if b {
+                        wins.push(Tile(ti, ni));
+                    }
+                    tr2[ni] -= 1;
+                }
+                assert_eq!(calc_wait_tiles(&tr, ti, with_pair), wins, "{:?}", tr);
+            }
+        }
+    }
+}
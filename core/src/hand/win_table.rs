@@ -0,0 +1,190 @@
+use std::sync::OnceLock;
+
+use crate::model::*;
+
+// [和了形テーブル]
+// 数牌の牌種ごとの枚数(1~9の各0~4枚)を5進数のキーとして完成形の判定結果を事前計算
+// 手牌は最大14枚なので枚数の合計が14枚以下の形のみを対象とする
+// 字牌は順子がないため枚数を3で割った余りのみで判定する (テーブル不要)
+//
+// エントリのビット構成
+//   bit 0        : 面子のみで構成されている
+//   bit 1~9      : bit(ni)の牌を雀頭として外すと面子のみで構成される
+//   bit 10~18    : bit(9+ni)の牌を1枚加えると面子のみで構成される
+//   bit 19~27    : bit(18+ni)の牌を1枚加えると雀頭+面子で構成される
+
+const MAX_COUNT: usize = 4;
+const MAX_TILE: usize = 14;
+const TABLE_SIZE: usize = 1953125; // 5^9
+
+const SETS: u32 = 1;
+const PAIR_SHIFT: usize = 0;
+const WAIT_SETS_SHIFT: usize = 9;
+const WAIT_SETS_PAIR_SHIFT: usize = 18;
+
+static SUIT_TABLE: OnceLock<Vec<u32>> = OnceLock::new();
+
+// 牌種の判定結果を返却 テーブルの対象外の形の場合はNone
+fn get_entry(tr: &TileRow, ti: Type) -> Option<u32> {
+    if ti == TZ {
+        return Some(calc_honor_entry(tr));
+    }
+    let key = calc_key(tr)?;
+    Some(SUIT_TABLE.get_or_init(build_suit_table)[key])
+}
+
+// 面子のみで構成されているかの判定
+pub fn is_sets(tr: &TileRow, ti: Type) -> Option<bool> {
+    get_entry(tr, ti).map(|e| e & SETS != 0)
+}
+
+// 雀頭として外すと面子のみで構成される牌の一覧
+pub fn calc_pairs(tr: &TileRow, ti: Type) -> Option<Vec<Tile>> {
+    get_entry(tr, ti).map(|e| bits_to_tiles(e, PAIR_SHIFT, ti))
+}
+
+// 1枚加えると面子のみ(with_pair=true: 雀頭+面子)で構成される牌の一覧
+// 加えた結果がテーブルの対象外となる牌(数牌の5枚目)は含まれないので呼び出し側で判定する
+pub fn calc_wait_tiles(tr: &TileRow, ti: Type, with_pair: bool) -> Option<Vec<Tile>> {
+    if ti != TZ && tr[1..TNUM].iter().sum::<usize>() >= MAX_TILE {
+        return None;
+    }
+    let shift = if with_pair {
+        WAIT_SETS_PAIR_SHIFT
+    } else {
+        WAIT_SETS_SHIFT
+    };
+    get_entry(tr, ti).map(|e| bits_to_tiles(e, shift, ti))
+}
+
+// 牌を1枚加えた形がテーブルの対象か
+pub fn can_add_tile(tr: &TileRow, ti: Type, ni: Tnum) -> bool {
+    ti == TZ || tr[ni] < MAX_COUNT
+}
+
+fn bits_to_tiles(e: u32, shift: usize, ti: Type) -> Vec<Tile> {
+    let mut res = vec![];
+    for ni in 1..TNUM {
+        if e & 1 << (shift + ni) != 0 {
+            res.push(Tile(ti, ni));
+        }
+    }
+    res
+}
+
+fn calc_key(tr: &TileRow) -> Option<usize> {
+    let mut key = 0;
+    let mut sum = 0;
+    for &n in tr.iter().skip(1) {
+        if n > MAX_COUNT {
+            return None;
+        }
+        key = key * 5 + n;
+        sum += n;
+    }
+    if sum > MAX_TILE {
+        return None;
+    }
+    Some(key)
+}
+
+// 面子(最大4つ)の組み合わせを列挙して完成形から逆算する
+fn build_suit_table() -> Vec<u32> {
+    let mut table = vec![0; TABLE_SIZE];
+
+    // 面子のみの形
+    let mut sets = vec![];
+    add_sets(&mut sets, &mut [0; TNUM], 0, 0);
+    for tr in &sets {
+        table[calc_key(tr).unwrap()] |= SETS;
+    }
+
+    // 雀頭+面子の形
+    let mut sets_pairs = vec![];
+    for tr in &sets {
+        let mut tr = *tr;
+        for ni in 1..TNUM {
+            tr[ni] += 2;
+            if let Some(key) = calc_key(&tr) {
+                table[key] |= 1 << (PAIR_SHIFT + ni);
+                sets_pairs.push(tr);
+            }
+            tr[ni] -= 2;
+        }
+    }
+
+    // 完成形から1枚除いた形
+    for (rows, shift) in &[
+        (&sets, WAIT_SETS_SHIFT),
+        (&sets_pairs, WAIT_SETS_PAIR_SHIFT),
+    ] {
+        for tr in rows.iter() {
+            let mut tr = *tr;
+            for ni in 1..TNUM {
+                if tr[ni] > 0 {
+                    tr[ni] -= 1;
+                    table[calc_key(&tr).unwrap()] |= 1 << (shift + ni);
+                    tr[ni] += 1;
+                }
+            }
+        }
+    }
+
+    table
+}
+
+// 面子の種類 0~8: 刻子(1~9), 9~15: 順子(1~7)
+// 重複を避けるため面子の種類は昇順に追加
+fn add_sets(res: &mut Vec<TileRow>, tr: &mut TileRow, n_set: usize, min_set: usize) {
+    res.push(*tr);
+    if n_set == MAX_TILE / 3 {
+        return;
+    }
+
+    for i in min_set..16 {
+        let ns: Vec<Tnum> = if i < 9 {
+            vec![i + 1; 3]
+        } else {
+            vec![i - 8, i - 7, i - 6]
+        };
+        for &ni in &ns {
+            tr[ni] += 1;
+        }
+        if ns.iter().all(|&ni| tr[ni] <= MAX_COUNT) {
+            add_sets(res, tr, n_set + 1, i);
+        }
+        for &ni in &ns {
+            tr[ni] -= 1;
+        }
+    }
+}
+
+// 字牌: 刻子と雀頭のみなので各牌の枚数を3で割った余りで判定
+fn calc_honor_entry(tr: &TileRow) -> u32 {
+    let mut n_mod1 = 0;
+    let mut n_mod2 = 0;
+    for &n in tr.iter().skip(1) {
+        match n % 3 {
+            1 => n_mod1 += 1,
+            2 => n_mod2 += 1,
+            _ => {}
+        }
+    }
+
+    let mut e = 0;
+    if n_mod1 == 0 && n_mod2 == 0 {
+        e |= SETS;
+    }
+    for (ni, &n) in tr.iter().enumerate().skip(1) {
+        let m = n % 3;
+        if n_mod1 == 0 && n_mod2 == 1 && m == 2 {
+            e |= 1 << (PAIR_SHIFT + ni);
+            e |= 1 << (WAIT_SETS_SHIFT + ni);
+        }
+        // 1枚加えた結果, 余り2の牌がちょうど1つになる場合
+        if (n_mod1 == 1 && n_mod2 == 0 && m == 1) || (n_mod1 == 0 && n_mod2 == 2 && m == 2) {
+            e |= 1 << (WAIT_SETS_PAIR_SHIFT + ni);
+        }
+    }
+    e
+}
@@ -20,6 +20,7 @@ pub struct YakuContext {
     counts: Counts,          // 面子や牌種別のカウント
     iipeikou_count: usize,   // 一盃口, 二盃口用
     yakuhai_check: TileRow,  // 役牌面子のカウント(雀頭は含まない)
+    kuitan: bool,            // 喰いタンの有無 (ルール設定)
 }
 
 impl YakuContext {
@@ -31,6 +32,7 @@ impl YakuContext {
         seat_wind: Tnum,
         is_drawn: bool,
         yaku_flags: YakuFlags,
+        rule: &Rule,
     ) -> Self {
         let pair_tile = get_pair(&parsed_hand);
         let win_tile = win_tile.to_normal(); // 赤5は通常5に変換
@@ -52,6 +54,7 @@ impl YakuContext {
             counts,
             iipeikou_count,
             yakuhai_check,
+            kuitan: rule.kuitan,
         }
     }
 
@@ -193,7 +196,7 @@ struct Counts {
     nis: [usize; TNUM],   // tile Number Indices counts(字牌は除外)
 }
 
-// 特殊形&特殊条件の役
+/// 特殊形&特殊条件の役 (evaluate_handの引数)
 #[derive(Debug, Default, Clone)]
 pub struct YakuFlags {
     pub menzentsumo: bool,
@@ -436,6 +439,9 @@ fn is_tanyaochuu(ctx: &YakuContext) -> bool {
     if ctx.parsed_hand.is_empty() {
         return false; // 国士対策
     }
+    if ctx.is_open && !ctx.kuitan {
+        return false;
+    }
 
     for SetPair(tp, t) in &ctx.parsed_hand {
         match tp {
@@ -0,0 +1,42 @@
+//! 麻雀の卓の状態管理, 和了判定・点数計算, Bot(Actor)の実行を行うライブラリ
+//!
+//! 主なモジュール
+//! - [`model`] - 牌, 卓情報([`model::Stage`]), 操作([`model::Action`]), イベント([`model::Event`]), ルール設定
+//! - [`hand`] - 向聴数, 有効牌, 和了判定, 役・点数計算([`hand::evaluate_hand`])
+//! - [`controller`] - [`controller::Actor`], [`controller::Listener`] traitと卓の状態を更新する[`controller::StageController`]
+//! - [`actor`] - 組み込みのActor([`actor::create_actor`]で名前から生成)
+//! - [`listener`] - 組み込みのListener (牌譜の出力, 集計など)
+//! - [`convert`] - 牌譜形式(mjai, 天鳳)との相互変換, 機械学習用の特徴量
+//! - [`app`] - コマンドラインの各モード, 試合の実行([`app::MahjongEngine`]), 強化学習用の環境([`app::MahjongEnv`])
+//!
+//! cargo feature `python` を有効にするとPython用のmoduleをビルドできます (README参照)
+//!
+//! ```
+//! use mahjong::hand::calc_shanten;
+//! use mahjong::model::*;
+//!
+//! // 123m 456p 789s 11z 23z (1向聴)
+//! let mut hand = TileTable::default();
+//! for &(ti, ni) in &[
+//!     (TM, 1), (TM, 2), (TM, 3), (TP, 4), (TP, 5), (TP, 6), (TS, 7),
+//!     (TS, 8), (TS, 9), (TZ, 1), (TZ, 1), (TZ, 2), (TZ, 3),
+//! ] {
+//!     hand[ti][ni] += 1;
+//! }
+//! assert_eq!(calc_shanten(&hand), 1);
+//! hand[TZ][3] -= 1;
+//! hand[TZ][2] += 1;
+//! assert_eq!(calc_shanten(&hand), 0);
+//! ```
+
+pub mod actor;
+pub mod app;
+pub mod controller;
+pub mod convert;
+pub mod hand;
+pub mod listener;
+pub mod model;
+pub mod util;
+
+#[cfg(feature = "python")]
+mod python;
@@ -58,8 +58,17 @@ impl Listener for StagePrinter {
                 self.print_score_change(&stg, &e.points);
                 println!("{}", stg);
             }
-            Event::End(_) => {
+            Event::End(e) => {
                 println!("[End]");
+                for s in 0..stg.get_seat_count() {
+                    println!(
+                        "{}: {:5} ({}位) {:+.1}",
+                        s,
+                        stg.players[s].score,
+                        e.ranks[s] + 1,
+                        e.final_points[s] as f64 / 1000.0
+                    );
+                }
             }
         }
     }
@@ -1,6 +1,7 @@
 use serde_json::{json, Value};
 
 use crate::controller::Listener;
+use crate::convert::mjai::MjaiSerializer;
 use crate::convert::tenhou::{TenhouLog, TenhouSerializer};
 use crate::model::*;
 use crate::util::common::*;
@@ -97,3 +98,43 @@ impl Listener for TenhouEventWriter {
         }
     }
 }
+
+// [MjaiEventWriter]
+// mjai形式の牌譜を出力 (局終了ごとに対戦開始からの全イベントを上書き)
+#[derive(Debug)]
+pub struct MjaiEventWriter {
+    start_time: u64,
+    serializer: MjaiSerializer,
+}
+
+impl MjaiEventWriter {
+    pub fn new(names: [String; SEAT]) -> Self {
+        Self {
+            start_time: unixtime_now(),
+            serializer: MjaiSerializer::new(names),
+        }
+    }
+}
+
+impl Listener for MjaiEventWriter {
+    fn notify_event(&mut self, stg: &Stage, event: &Event) {
+        let mut write = false;
+        match event {
+            Event::Begin(_) => {
+                self.start_time = unixtime_now();
+            }
+            Event::Win(_) | Event::Draw(_) | Event::End(_) => {
+                write = true;
+            }
+            _ => {}
+        }
+
+        self.serializer.push_event(stg, event);
+        if write {
+            write_to_file(
+                &format!("data/{}.mjson", self.start_time),
+                &self.serializer.serialize(),
+            );
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use std::sync::{Arc, Mutex};
+
+use crate::controller::{calc_possible_call_actions, calc_possible_turn_actions, Listener};
+use crate::convert::feature::FeatureDataset;
+use crate::model::*;
+
+// [FeatureCollector]
+// Eventの列から各座席の(選択時の卓情報, 選択可能な操作, 選択した操作)を復元してdatasetに追加
+// Actorの選択を直接参照しないので,自己対戦と牌譜の読み込みの両方で使用できる
+// 選択肢が1つしかない場合(リーチ中のツモ切りなど)や,他家の操作により選択が確定しない場合
+// (ポンとチーの競合,頭ハネなど)は追加しない
+// 鳴き,ロンの選択待ちの(卓情報, [(座席, 選択可能な操作)])
+type CallChoices = (Stage, Vec<(Seat, Vec<Action>)>);
+
+#[derive(Debug)]
+pub struct FeatureCollector {
+    dataset: Arc<Mutex<FeatureDataset>>,
+    melding: Option<Action>, // 直前の鳴き (ツモ番の操作の判定用)
+    turn: Option<(Seat, Stage, Vec<Action>)>, // ツモ番の操作の選択待ち
+    call: Option<CallChoices>, // 鳴き,ロンの選択待ち
+}
+
+impl FeatureCollector {
+    pub fn new(dataset: Arc<Mutex<FeatureDataset>>) -> Self {
+        Self {
+            dataset: dataset,
+            melding: None,
+            turn: None,
+            call: None,
+        }
+    }
+
+    fn set_turn(&mut self, stg: &Stage) {
+        let acts = calc_possible_turn_actions(stg, &self.melding);
+        self.turn = Some((stg.turn, stg.clone(), acts));
+    }
+
+    fn set_call(&mut self, stg: &Stage, can_meld: bool) {
+        let acts_list = calc_possible_call_actions(stg, can_meld);
+        let mut seats = vec![];
+        for (s, acts) in acts_list.iter().enumerate().take(stg.get_seat_count()) {
+            if acts.len() > 1 {
+                seats.push((s, acts.clone()));
+            }
+        }
+        if !seats.is_empty() {
+            self.call = Some((stg.clone(), seats));
+        }
+    }
+
+    // ツモ番の操作の確定
+    fn resolve_turn(&mut self, seat: Seat, act: Action) {
+        if let Some((s, stg, acts)) = self.turn.take() {
+            if s == seat {
+                self.push(&stg, s, &acts, &act);
+            }
+        }
+    }
+
+    // 鳴き,ロンの操作の確定 selectedに含まれない座席はf(acts)がtrueの場合にスキップとして追加
+    fn resolve_call(&mut self, selected: &[(Seat, Action)], f: impl Fn(&Vec<Action>) -> bool) {
+        if let Some((stg, seats)) = self.call.take() {
+            for (s, acts) in &seats {
+                match selected.iter().find(|(s2, _)| s2 == s) {
+                    Some((_, act)) => self.push(&stg, *s, acts, act),
+                    None => {
+                        if f(acts) {
+                            self.push(&stg, *s, acts, &Action::nop());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn push(&self, stg: &Stage, seat: Seat, acts: &Vec<Action>, act: &Action) {
+        self.dataset.lock().unwrap().push(stg, seat, acts, act);
+    }
+}
+
+impl Listener for FeatureCollector {
+    fn notify_event(&mut self, stg: &Stage, event: &Event) {
+        use ActionType::*;
+        match event {
+            Event::Begin(_) => {}
+            Event::New(_) => {
+                self.melding = None;
+                self.call = None;
+                self.set_turn(stg);
+            }
+            Event::Deal(_) => {
+                self.resolve_call(&[], |_| true);
+                self.set_turn(stg);
+            }
+            Event::Discard(e) => {
+                let act = if e.is_riichi {
+                    Action::riichi(e.tile)
+                } else {
+                    Action::discard(e.tile)
+                };
+                self.resolve_turn(e.seat, act);
+                self.melding = None;
+                self.set_call(stg, true);
+            }
+            Event::Meld(e) => {
+                let tp = match e.meld_type {
+                    MeldType::Chi => Chi,
+                    MeldType::Pon => Pon,
+                    MeldType::Minkan => Minkan,
+                    MeldType::Ankan => Ankan,
+                    MeldType::Kakan => Kakan,
+                };
+                let act = Action(tp, e.consumed.clone());
+                match tp {
+                    Chi => self.resolve_call(&[(e.seat, act.clone())], |_| true),
+                    Pon | Minkan => {
+                        // チーが可能だった座席は選択が確定しない
+                        let sel = [(e.seat, act.clone())];
+                        self.resolve_call(&sel, |acts| !acts.iter().any(|a| a.0 == Chi));
+                    }
+                    _ => self.resolve_turn(e.seat, act.clone()),
+                }
+                self.melding = Some(act);
+                match tp {
+                    Chi | Pon => self.set_turn(stg),
+                    Ankan | Kakan => self.set_call(stg, false), // 槍槓
+                    _ => {}
+                }
+            }
+            Event::Kita(e) => {
+                self.resolve_turn(e.seat, Action::kita());
+                self.melding = Some(Action::kita());
+                self.set_call(stg, false);
+            }
+            Event::Dora(_) => {}
+            Event::Win(e) => {
+                if self.call.is_some() {
+                    let sel: Vec<(Seat, Action)> =
+                        e.contexts.iter().map(|c| (c.0, Action::ron())).collect();
+                    self.resolve_call(&sel, |_| false);
+                } else if let Some(c) = e.contexts.first() {
+                    self.resolve_turn(c.0, Action::tsumo());
+                }
+                self.turn = None;
+            }
+            Event::Draw(e) => {
+                if e.type_ == DrawType::Kyushukyuhai {
+                    self.resolve_turn(stg.turn, Action::kyushukyuhai());
+                }
+                self.resolve_call(&[], |_| true);
+                self.turn = None;
+            }
+            Event::End(_) => {
+                self.turn = None;
+                self.call = None;
+            }
+        }
+    }
+}
@@ -1,9 +1,13 @@
 mod event_printer;
 mod event_sender;
 mod event_writer;
+mod feature_collector;
 mod prompt;
+mod statistics;
 
 pub use event_printer::{StageDebugPrinter, StagePrinter, StageStepPrinter};
 pub use event_sender::{EventSender, StageSender};
-pub use event_writer::{EventWriter, TenhouEventWriter};
+pub use event_writer::{EventWriter, MjaiEventWriter, TenhouEventWriter};
+pub use feature_collector::FeatureCollector;
 pub use prompt::Prompt;
+pub use statistics::{print_stats_table, ActorStats, GameStats, StatsCollector};
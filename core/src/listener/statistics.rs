@@ -0,0 +1,404 @@
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Value};
+
+use crate::controller::Listener;
+use crate::model::*;
+
+// [StatsCollector]
+// 1試合分の各座席の成績
+#[derive(Debug, Default, Clone)]
+pub struct SeatStats {
+    pub kyoku: u32,         // 局数
+    pub win: u32,           // 和了回数
+    pub win_point: i64,     // 和了点の合計 (本場,供託を含まない)
+    pub deal_in: u32,       // 放銃回数
+    pub deal_in_point: i64, // 放銃点の合計 (本場,供託を含まない)
+    pub riichi: u32,        // リーチした局数
+    pub call: u32,          // 副露(暗槓を除く)した局数
+    pub score: Score,       // 終局時の得点
+    pub rank: usize,        // 終局時の順位 (0~3)
+    pub final_point: Point, // 最終ポイント (返し点との差,ウマ,オカを含む)
+}
+
+pub type GameStats = [SeatStats; SEAT];
+
+// Eventから各座席の成績を集計
+// 試合終了後にstatsから結果を取得する
+#[derive(Debug)]
+pub struct StatsCollector {
+    stats: Arc<Mutex<GameStats>>,
+}
+
+impl StatsCollector {
+    pub fn new(stats: Arc<Mutex<GameStats>>) -> Self {
+        Self { stats }
+    }
+}
+
+impl Listener for StatsCollector {
+    fn notify_event(&mut self, stg: &Stage, event: &Event) {
+        let n = stg.get_seat_count();
+        let st = &mut *self.stats.lock().unwrap();
+        match event {
+            Event::Begin(_) => {
+                *st = GameStats::default();
+            }
+            Event::New(_) => {
+                for x in st.iter_mut().take(n) {
+                    x.kyoku += 1;
+                }
+            }
+            Event::Win(e) => {
+                let mut is_deal_in = false;
+                for (s, _, ctx) in &e.contexts {
+                    st[*s].win += 1;
+                    st[*s].win_point += ctx.points.0 as i64;
+                    if stg.turn != *s {
+                        // ロン (ダブロンの場合も放銃回数は1回)
+                        is_deal_in = true;
+                        st[stg.turn].deal_in_point += ctx.points.0 as i64;
+                    }
+                }
+                if is_deal_in {
+                    st[stg.turn].deal_in += 1;
+                }
+                update_kyoku_end(st, stg);
+            }
+            Event::Draw(_) => {
+                update_kyoku_end(st, stg);
+            }
+            Event::End(e) => {
+                for (s, x) in st.iter_mut().enumerate().take(n) {
+                    x.score = stg.players[s].score;
+                    x.rank = e.ranks[s];
+                    x.final_point = e.final_points[s];
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn update_kyoku_end(st: &mut GameStats, stg: &Stage) {
+    for (x, pl) in st.iter_mut().zip(&stg.players).take(stg.get_seat_count()) {
+        if pl.riichi.is_some() {
+            x.riichi += 1;
+        }
+        if pl.melds.iter().any(|m| m.type_ != MeldType::Ankan) {
+            x.call += 1;
+        }
+    }
+}
+
+// [ActorStats]
+// 複数試合のActorの成績の集計
+#[derive(Debug, Default, Clone)]
+pub struct ActorStats {
+    game: u32,
+    kyoku: u32,
+    win: u32,
+    win_point: i64,
+    deal_in: u32,
+    deal_in_point: i64,
+    riichi: u32,
+    call: u32,
+    ranks: [u32; SEAT], // 各順位の回数
+    tobi: u32,          // 終局時の得点が0未満
+    rank_sum: f64,      // 順位(1~4)の合計
+    rank_sum2: f64,     // 順位の2乗の合計 (標準誤差用)
+    delta_sum: f64,     // 得点の増減の合計
+    delta_sum2: f64,    // 得点の増減の2乗の合計 (標準誤差用)
+    point_sum: f64,     // 最終ポイントの合計
+    point_sum2: f64,    // 最終ポイントの2乗の合計 (標準誤差用)
+    // デュプリケートの場合の牌山(シード値)ごとの成績
+    // 同じ牌山の試合は独立ではないので, 標準誤差は牌山ごとの平均値のばらつきから計算する
+    groups: Vec<ActorStats>,
+}
+
+impl ActorStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, st: &SeatStats, initial_score: Score) {
+        let rank = (st.rank + 1) as f64;
+        let delta = (st.score - initial_score) as f64;
+        self.game += 1;
+        self.kyoku += st.kyoku;
+        self.win += st.win;
+        self.win_point += st.win_point;
+        self.deal_in += st.deal_in;
+        self.deal_in_point += st.deal_in_point;
+        self.riichi += st.riichi;
+        self.call += st.call;
+        self.ranks[st.rank] += 1;
+        if st.score < 0 {
+            self.tobi += 1;
+        }
+        self.rank_sum += rank;
+        self.rank_sum2 += rank * rank;
+        self.delta_sum += delta;
+        self.delta_sum2 += delta * delta;
+        let point = st.final_point as f64;
+        self.point_sum += point;
+        self.point_sum2 += point * point;
+    }
+
+    // デュプリケートの試合の成績を追加 (group: 牌山ごとの通し番号)
+    pub fn add_dup(&mut self, group: usize, st: &SeatStats, initial_score: Score) {
+        self.add(st, initial_score);
+        if self.groups.len() <= group {
+            self.groups.resize_with(group + 1, ActorStats::new);
+        }
+        self.groups[group].add(st, initial_score);
+    }
+
+    // デュプリケートの場合は平均値はそのままで, 標準誤差を牌山ごとの平均値から計算
+    fn estimate(&self, f: impl Fn(&ActorStats) -> Estimate) -> Estimate {
+        let e = f(self);
+        if self.groups.is_empty() {
+            return e;
+        }
+        let (mut sum, mut sum2, mut n) = (0.0, 0.0, 0);
+        for g in self.groups.iter().filter(|g| g.game > 0) {
+            let m = f(g).mean;
+            sum += m;
+            sum2 += m * m;
+            n += 1;
+        }
+        Estimate {
+            mean: e.mean,
+            se: Estimate::from_sums(sum, sum2, n).se,
+        }
+    }
+
+    pub fn avg_rank(&self) -> Estimate {
+        self.estimate(|st| Estimate::from_sums(st.rank_sum, st.rank_sum2, st.game))
+    }
+
+    pub fn avg_score_delta(&self) -> Estimate {
+        self.estimate(|st| Estimate::from_sums(st.delta_sum, st.delta_sum2, st.game))
+    }
+
+    pub fn avg_final_point(&self) -> Estimate {
+        self.estimate(|st| Estimate::from_sums(st.point_sum, st.point_sum2, st.game))
+    }
+
+    pub fn rank_rate(&self, rank: usize) -> Estimate {
+        self.estimate(|st| Estimate::from_count(st.ranks[rank], st.game))
+    }
+
+    pub fn tobi_rate(&self) -> Estimate {
+        self.estimate(|st| Estimate::from_count(st.tobi, st.game))
+    }
+
+    pub fn win_rate(&self) -> Estimate {
+        self.estimate(|st| Estimate::from_count(st.win, st.kyoku))
+    }
+
+    pub fn deal_in_rate(&self) -> Estimate {
+        self.estimate(|st| Estimate::from_count(st.deal_in, st.kyoku))
+    }
+
+    pub fn riichi_rate(&self) -> Estimate {
+        self.estimate(|st| Estimate::from_count(st.riichi, st.kyoku))
+    }
+
+    pub fn call_rate(&self) -> Estimate {
+        self.estimate(|st| Estimate::from_count(st.call, st.kyoku))
+    }
+
+    pub fn avg_win_point(&self) -> f64 {
+        div(self.win_point as f64, self.win)
+    }
+
+    pub fn avg_deal_in_point(&self) -> f64 {
+        div(self.deal_in_point as f64, self.deal_in)
+    }
+
+    pub fn to_json(&self, name: &str, n_seat: usize) -> Value {
+        json!({
+            "name": name,
+            "game": self.game,
+            "kyoku": self.kyoku,
+            "avg_rank": self.avg_rank().to_json(),
+            "avg_score_delta": self.avg_score_delta().to_json(),
+            "avg_final_point": self.avg_final_point().to_json(),
+            "rank_rates": (0..n_seat).map(|r| self.rank_rate(r).to_json()).collect::<Vec<Value>>(),
+            "tobi_rate": self.tobi_rate().to_json(),
+            "win_rate": self.win_rate().to_json(),
+            "deal_in_rate": self.deal_in_rate().to_json(),
+            "riichi_rate": self.riichi_rate().to_json(),
+            "call_rate": self.call_rate().to_json(),
+            "avg_win_point": self.avg_win_point(),
+            "avg_deal_in_point": self.avg_deal_in_point(),
+        })
+    }
+}
+
+// 成績の一覧を表形式で出力 (±は95%信頼区間, デュプリケートの場合は牌山ごとの平均値から計算)
+pub fn print_stats_table(names: &[String], stats: &[ActorStats], n_seat: usize) {
+    let pct = |e: Estimate| format!("{:5.2}±{:4.2}", e.mean * 100.0, e.ci95() * 100.0);
+    print!(
+        "{:4} {:>6} {:>11} {:>13} {:>11}",
+        "", "game", "avg_rank", "score_delta", "final_point"
+    );
+    for r in 0..n_seat {
+        print!(" {:>10}", format!("rank{}(%)", r + 1));
+    }
+    println!(
+        " {:>10} {:>10} {:>10} {:>10} {:>10} {:>8} {:>8}",
+        "tobi(%)", "win(%)", "deal_in(%)", "riichi(%)", "call(%)", "win_pt", "deal_pt"
+    );
+
+    for (i, st) in stats.iter().enumerate() {
+        let ar = st.avg_rank();
+        let sd = st.avg_score_delta();
+        let fp = st.avg_final_point();
+        print!(
+            "ac{:<2} {:6} {:11} {:13} {:11}",
+            i,
+            st.game,
+            format!("{:.3}±{:.3}", ar.mean, ar.ci95()),
+            format!("{:.0}±{:.0}", sd.mean, sd.ci95()),
+            format!("{:.1}±{:.1}", fp.mean / 1000.0, fp.ci95() / 1000.0),
+        );
+        for r in 0..n_seat {
+            print!(" {:>10}", pct(st.rank_rate(r)));
+        }
+        println!(
+            " {:>10} {:>10} {:>10} {:>10} {:>10} {:8.0} {:8.0}",
+            pct(st.tobi_rate()),
+            pct(st.win_rate()),
+            pct(st.deal_in_rate()),
+            pct(st.riichi_rate()),
+            pct(st.call_rate()),
+            st.avg_win_point(),
+            st.avg_deal_in_point(),
+        );
+    }
+    for (i, name) in names.iter().enumerate() {
+        println!("ac{}: {}", i, name);
+    }
+}
+
+// [Estimate]
+// 平均値(または割合)とその標準誤差
+#[derive(Debug, Clone, Copy)]
+pub struct Estimate {
+    pub mean: f64,
+    pub se: f64,
+}
+
+impl Estimate {
+    fn from_sums(sum: f64, sum2: f64, n: u32) -> Self {
+        let mean = div(sum, n);
+        let se = if n > 1 {
+            let n = n as f64;
+            let var = (sum2 - sum * sum / n) / (n - 1.0); // 不偏分散
+            (var.max(0.0) / n).sqrt()
+        } else {
+            0.0
+        };
+        Self { mean, se }
+    }
+
+    fn from_count(k: u32, n: u32) -> Self {
+        let p = div(k as f64, n);
+        let se = if n > 0 {
+            (p * (1.0 - p) / n as f64).sqrt()
+        } else {
+            0.0
+        };
+        Self { mean: p, se }
+    }
+
+    // 95%信頼区間の幅(片側)
+    pub fn ci95(&self) -> f64 {
+        1.96 * self.se
+    }
+
+    pub fn to_json(self) -> Value {
+        json!({
+            "mean": self.mean,
+            "se": self.se,
+            "ci95": [self.mean - self.ci95(), self.mean + self.ci95()],
+        })
+    }
+}
+
+fn div(a: f64, n: u32) -> f64 {
+    if n == 0 {
+        0.0
+    } else {
+        a / n as f64
+    }
+}
+
+#[test]
+fn test_actor_stats() {
+    let mut st = ActorStats::new();
+    for (score, rank) in [(45000, 0), (30000, 1), (20000, 2), (-5000, 3)] {
+        let mut s = SeatStats::default();
+        s.kyoku = 5;
+        s.win = 1;
+        s.win_point = 8000;
+        s.score = score;
+        s.rank = rank;
+        s.final_point = score - 30000;
+        st.add(&s, 25000);
+    }
+
+    let ar = st.avg_rank();
+    assert_eq!(ar.mean, 2.5);
+    assert!((ar.se - (5.0f64 / 3.0 / 4.0).sqrt()).abs() < 1e-9);
+    assert_eq!(st.avg_score_delta().mean, -2500.0);
+    assert_eq!(st.avg_final_point().mean, -7500.0);
+    assert_eq!(st.rank_rate(0).mean, 0.25);
+    assert_eq!(st.tobi_rate().mean, 0.25);
+    assert_eq!(st.win_rate().mean, 0.2);
+    assert_eq!(st.avg_win_point(), 8000.0);
+    assert_eq!(st.avg_deal_in_point(), 0.0);
+}
+
+#[test]
+fn test_actor_stats_dup() {
+    // 同じ牌山で座席を入れ替えた4試合で順位が1~4位に分かれる場合,
+    // 試合単位では順位がばらつくが牌山ごとの平均は常に2.5なので標準誤差は0
+    let mut st = ActorStats::new();
+    let mut st_nodup = ActorStats::new();
+    for group in 0..3 {
+        for rank in 0..4 {
+            let mut s = SeatStats::default();
+            s.kyoku = 4;
+            s.win = if rank == 0 { 2 } else { 0 };
+            s.score = 40000 - rank as Score * 10000;
+            s.rank = rank;
+            st.add_dup(group, &s, 25000);
+            st_nodup.add(&s, 25000);
+        }
+    }
+
+    let ar = st.avg_rank();
+    assert_eq!(ar.mean, 2.5);
+    assert_eq!(ar.se, 0.0);
+    assert!(st_nodup.avg_rank().se > 0.0);
+    assert_eq!(st.avg_score_delta().mean, 0.0);
+    assert_eq!(st.avg_score_delta().se, 0.0);
+    assert_eq!(st.rank_rate(0).mean, 0.25);
+    assert_eq!(st.rank_rate(0).se, 0.0);
+    assert_eq!(st.win_rate().mean, 0.125);
+    assert_eq!(st.win_rate().se, 0.0);
+
+    // 牌山ごとの平均がばらつく場合は牌山の数で標準誤差を計算
+    let mut st = ActorStats::new();
+    for (group, rank) in [(0, 0), (0, 0), (1, 3), (1, 3)] {
+        let mut s = SeatStats::default();
+        s.rank = rank;
+        st.add_dup(group, &s, 25000);
+    }
+    let ar = st.avg_rank();
+    assert_eq!(ar.mean, 2.5);
+    assert!((ar.se - 1.5).abs() < 1e-9); // 平均1と4の2グループ: sqrt(4.5 / 2)
+}
@@ -1,11 +1,4 @@
-mod actor;
-mod app;
-mod controller;
-mod convert;
-mod hand;
-mod listener;
-mod model;
-mod util;
+use mahjong::{app, error};
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
@@ -24,14 +17,26 @@ fn main() {
             // Engine (bot対戦シミュレーションモード)
             app::EngineApp::new(args2).run();
         }
+        "F" => {
+            // Feature (機械学習用の特徴量出力モード)
+            app::FeatureApp::new(args2).run();
+        }
         "J" => {
             // Jantama (雀魂botモード)
             app::MahjongsoulApp::new(args2).run();
         }
+        "M" => {
+            // Mjai client (mjaiサーバ接続モード)
+            app::MjaiClientApp::new(args2).run();
+        }
         "R" => {
             // Replay (牌譜リプレイモード)
             app::ReplayApp::new(args2).run();
         }
+        "T" => {
+            // Tournament (複数Actorの総当たり対戦・レーティングモード)
+            app::TournamentApp::new(args2).run();
+        }
         m => {
             error!("unknown mode: {}", m)
         }
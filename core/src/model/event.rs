@@ -1,5 +1,7 @@
 use super::*;
 
+/// 卓の状態を変化させるイベント
+/// StageController::handle_eventで卓情報に反映され,ActorとListenerに通知される
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum Event {
@@ -29,6 +31,7 @@ impl Event {
         scores: [Score; SEAT],
         hands: [Vec<Tile>; SEAT],
         mode: usize,
+        rule: Rule,
     ) -> Self {
         Self::New(EventNew {
             bakaze,
@@ -39,6 +42,7 @@ impl Event {
             scores,
             hands,
             mode,
+            rule,
         })
     }
 
@@ -92,8 +96,12 @@ impl Event {
         })
     }
 
-    pub fn end() -> Self {
-        Self::End(EventEnd {})
+    pub fn end(points: [Point; SEAT], ranks: [usize; SEAT], final_points: [Point; SEAT]) -> Self {
+        Self::End(EventEnd {
+            points,
+            ranks,
+            final_points,
+        })
     }
 }
 
@@ -109,7 +117,30 @@ pub struct EventNew {
     pub doras: Vec<Tile>,
     pub scores: [Score; SEAT],
     pub hands: [Vec<Tile>; SEAT],
-    pub mode: usize, // 1: 4人東, 2: 4人南
+    pub mode: usize, // 1: 4人東, 2: 4人南, 11: 3人東, 12: 3人南
+    #[serde(default)]
+    pub rule: Rule,
+}
+
+#[cfg(test)]
+impl EventNew {
+    // 東1局0本場(親: 座席0, ドラ表示牌: 1s, 持ち点: 配給原点)の局開始イベント (テスト用)
+    // handsは各座席の手牌の文字列表現(tiles_from_string), 親は14枚で末尾がツモ牌
+    // 空文字列の座席は手牌が見えていない(ツモ牌はZ8)ものとして扱う
+    pub(crate) fn from_hands(mode: usize, hands: [&str; SEAT]) -> Self {
+        let rule = Rule::default();
+        Self {
+            bakaze: 0,
+            kyoku: 0,
+            honba: 0,
+            kyoutaku: 0,
+            doras: vec![Tile(TS, 1)],
+            scores: [rule.initial_score; SEAT],
+            hands: hands.map(|h| tiles_from_string(h).unwrap()),
+            mode,
+            rule,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -158,8 +189,13 @@ pub struct EventDraw {
     pub points: [Point; SEAT],
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct EventEnd {}
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventEnd {
+    pub points: [Point; SEAT],       // 終局時の供託の精算による得点変動
+    pub ranks: [usize; SEAT],        // 最終順位 (0~3, 同点の場合は起家に近い座席が上位)
+    pub final_points: [Point; SEAT], // 最終ポイント (返し点との差,ウマ,オカを含む 単位は点数と同じ)
+}
 
 // [DrawType]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -171,6 +207,7 @@ pub enum DrawType {
     Suuchariichi,   // 四家立直
     Sanchaho,       // 三家和
     Kouhaiheikyoku, // 荒廃平局
+    Nagashimangan,  // 流し満貫 (荒廃平局時)
 }
 
 impl fmt::Display for DrawType {
@@ -186,6 +223,7 @@ impl fmt::Display for DrawType {
                 DrawType::Suuchariichi => "四家立直",
                 DrawType::Sanchaho => "三家和",
                 DrawType::Kouhaiheikyoku => "荒廃平局",
+                DrawType::Nagashimangan => "流し満貫",
             }
         )
     }
@@ -1,4 +1,5 @@
 use super::*;
+use crate::util::common::vec_count;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MeldType {
@@ -9,7 +10,7 @@ pub enum MeldType {
     Ankan,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Meld {
     pub step: usize,
     pub seat: Seat,
@@ -25,3 +26,77 @@ impl fmt::Display for Meld {
         write!(f, "{}", s.join("|"))
     }
 }
+
+// 鳴きの文字列表現(m123, p5+55など)をパース
+// 牌の後の+は他家から鳴いた牌 (+の位置から鳴いた相手を決定), +がない4枚の同じ牌は暗槓
+pub fn meld_from_string(exp: &str, seat: Seat) -> Result<Meld, String> {
+    let undef: usize = 255;
+    let mut ti = undef;
+    let mut nis = vec![];
+    let mut from = 0;
+    let mut tiles = vec![];
+    let mut froms = vec![];
+    for c in exp.chars() {
+        match c {
+            'm' => ti = 0,
+            'p' => ti = 1,
+            's' => ti = 2,
+            'z' => ti = 3,
+            '+' => {
+                if froms.is_empty() {
+                    return Err("invalid '+' suffix".into());
+                }
+                let last = froms.len() - 1;
+                froms[last] = from % SEAT;
+            }
+            '0'..='9' => {
+                if ti == undef {
+                    return Err("tile number befor tile type".into());
+                }
+
+                from += 1;
+                let ni = c.to_digit(10).unwrap() as usize;
+                nis.push(if ni == 0 { 5 } else { ni });
+                tiles.push(Tile(ti, ni));
+                froms.push(seat);
+            }
+            _ => {
+                return Err(format!("invalid char: '{}'", c));
+            }
+        }
+    }
+
+    if nis.is_empty() {
+        return Err(format!("invalid meld: '{}'", exp));
+    }
+
+    nis.sort();
+    let mut diffs = vec![];
+    let mut ni0 = nis[0];
+    for ni in &nis[1..] {
+        diffs.push(ni - ni0);
+        ni0 = *ni;
+    }
+
+    let meld_type = if diffs.len() == 2 && vec_count(&diffs, &1) == 2 {
+        MeldType::Chi
+    } else if diffs.len() == 2 && vec_count(&diffs, &0) == 2 {
+        MeldType::Pon
+    } else if diffs.len() == 3 && vec_count(&diffs, &0) == 3 {
+        if vec_count(&froms, &seat) == 4 {
+            MeldType::Ankan
+        } else {
+            MeldType::Minkan
+        }
+    } else {
+        return Err(format!("invalid meld: '{}'", exp));
+    };
+
+    Ok(Meld {
+        step: 0,
+        seat: seat,
+        type_: meld_type,
+        tiles: tiles,
+        froms: froms,
+    })
+}
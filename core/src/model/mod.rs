@@ -5,6 +5,7 @@ mod event;
 mod kita;
 mod meld;
 mod player;
+mod rule;
 mod stage;
 mod tile;
 mod win_context;
@@ -20,6 +21,7 @@ pub use event::*;
 pub use kita::*;
 pub use meld::*;
 pub use player::*;
+pub use rule::*;
 pub use stage::*;
 pub use tile::*;
 pub use win_context::*;
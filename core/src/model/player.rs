@@ -1,18 +1,20 @@
 use super::*;
 use crate::util::common::vec_to_string;
 
-#[derive(Debug, Default, Serialize)]
+/// プレイヤー(座席)ごとの情報
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct Player {
-    pub seat: Seat,             // 座席番号(場・局が変わってもゲーム終了まで不変)
-    pub score: Score,           // 得点
-    pub hand: TileTable,        // 手牌(4x10の配列)
-    pub drawn: Option<Tile>,    // ツモ牌
-    pub melds: Vec<Meld>,       // 鳴き一覧
-    pub kitas: Vec<Kita>,       // 北抜き vecの中身はすべてTile(TZ, TN)
-    pub riichi: Option<Index>,  // リーチ宣言牌のdiscardsにおけるindex
-    pub discards: Vec<Discard>, // 捨て牌一覧
-    pub is_shown: bool,         // 手牌が見えるかどうか 見えない場合,手牌はすべてz8(=unknown)
-    pub rank: usize,            // 現在の順位
+    pub seat: Seat,                  // 座席番号(場・局が変わってもゲーム終了まで不変)
+    pub score: Score,                // 得点
+    pub hand: TileTable,             // 手牌(4x10の配列)
+    pub drawn: Option<Tile>,         // ツモ牌
+    pub melds: Vec<Meld>,            // 鳴き一覧
+    pub kitas: Vec<Kita>,            // 北抜き vecの中身はすべてTile(TZ, TN)
+    pub riichi: Option<Index>,       // リーチ宣言牌のdiscardsにおけるindex
+    pub discards: Vec<Discard>,      // 捨て牌一覧
+    pub is_shown: bool,              // 手牌が見えるかどうか 見えない場合,手牌はすべてz8(=unknown)
+    pub rank: usize,                 // 現在の順位
+    pub pao: Option<(Seat, String)>, // 責任払い(包)の対象 (役満を確定させる鳴きをさせた他家, 役名)
 
     // 聴牌
     pub win_tiles: Vec<Tile>,   // 聴牌時の和了牌
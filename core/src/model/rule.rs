@@ -0,0 +1,71 @@
+use super::*;
+
+/// ゲームのルール設定
+/// jsonファイルから読み込み可能. 省略した項目にはデフォルト値が使用される.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Rule {
+    pub initial_score: Score, // 配給原点
+    pub red5: [usize; 3],     // 赤5の枚数 (萬子, 筒子, 索子)
+    pub kuitan: bool,         // 喰いタン
+    pub kuikae: bool,         // 喰い替え (falseの場合は鳴いた牌と同じ牌, 筋の牌の打牌を禁止)
+    pub tobi: bool,           // 飛び (持ち点が0未満になったプレイヤーがいる場合に終局)
+    pub double_ron: bool,     // ダブロン (falseの場合は頭ハネ)
+    pub sanchaho: bool,       // 三家和の途中流局 (falseの場合はトリロンまたは頭ハネ)
+    pub kiriage: bool,        // 切り上げ満貫 (30符4飜, 60符3飜を満貫として扱う)
+    pub tsumo_loss: bool,     // 三麻のツモ損 (falseの場合は北家の支払い分を残りの2人で折半)
+    pub pao: bool,            // 大三元,大四喜の責任払い(包)
+    pub pao_suukantsu: bool,  // 四槓子の責任払い(包) (4つ目の槓が大明槓の場合)
+    pub extension: bool,      // 延長戦(西入) 返し点に達したプレイヤーがいない場合 (サドンデス)
+    pub agari_yame: bool,     // オーラスの親の和了止め (親がトップの場合)
+    pub tenpai_yame: bool,    // オーラスの親の聴牌止め (親がトップの場合)
+    pub kyoutaku_top: bool,   // 終局時の供託をトップが獲得 (falseの場合は消滅)
+    pub return_score: Score,  // 返し点 (オカ = (返し点 - 配給原点) * 人数)
+    pub uma: [Point; SEAT],   // 順位ウマ (4人)
+    pub uma3: [Point; 3],     // 順位ウマ (三麻)
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self {
+            initial_score: 25000,
+            red5: [1, 1, 1],
+            kuitan: true,
+            kuikae: false,
+            tobi: true,
+            double_ron: true,
+            sanchaho: false,
+            kiriage: false,
+            tsumo_loss: true,
+            pao: true,
+            pao_suukantsu: false,
+            extension: false,
+            agari_yame: false,
+            tenpai_yame: false,
+            kyoutaku_top: false,
+            return_score: 30000,
+            uma: [20000, 10000, -10000, -20000],
+            uma3: [15000, 0, -15000],
+        }
+    }
+}
+
+impl Rule {
+    pub fn from_file(file_path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(file_path).map_err(|e| e.to_string())?;
+        let rule: Self = serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+        rule.validate()?;
+        Ok(rule)
+    }
+
+    // 最終ポイントの合計が0になるようにウマの合計は0でなければならない
+    pub fn validate(&self) -> Result<(), String> {
+        if self.uma.iter().sum::<Point>() != 0 {
+            return Err(format!("sum of uma must be 0: {:?}", self.uma));
+        }
+        if self.uma3.iter().sum::<Point>() != 0 {
+            return Err(format!("sum of uma3 must be 0: {:?}", self.uma3));
+        }
+        Ok(())
+    }
+}
@@ -37,7 +37,9 @@ impl fmt::Display for TileStateType {
     }
 }
 
-#[derive(Debug, Default, Serialize)]
+/// 卓情報 (場, 牌山の残り枚数, 各プレイヤーの手牌・捨て牌・鳴き・得点など)
+/// Actorに渡される卓情報は座席ごとに他家の手牌,ツモ牌が`Z8`でマスクされている
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct Stage {
     pub bakaze: usize,                               // 場 (東:0, 南:1, 西:2, 北:3)
     pub kyoku: usize,                                // 局 (0~3 = 親のseat)
@@ -51,7 +53,8 @@ pub struct Stage {
     pub last_tile: Option<(Seat, ActionType, Tile)>, // 他家にロンされる可能性のある牌(捨て牌,槍槓) フリテン判定用
     pub last_riichi: Option<Seat>,                   // リーチがロンされずに成立した場合の供託更新用
     pub players: [Player; SEAT],                     // 各プレイヤー情報
-    pub is_3p: bool,                                 // 三麻フラグ(未実装, 常にfalse)
+    pub is_3p: bool,                                 // 三麻フラグ (座席3は使用しない)
+    pub rule: Rule,                                  // ルール設定
     pub tile_states: [[[TileStateType; TILE]; TNUM]; TYPE],
 }
 
@@ -61,6 +64,15 @@ impl Stage {
         seat == self.kyoku
     }
 
+    #[inline]
+    pub fn get_seat_count(&self) -> usize {
+        if self.is_3p {
+            3
+        } else {
+            SEAT
+        }
+    }
+
     #[inline]
     pub fn get_prevalent_wind(&self) -> Tnum {
         self.bakaze % SEAT + 1 // WE | WS | WW | WN
@@ -68,7 +80,8 @@ impl Stage {
 
     #[inline]
     pub fn get_seat_wind(&self, seat: Seat) -> Tnum {
-        (seat + SEAT - self.kyoku) % SEAT + 1 // WE | WS | WW | WN
+        let n = self.get_seat_count();
+        (seat + n - self.kyoku) % n + 1 // WE | WS | WW | WN
     }
 
     pub fn get_scores(&self) -> [Score; SEAT] {
@@ -130,17 +143,42 @@ pub fn tiles_from_tile_table(tt: &TileTable) -> Vec<Tile> {
     let mut hand = vec![];
     for ti in 0..TYPE {
         for ni in 1..TNUM {
-            // 赤5
+            // 赤5 (tt[ti][5]は赤5を含む枚数)
+            let mut n = tt[ti][ni];
             if ni == 5 {
                 for _ in 0..tt[ti][0] {
                     hand.push(Tile(ti, 0));
                 }
+                n -= tt[ti][0];
             }
 
-            for _ in 0..tt[ti][ni] {
+            for _ in 0..n {
                 hand.push(Tile(ti, ni));
             }
         }
     }
     hand
 }
+
+#[test]
+fn test_tiles_from_tile_table() {
+    // 赤5を含む手牌: tt[ti][5]は赤5を含む枚数なので赤5を二重に数えない
+    let mut tt = TileTable::default();
+    for &t in &[Tile(TM, 0), Tile(TM, 5), Tile(TP, 0), Tile(TZ, DR)] {
+        tt[t.0][t.1] += 1;
+        if t.1 == 0 {
+            tt[t.0][5] += 1;
+        }
+    }
+    let hand = tiles_from_tile_table(&tt);
+    assert_eq!(
+        hand,
+        vec![Tile(TM, 0), Tile(TM, 5), Tile(TP, 0), Tile(TZ, DR)]
+    );
+
+    // 赤5のみ
+    let mut tt = TileTable::default();
+    tt[TS][0] = 1;
+    tt[TS][5] = 1;
+    assert_eq!(tiles_from_tile_table(&tt), vec![Tile(TS, 0)]);
+}
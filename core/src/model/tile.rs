@@ -2,6 +2,7 @@ use serde::{de, ser};
 
 use super::*;
 
+/// 牌 (種別 TM|TP|TS|TZ, 数字 1~9) 赤5は数字0, 字牌は1~7(東南西北白發中)
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub struct Tile(pub Type, pub Tnum); // (type index, number index)
 
@@ -137,3 +138,29 @@ impl<'de> de::Deserialize<'de> for Tile {
         deserializer.deserialize_identifier(TileVisitor)
     }
 }
+
+// 牌の文字列表現(m123p40z1など,数字の前に種別)をパース 0は赤5
+pub fn tiles_from_string(exp: &str) -> Result<Vec<Tile>, String> {
+    let mut tiles = vec![];
+    let undef: usize = 255;
+    let mut ti = undef;
+    for c in exp.chars() {
+        match c {
+            'm' => ti = 0,
+            'p' => ti = 1,
+            's' => ti = 2,
+            'z' => ti = 3,
+            '0'..='9' => {
+                if ti == undef {
+                    return Err(format!("tile number befor tile type"));
+                }
+                let ni = c.to_digit(10).unwrap() as usize;
+                tiles.push(Tile(ti, ni));
+            }
+            _ => {
+                return Err(format!("invalid char: '{}'", c));
+            }
+        }
+    }
+    Ok(tiles)
+}
@@ -2,6 +2,7 @@ use super::*;
 
 pub type Points = (Point, Point, Point); // (ロンの支払い, ツモ・子の支払い, ツモ・親の支払い)
 
+/// 和了の結果 (役, 符, 飜, 点数)
 #[derive(Debug, Deserialize, Serialize)]
 pub struct WinContext {
     pub hand: Vec<Tile>,             // 和了手牌(鳴きは含まない)
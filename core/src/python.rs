@@ -0,0 +1,542 @@
+// [Python bindings]
+// cargo feature "python" が有効な場合のみビルドされる
+// 牌は文字列(m1, p0(赤5), z7など), 手牌は牌の文字列表現(m123p40z11など)で受け渡す
+// 卓情報(Stage), Event, 和了の結果(WinContext)はjsonと同じ構造のdictに変換する
+// 操作(Action)は(操作の種類, [牌])のtupleで表し, Pythonから渡す場合は選択可能な操作のindexも使用できる
+//
+//   import mahjong
+//   mahjong.calc_shanten("m123p456s789z1123")
+//   mahjong.evaluate_hand("m123p456s789z11222", "z2", yaku_flags=mahjong.YakuFlags(riichi=True))
+//   mahjong.run_game(1, 1, ["EfficiencyBot", my_actor, "RandomDiscard", "EfficiencyBot"])
+
+use std::sync::{Arc, Mutex};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use serde::Serialize;
+
+use crate::actor::{fallback_action, is_valid_action, try_create_actor};
+use crate::app::{calc_final_points, MahjongEngine, MahjongEnv, Observation};
+use crate::controller::*;
+use crate::hand;
+use crate::model::*;
+
+use crate::error;
+
+#[pymodule]
+fn mahjong(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(parse_tiles, m)?)?;
+    m.add_function(wrap_pyfunction!(calc_shanten, m)?)?;
+    m.add_function(wrap_pyfunction!(calc_effective_tiles, m)?)?;
+    m.add_function(wrap_pyfunction!(calc_discards_with_effective_tiles, m)?)?;
+    m.add_function(wrap_pyfunction!(calc_win_tiles, m)?)?;
+    m.add_function(wrap_pyfunction!(is_win, m)?)?;
+    m.add_function(wrap_pyfunction!(evaluate_hand, m)?)?;
+    m.add_function(wrap_pyfunction!(run_game, m)?)?;
+    m.add_class::<PyYakuFlags>()?;
+    m.add_class::<PyEnv>()?;
+    Ok(())
+}
+
+fn value_error(e: String) -> PyErr {
+    PyValueError::new_err(e)
+}
+
+fn tile_to_string(t: &Tile) -> String {
+    t.to_string()
+}
+
+fn tiles_to_strings(tiles: &[Tile]) -> Vec<String> {
+    tiles.iter().map(tile_to_string).collect()
+}
+
+fn parse_tile(exp: &str) -> PyResult<Tile> {
+    let tiles = tiles_from_string(exp).map_err(value_error)?;
+    if tiles.len() != 1 {
+        return Err(value_error(format!("invalid tile: '{}'", exp)));
+    }
+    Ok(tiles[0])
+}
+
+// 赤5は通常の5としても数える
+fn parse_hand(exp: &str) -> PyResult<TileTable> {
+    let mut hand = TileTable::default();
+    for t in tiles_from_string(exp).map_err(value_error)? {
+        hand[t.0][t.1] += 1;
+        if t.1 == 0 {
+            hand[t.0][5] += 1;
+        }
+    }
+    Ok(hand)
+}
+
+// Rustの値をjson経由でPythonのオブジェクト(dict, listなど)に変換
+fn to_py<'py, T: Serialize>(py: Python<'py>, v: &T) -> PyResult<Bound<'py, PyAny>> {
+    let s = serde_json::to_string(v).map_err(|e| value_error(e.to_string()))?;
+    py.import("json")?.call_method1("loads", (s,))
+}
+
+fn rule_from_py(rule: Option<&Bound<'_, PyAny>>) -> PyResult<Rule> {
+    match rule {
+        None => Ok(Rule::default()),
+        Some(r) => {
+            let s: String = r
+                .py()
+                .import("json")?
+                .call_method1("dumps", (r,))?
+                .extract()?;
+            let rule: Rule = serde_json::from_str(&s).map_err(|e| value_error(e.to_string()))?;
+            rule.validate().map_err(value_error)?;
+            Ok(rule)
+        }
+    }
+}
+
+// Pythonの操作をActionに変換 (選択可能な操作のindex または (操作の種類, [牌]))
+fn action_from_py(obj: &Bound<'_, PyAny>, acts: &[Action]) -> PyResult<Action> {
+    if let Ok(i) = obj.extract::<usize>() {
+        return acts
+            .get(i)
+            .cloned()
+            .ok_or_else(|| value_error(format!("action index out of range: {}", i)));
+    }
+    let (tp, tiles): (String, Vec<String>) = obj.extract()?;
+    let tp: ActionType = serde_json::from_value(serde_json::Value::String(tp))
+        .map_err(|e| value_error(e.to_string()))?;
+    let tiles = tiles
+        .iter()
+        .map(|t| parse_tile(t))
+        .collect::<PyResult<Vec<Tile>>>()?;
+    Ok(Action(tp, tiles))
+}
+
+fn actions_to_py<'py>(py: Python<'py>, acts: &Vec<Action>) -> PyResult<Bound<'py, PyAny>> {
+    to_py(py, acts)
+}
+
+/// 牌の文字列表現を牌のリストに変換
+#[pyfunction]
+#[pyo3(name = "tiles_from_string")]
+fn parse_tiles(exp: &str) -> PyResult<Vec<String>> {
+    let tiles = tiles_from_string(exp).map_err(value_error)?;
+    Ok(tiles_to_strings(&tiles))
+}
+
+/// 向聴数 (通常形, 七対子, 国士無双のうち最小, 聴牌: 0, 和了: -1)
+#[pyfunction]
+fn calc_shanten(hand: &str) -> PyResult<i32> {
+    Ok(hand::calc_shanten(&parse_hand(hand)?))
+}
+
+/// 3n+1枚の手牌の有効牌
+#[pyfunction]
+fn calc_effective_tiles(hand: &str) -> PyResult<Vec<String>> {
+    Ok(tiles_to_strings(&hand::calc_effective_tiles(&parse_hand(
+        hand,
+    )?)))
+}
+
+/// 3n+2枚の手牌の打牌候補ごとの(打牌, 打牌後の向聴数, 有効牌)
+#[pyfunction]
+fn calc_discards_with_effective_tiles(hand: &str) -> PyResult<Vec<(String, i32, Vec<String>)>> {
+    let res = hand::calc_discards_with_effective_tiles(&parse_hand(hand)?);
+    Ok(res
+        .iter()
+        .map(|(t, s, ts)| (tile_to_string(t), *s, tiles_to_strings(ts)))
+        .collect())
+}
+
+/// 3n+1枚の手牌の和了牌 (聴牌していない場合は空)
+#[pyfunction]
+fn calc_win_tiles(hand: &str) -> PyResult<Vec<String>> {
+    let hand = parse_hand(hand)?;
+    let mut tiles = hand::calc_tiles_to_normal_win(&hand);
+    tiles.extend(hand::calc_tiles_to_chiitoitsu_win(&hand));
+    tiles.extend(hand::calc_tiles_to_kokushimusou_win(&hand));
+    tiles.sort();
+    tiles.dedup();
+    Ok(tiles_to_strings(&tiles))
+}
+
+/// 3n+2枚の手牌が和了形か (役の有無は判定しない)
+#[pyfunction]
+fn is_win(hand: &str) -> PyResult<bool> {
+    let hand = parse_hand(hand)?;
+    Ok(hand::is_normal_win(&hand)
+        || hand::is_chiitoitsu_win(&hand)
+        || hand::is_kokushimusou_win(&hand))
+}
+
+/// 特殊形&特殊条件の役
+#[pyclass(name = "YakuFlags", get_all, set_all, skip_from_py_object)]
+#[derive(Debug, Clone, Default)]
+struct PyYakuFlags {
+    menzentsumo: bool,
+    riichi: bool,
+    dabururiichi: bool,
+    ippatsu: bool,
+    haiteiraoyue: bool,
+    houteiraoyui: bool,
+    rinshankaihou: bool,
+    chankan: bool,
+    tenhou: bool,
+    tiihou: bool,
+}
+
+#[pymethods]
+impl PyYakuFlags {
+    #[new]
+    #[pyo3(signature = (*, menzentsumo=false, riichi=false, dabururiichi=false, ippatsu=false,
+        haiteiraoyue=false, houteiraoyui=false, rinshankaihou=false, chankan=false, tenhou=false,
+        tiihou=false))]
+    #[allow(clippy::too_many_arguments)] // Pythonのキーワード引数
+    fn new(
+        menzentsumo: bool,
+        riichi: bool,
+        dabururiichi: bool,
+        ippatsu: bool,
+        haiteiraoyue: bool,
+        houteiraoyui: bool,
+        rinshankaihou: bool,
+        chankan: bool,
+        tenhou: bool,
+        tiihou: bool,
+    ) -> Self {
+        Self {
+            menzentsumo,
+            riichi,
+            dabururiichi,
+            ippatsu,
+            haiteiraoyue,
+            houteiraoyui,
+            rinshankaihou,
+            chankan,
+            tenhou,
+            tiihou,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+impl From<&PyYakuFlags> for hand::YakuFlags {
+    fn from(f: &PyYakuFlags) -> Self {
+        Self {
+            menzentsumo: f.menzentsumo,
+            riichi: f.riichi,
+            dabururiichi: f.dabururiichi,
+            ippatsu: f.ippatsu,
+            haiteiraoyue: f.haiteiraoyue,
+            houteiraoyui: f.houteiraoyui,
+            rinshankaihou: f.rinshankaihou,
+            chankan: f.chankan,
+            tenhou: f.tenhou,
+            tiihou: f.tiihou,
+        }
+    }
+}
+
+/// 役と点数の計算 (CalculatorAppと同じ)
+/// hand: 和了牌を含む手牌(鳴きは含まない), melds: 鳴きの文字列表現のリスト(m123, p5+55など)
+/// 門前のツモ和了の場合は門前清自摸和を自動的に付与する
+/// 和了形の場合は和了の結果(dict), 和了形でない場合はNoneを返却
+#[pyfunction]
+#[pyo3(signature = (hand, win_tile, melds=vec![], doras="", ura_doras="", is_drawn=true,
+    is_dealer=false, prevalent_wind=1, seat_wind=2, yaku_flags=None, n_kita=0, rule=None))]
+#[allow(clippy::too_many_arguments)] // Pythonのキーワード引数
+fn evaluate_hand<'py>(
+    py: Python<'py>,
+    hand: &str,
+    win_tile: &str,
+    melds: Vec<String>,
+    doras: &str,
+    ura_doras: &str,
+    is_drawn: bool,
+    is_dealer: bool,
+    prevalent_wind: usize,
+    seat_wind: usize,
+    yaku_flags: Option<PyRef<'py, PyYakuFlags>>,
+    n_kita: usize,
+    rule: Option<&Bound<'py, PyAny>>,
+) -> PyResult<Option<Bound<'py, PyAny>>> {
+    let hand_table = parse_hand(hand)?;
+    let win_tile = parse_tile(win_tile)?;
+    if hand_table[win_tile.0][win_tile.1] == 0 {
+        return Err(value_error(format!(
+            "win tile {} is not contained in hand",
+            win_tile
+        )));
+    }
+    let melds = melds
+        .iter()
+        .map(|m| meld_from_string(m, 0).map_err(value_error))
+        .collect::<PyResult<Vec<Meld>>>()?;
+    let doras = tiles_from_string(doras).map_err(value_error)?;
+    let ura_doras = tiles_from_string(ura_doras).map_err(value_error)?;
+    let mut yaku_flags = match &yaku_flags {
+        Some(f) => hand::YakuFlags::from(&**f),
+        None => hand::YakuFlags::default(),
+    };
+    if is_drawn && melds.iter().all(|m| m.type_ == MeldType::Ankan) {
+        yaku_flags.menzentsumo = true;
+    }
+    let rule = rule_from_py(rule)?;
+
+    match hand::evaluate_hand(
+        &hand_table,
+        &melds,
+        n_kita,
+        &doras,
+        &ura_doras,
+        win_tile,
+        is_drawn,
+        is_dealer,
+        prevalent_wind,
+        seat_wind,
+        &yaku_flags,
+        &rule,
+    ) {
+        Some(ctx) => Ok(Some(to_py(py, &ctx)?)),
+        None => Ok(None),
+    }
+}
+
+// Pythonのオブジェクトを操作するActor
+// select_action(stage, actions)メソッドを持つオブジェクト, または同じ引数の関数を指定する
+// notify_event(stage, event)メソッドがある場合はEventを通知する
+// 例外が発生した場合は代替の操作(ツモ切り,スキップ)を行い,試合終了後に例外を送出する
+struct PyActor {
+    config: Config,
+    seat: Seat,
+    obj: Py<PyAny>,
+    error: Arc<Mutex<Option<PyErr>>>,
+}
+
+impl Clone for PyActor {
+    fn clone(&self) -> Self {
+        Python::attach(|py| Self {
+            config: self.config.clone(),
+            seat: self.seat,
+            obj: self.obj.clone_ref(py),
+            error: self.error.clone(),
+        })
+    }
+}
+
+impl PyActor {
+    fn new(obj: Py<PyAny>, error: Arc<Mutex<Option<PyErr>>>) -> Self {
+        Self {
+            config: Config {
+                name: "Python".to_string(),
+                args: vec![],
+            },
+            seat: NO_SEAT,
+            obj: obj,
+            error: error,
+        }
+    }
+
+    fn set_error(&self, e: PyErr) {
+        let mut error = self.error.lock().unwrap();
+        if error.is_none() {
+            *error = Some(e);
+        }
+    }
+
+    fn call_select_action(&self, py: Python, stg: &Stage, acts: &Vec<Action>) -> PyResult<Action> {
+        let obj = self.obj.bind(py);
+        let args = (to_py(py, stg)?, actions_to_py(py, acts)?);
+        let res = if obj.hasattr("select_action")? {
+            obj.call_method1("select_action", args)?
+        } else {
+            obj.call1(args)?
+        };
+        let act = action_from_py(&res, acts)?;
+        if !is_valid_action(stg, self.seat, acts, &act) {
+            return Err(value_error(format!(
+                "action={:?} is not contained in actions={:?}",
+                act, acts
+            )));
+        }
+        Ok(act)
+    }
+}
+
+impl Actor for PyActor {
+    fn init(&mut self, seat: Seat) {
+        self.seat = seat;
+    }
+
+    fn select_action(&mut self, stg: &Stage, acts: &Vec<Action>) -> Action {
+        if self.error.lock().unwrap().is_none() {
+            match Python::attach(|py| self.call_select_action(py, stg, acts)) {
+                Ok(act) => return act,
+                Err(e) => self.set_error(e),
+            }
+        }
+        fallback_action(stg, self.seat, acts).unwrap_or_else(|e| {
+            error!("{}", e);
+            Action::nop()
+        })
+    }
+
+    fn get_config(&self) -> &Config {
+        &self.config
+    }
+}
+
+impl Listener for PyActor {
+    fn notify_event(&mut self, stg: &Stage, event: &Event) {
+        if self.error.lock().unwrap().is_some() {
+            return;
+        }
+        let res = Python::attach(|py| -> PyResult<()> {
+            let obj = self.obj.bind(py);
+            if obj.hasattr("notify_event")? {
+                obj.call_method1("notify_event", (to_py(py, stg)?, to_py(py, event)?))?;
+            }
+            Ok(())
+        });
+        if let Err(e) = res {
+            self.set_error(e);
+        }
+    }
+}
+
+// 座席ごとのActor(Actorの名前 または Pythonのオブジェクト)
+// 三麻(mode > 10)の場合は3つでもよい (座席3はNull)
+fn actors_from_py(
+    mode: usize,
+    actors: Vec<Bound<'_, PyAny>>,
+    error: &Arc<Mutex<Option<PyErr>>>,
+) -> PyResult<[Box<dyn Actor>; SEAT]> {
+    let n_seat = if mode > 10 { 3 } else { SEAT };
+    if actors.len() != SEAT && actors.len() != n_seat {
+        return Err(value_error(format!("{} actors are required", n_seat)));
+    }
+
+    let mut res = [0, 1, 2, 3].map(|_| try_create_actor("Null").unwrap());
+    for (s, a) in actors.iter().enumerate() {
+        res[s] = match a.extract::<String>() {
+            Ok(name) => try_create_actor(&name).map_err(value_error)?,
+            Err(_) => Box::new(PyActor::new(a.clone().unbind(), error.clone())),
+        };
+    }
+    if mode > 10 {
+        res[3] = try_create_actor("Null").unwrap(); // 三麻は座席3を使用しない
+    }
+    Ok(res)
+}
+
+/// 1試合を実行して終局時の得点,順位,最終ポイントを返却
+/// mode: 1: 東風戦, 2: 半荘戦, 4: 一荘戦 (三麻の場合は+10)
+#[pyfunction]
+#[pyo3(signature = (seed, mode, actors, rule=None))]
+fn run_game<'py>(
+    py: Python<'py>,
+    seed: u64,
+    mode: usize,
+    actors: Vec<Bound<'py, PyAny>>,
+    rule: Option<&Bound<'py, PyAny>>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let rule = rule_from_py(rule)?;
+    let error = Arc::new(Mutex::new(None));
+    let actors = actors_from_py(mode, actors, &error)?;
+
+    let mut game = MahjongEngine::new(seed, mode, &rule, actors, vec![]);
+    game.run();
+    if let Some(e) = error.lock().unwrap().take() {
+        return Err(e);
+    }
+
+    let stg = game.get_stage();
+    let n_seat = stg.get_seat_count();
+    let scores = stg.get_scores();
+    let ranks = [0, 1, 2, 3].map(|s| stg.players[s].rank);
+    let final_points = calc_final_points(&scores, &ranks, n_seat, &rule);
+    let res = serde_json::json!({
+        "seed": seed,
+        "scores": scores[..n_seat],
+        "ranks": ranks[..n_seat],
+        "final_points": final_points[..n_seat],
+    });
+    to_py(py, &res)
+}
+
+/// 強化学習用の環境 (MahjongEnvのラッパー)
+///   env = mahjong.Env(1, 0, ["Null", "EfficiencyBot", "EfficiencyBot", "EfficiencyBot"])
+///   obs = env.reset(seed)
+///   obs, reward, done = env.step(action)
+/// 対戦相手のActorで発生した例外はstepで送出する
+// 試合は別スレッドで実行されるので, 対戦相手のPythonのActorが呼び出せるように待機中はGILを解放する
+#[pyclass(name = "Env", unsendable)]
+struct PyEnv {
+    env: MahjongEnv,
+    error: Arc<Mutex<Option<PyErr>>>,
+}
+
+#[pymethods]
+impl PyEnv {
+    /// actors: 学習対象以外の座席のActorの名前 (学習対象の座席の要素は使用しない)
+    #[new]
+    #[pyo3(signature = (mode, seat, actors, rule=None))]
+    fn new(
+        mode: usize,
+        seat: Seat,
+        actors: Vec<Bound<'_, PyAny>>,
+        rule: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<Self> {
+        let rule = rule_from_py(rule)?;
+        let error = Arc::new(Mutex::new(None));
+        let actors = actors_from_py(mode, actors, &error)?;
+        let env = MahjongEnv::new(mode, &rule, seat, actors).map_err(value_error)?;
+        Ok(Self {
+            env: env,
+            error: error,
+        })
+    }
+
+    fn reset<'py>(&mut self, py: Python<'py>, seed: u64) -> PyResult<Bound<'py, PyAny>> {
+        let env = &mut self.env;
+        let obs = py.detach(|| env.reset(seed));
+        self.raise_error()?;
+        observation_to_py(py, &obs)
+    }
+
+    fn legal_actions<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        actions_to_py(py, &self.env.legal_actions())
+    }
+
+    /// action: 選択可能な操作のindex または (操作の種類, [牌])
+    fn step<'py>(
+        &mut self,
+        py: Python<'py>,
+        action: &Bound<'py, PyAny>,
+    ) -> PyResult<(Bound<'py, PyAny>, f32, bool)> {
+        let act = action_from_py(action, &self.env.legal_actions())?;
+        let env = &mut self.env;
+        let (obs, reward, done) = py.detach(|| env.step(&act)).map_err(value_error)?;
+        self.raise_error()?;
+        Ok((observation_to_py(py, &obs)?, reward, done))
+    }
+}
+
+impl PyEnv {
+    fn raise_error(&self) -> PyResult<()> {
+        match self.error.lock().unwrap().take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+fn observation_to_py<'py>(py: Python<'py>, obs: &Observation) -> PyResult<Bound<'py, PyAny>> {
+    let res = serde_json::json!({
+        "seat": obs.seat,
+        "stage": obs.stage,
+        "legal_actions": obs.legal_actions,
+    });
+    to_py(py, &res)
+}
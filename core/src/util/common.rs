@@ -6,6 +6,7 @@ use std::{fmt, fs, io};
 use serde_json::Value;
 
 use crate::error;
+use crate::model::{Score, SEAT};
 
 pub fn next_value<T>(it: &mut std::slice::Iter<std::string::String>, opt: &str) -> T
 where
@@ -131,6 +132,17 @@ pub fn rank_by_rank_vec<T: Ord + Clone>(v: &Vec<T>) -> Vec<usize> {
     res
 }
 
+// 得点から各座席の順位(0~)を計算 (同点の場合は座席番号が小さい方が上位)
+// 三麻の場合など,座席n以降の順位は座席番号と同じ値
+pub fn calc_ranks(scores: &[Score; SEAT], n: usize) -> [usize; SEAT] {
+    let ranks = rank_by_rank_vec(&scores[..n].to_vec());
+    let mut res = [0; SEAT];
+    for s in 0..SEAT {
+        res[s] = if s < n { ranks[s] } else { s };
+    }
+    res
+}
+
 pub fn as_usize(v: &Value) -> usize {
     v.as_i64().unwrap() as usize
 }
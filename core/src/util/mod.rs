@@ -1,5 +1,4 @@
 pub mod common;
 pub mod log;
 pub mod server;
-pub mod test;
 pub mod variant;
@@ -0,0 +1,48 @@
+#!/bin/bash
+# 自己対戦のthroughput(games/sec)を計測
+# 使用法: script/bench.sh [REV...]
+#   REVを省略した場合は作業ツリーを,指定した場合は各リビジョンをgit worktreeに展開してビルド・計測
+#   各設定をREPEAT回(既定5回)実行して中央値を出力
+set -eu
+
+REPEAT=${REPEAT:-5}
+ROOT=$(cd "$(dirname "$0")/.." && pwd)
+
+# 東風戦,1スレッド,牌山のシード固定
+BENCHES=(
+    "E -s 1 -m 1 -g 100 -t 1 -0 EfficiencyBot -1 EfficiencyBot -2 EfficiencyBot -3 EfficiencyBot"
+    "E -s 1 -m 1 -g 1000 -t 1 -0 RandomDiscard -1 RandomDiscard -2 RandomDiscard -3 RandomDiscard"
+)
+
+# $1: Cargo.tomlのあるディレクトリ
+run_benches() {
+    if ! log=$(cd "$1" && cargo build --release -q 2>&1); then
+        echo "$log" >&2
+        exit 1
+    fi
+    for args in "${BENCHES[@]}"; do
+        # 古いリビジョンはthroughputを出力しないため,試合数と合計の実行時間から計算
+        n_game=$(echo "$args" | sed 's/.*-g \([0-9]*\).*/\1/')
+        results=$(for _ in $(seq "$REPEAT"); do
+            # shellcheck disable=SC2086
+            "$1/target/release/mahjong" $args 2>&1 |
+                awk -v n="$n_game" '/^total elapsed time:/ { printf "%.1f\n", n / $4 }'
+        done | sort -n)
+        median=$(echo "$results" | sed -n "$(((REPEAT + 1) / 2))p")
+        echo "$args"
+        echo "    $median games/sec (median of $REPEAT)"
+    done
+}
+
+if [ $# -eq 0 ]; then
+    run_benches "$ROOT/core"
+    exit 0
+fi
+
+for rev in "$@"; do
+    dir=$(mktemp -d)
+    git -C "$ROOT" worktree add -q --detach "$dir" "$rev"
+    echo "== $rev ($(git -C "$ROOT" rev-parse --short "$rev"))"
+    run_benches "$dir/core"
+    git -C "$ROOT" worktree remove --force "$dir"
+done